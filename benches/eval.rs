@@ -0,0 +1,86 @@
+//! Compares `Executor::execute` with the step by step `Executor::steps` on long expressions
+//!
+//! Run with `cargo bench`, a size can be given like `cargo bench -- 2000`
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use kalc_kman::{Context, Executor, Lexer};
+
+/// Makes a formula of about this many operators
+type Generator = fn(usize) -> String;
+
+/// `1 + 2 + 3 + ...`
+fn sum(len: usize) -> String {
+    (1..=len)
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// `(1 * 2) + (3 / 4) - ...`, every operator in its own group
+fn groups(len: usize) -> String {
+    let operators = ["+", "-", "*", "/"];
+    (1..=len)
+        .map(|i| format!("({i} {} {})", operators[i % 4], i % 7 + 1))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// `sum([1, 2, ...]) + if(1 < 2, sqrt(4), 0) + ...`
+fn mixed(len: usize) -> String {
+    let list: Vec<String> = (1..=len / 4).map(|i| i.to_string()).collect();
+    let parts: Vec<String> = (0..len / 4)
+        .map(|i| format!("if({i} < {}, sqrt({}), 0)", i + 1, i * i))
+        .collect();
+    format!("sum([{}]) + {}", list.join(", "), parts.join(" + "))
+}
+
+fn executor(formula: &str) -> Executor {
+    let mut lexer = Lexer::default();
+    lexer.parse(formula).unwrap();
+    Executor::with_context(lexer, Context::default())
+}
+
+/// The fastest of a few runs, the executors are made before the time is measured
+fn measure(formula: &str, run: impl Fn(&mut Executor)) -> Duration {
+    (0..5)
+        .map(|_| {
+            let mut executor = executor(formula);
+            let start = Instant::now();
+            run(black_box(&mut executor));
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    // `cargo bench` gives `--bench`
+    let sizes: Vec<usize> = match std::env::args().skip(1).find_map(|arg| arg.parse().ok()) {
+        Some(size) => vec![size],
+        None => vec![100, 500, 1000],
+    };
+    println!(
+        "{:<8} {:>6} {:>14} {:>14} {:>9}",
+        "formula", "size", "execute", "steps", "speedup"
+    );
+    let formulas: [(&str, Generator); 3] = [("sum", sum), ("groups", groups), ("mixed", mixed)];
+    for (name, formula) in formulas {
+        for &size in &sizes {
+            let formula = formula(size);
+            let direct = measure(&formula, |executor| executor.execute().unwrap());
+            let steps = measure(&formula, |executor| {
+                black_box(executor.steps().unwrap());
+            });
+            println!(
+                "{name:<8} {size:>6} {:>14?} {:>14?} {:>8.1}x",
+                direct,
+                steps,
+                steps.as_secs_f64() / direct.as_secs_f64()
+            );
+        }
+    }
+}
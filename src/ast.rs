@@ -0,0 +1,315 @@
+use std::fmt::Display;
+
+use crate::{
+    context::Context,
+    executor::Executor,
+    function::Function,
+    token::{Binary, Token},
+    value::Value,
+};
+
+/// The characters of the formula that a node or a lexeme comes from, the end is not included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// From the start of this span to the end of the other one
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+    Sin,
+    Cos,
+    Sqrt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    /// `.*` element by element
+    ElemMul,
+    Div,
+    Pow,
+    /// `a ± b`
+    PlusMinus,
+    /// `(3h 20min) in min` or `255 to hex`
+    In,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl UnaryOp {
+    /// The token of the executor with this operator
+    pub(crate) fn token(self) -> fn(Box<Token>) -> Token {
+        match self {
+            UnaryOp::Neg => Token::Neg,
+            UnaryOp::Not => Token::Not,
+            UnaryOp::BitNot => Token::BitNot,
+            UnaryOp::Sin => Token::Sin,
+            UnaryOp::Cos => Token::Cos,
+            UnaryOp::Sqrt => Token::Sqrt,
+        }
+    }
+}
+
+impl BinaryOp {
+    /// The token of the executor with this operator
+    pub(crate) fn token(self) -> Binary {
+        match self {
+            BinaryOp::Add => Token::Add,
+            BinaryOp::Sub => Token::Sub,
+            BinaryOp::Mul => Token::Mul,
+            BinaryOp::ElemMul => Token::ElemMul,
+            BinaryOp::Div => Token::Div,
+            BinaryOp::Pow => Token::Pow,
+            BinaryOp::PlusMinus => Token::PlusMinus,
+            BinaryOp::In => Token::In,
+            BinaryOp::BitAnd => Token::BitAnd,
+            BinaryOp::BitOr => Token::BitOr,
+            BinaryOp::BitXor => Token::BitXor,
+            BinaryOp::Shl => Token::Shl,
+            BinaryOp::Shr => Token::Shr,
+            BinaryOp::Eq => Token::Eq,
+            BinaryOp::Ne => Token::Ne,
+            BinaryOp::Lt => Token::Lt,
+            BinaryOp::Le => Token::Le,
+            BinaryOp::Gt => Token::Gt,
+            BinaryOp::Ge => Token::Ge,
+            BinaryOp::And => Token::And,
+            BinaryOp::Or => Token::Or,
+        }
+    }
+}
+
+/// A node of the syntax tree of a formula, made by `kalc_kman::parse`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    /// A number or another value that is written in the formula, like `2.5`, `45 days` or `hex`
+    Value(Value),
+    Var(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `if(condition, a, b)` or `condition ? a : b`
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    List(Vec<Expr>),
+    /// `1..10 step 2`, without a step the step is 1
+    Range(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    /// `x -> x * 2`
+    Lambda(String, Box<Expr>),
+    /// `a = b`, only used by functions like `solve(x * x = 2, x)`
+    Equation(Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+    /// The parentheses, the whole formula is a group too
+    Group(Vec<Expr>),
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// The nodes inside of this node, in the order of the formula
+    pub fn children(&self) -> Vec<&Expr> {
+        match &self.kind {
+            ExprKind::Value(_) | ExprKind::Var(_) => Vec::new(),
+            ExprKind::Unary(_, a) | ExprKind::Lambda(_, a) => vec![a],
+            ExprKind::Binary(_, a, b) | ExprKind::Equation(a, b) => vec![a, b],
+            ExprKind::If(a, b, c) => vec![a, b, c],
+            ExprKind::Range(a, b, step) => {
+                let mut children = vec![a.as_ref(), b.as_ref()];
+                children.extend(step.as_deref());
+                children
+            }
+            ExprKind::List(exprs) | ExprKind::Call(_, exprs) | ExprKind::Group(exprs) => {
+                exprs.iter().collect()
+            }
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match &mut self.kind {
+            ExprKind::Value(_) | ExprKind::Var(_) => Vec::new(),
+            ExprKind::Unary(_, a) | ExprKind::Lambda(_, a) => vec![a],
+            ExprKind::Binary(_, a, b) | ExprKind::Equation(a, b) => vec![a, b],
+            ExprKind::If(a, b, c) => vec![a, b, c],
+            ExprKind::Range(a, b, step) => {
+                let mut children = vec![a.as_mut(), b.as_mut()];
+                children.extend(step.as_deref_mut());
+                children
+            }
+            ExprKind::List(exprs) | ExprKind::Call(_, exprs) | ExprKind::Group(exprs) => {
+                exprs.iter_mut().collect()
+            }
+        }
+    }
+
+    /// Every node, a node before the nodes inside of it
+    pub fn walk(&self, visit: &mut impl FnMut(&Expr)) {
+        visit(self);
+        for child in self.children() {
+            child.walk(visit)
+        }
+    }
+
+    /// Every node can be changed, the nodes inside of a node are changed first
+    pub fn transform(&mut self, change: &mut impl FnMut(&mut Expr)) {
+        for child in self.children_mut() {
+            child.transform(change)
+        }
+        change(self)
+    }
+
+    /// Calculates the expression, the variables need to be replaced before
+    pub fn eval(&self, context: &Context) -> Result<Value, String> {
+        Value::try_from(Executor::evaluate(Token::from(self), context)?)
+    }
+
+    /// The simplified derivative by the variable, the spans of the result are empty
+    pub fn derivative(&self, var: &str) -> Result<Expr, String> {
+        Token::from(self)
+            .derivative(var)
+            .map(|token| Expr::from(&token))
+    }
+}
+
+/// The formula, it can be parsed again to the same formula
+impl Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // the parser puts the whole formula in parentheses
+        let mut token = match Token::from(self) {
+            Token::Group(mut tokens) if tokens.len() == 1 => tokens.remove(0),
+            token => token,
+        };
+        literals(&mut token);
+        write!(f, "{token}")
+    }
+}
+
+/// The floats are written with a `.` or an exponent, `2.0 / 4` is not the integer
+/// division `2 / 4` and `1e21` is too long for an integer. The parentheses around a
+/// single number are not written, they would be one more group every time
+fn literals(token: &mut Token) {
+    match token {
+        Token::F(num) if num.is_finite() => *token = Token::Var(format!("{num:?}")),
+        Token::Group(tokens)
+            if tokens.len() == 1
+                && (tokens[0].children().is_empty() || matches!(tokens[0], Token::Group(_))) =>
+        {
+            *token = tokens.remove(0);
+            literals(token);
+        }
+        token => token.children_mut().into_iter().for_each(literals),
+    }
+}
+
+/// The expression as the tree that the executor calculates
+impl From<&Expr> for Token {
+    fn from(expr: &Expr) -> Token {
+        let token = |expr: &Expr| Box::new(Token::from(expr));
+        let tokens = |exprs: &[Expr]| exprs.iter().map(Token::from).collect();
+        match &expr.kind {
+            ExprKind::Value(value) => Token::from(value.clone()),
+            ExprKind::Var(name) => Token::Var(name.clone()),
+            ExprKind::Unary(op, a) => op.token()(token(a)),
+            ExprKind::Binary(op, a, b) => op.token()(token(a), token(b)),
+            ExprKind::If(c, a, b) => Token::If(token(c), token(a), token(b)),
+            ExprKind::List(exprs) => Token::List(tokens(exprs)),
+            ExprKind::Range(from, to, step) => Token::Range(
+                token(from),
+                token(to),
+                step.as_deref().map_or(Box::new(Token::I(1)), token),
+            ),
+            ExprKind::Lambda(name, body) => Token::Lambda(name.clone(), token(body)),
+            ExprKind::Equation(a, b) => Token::Equation(token(a), token(b)),
+            ExprKind::Call(function, args) => Token::Call(*function, tokens(args)),
+            ExprKind::Group(exprs) => Token::Group(tokens(exprs)),
+        }
+    }
+}
+
+/// A tree of the executor as an expression, the spans are empty
+impl From<&Token> for Expr {
+    fn from(token: &Token) -> Expr {
+        let expr = |token: &Token| Box::new(Expr::from(token));
+        let exprs = |tokens: &[Token]| tokens.iter().map(Expr::from).collect();
+        let unary = |op, a: &Token| ExprKind::Unary(op, expr(a));
+        let binary = |op, a: &Token, b: &Token| ExprKind::Binary(op, expr(a), expr(b));
+        let kind = match token {
+            Token::Var(name) => ExprKind::Var(name.clone()),
+            Token::Neg(a) => unary(UnaryOp::Neg, a),
+            Token::Not(a) => unary(UnaryOp::Not, a),
+            Token::BitNot(a) => unary(UnaryOp::BitNot, a),
+            Token::Sin(a) => unary(UnaryOp::Sin, a),
+            Token::Cos(a) => unary(UnaryOp::Cos, a),
+            Token::Sqrt(a) => unary(UnaryOp::Sqrt, a),
+            Token::Add(a, b) => binary(BinaryOp::Add, a, b),
+            Token::Sub(a, b) => binary(BinaryOp::Sub, a, b),
+            Token::Mul(a, b) => binary(BinaryOp::Mul, a, b),
+            Token::ElemMul(a, b) => binary(BinaryOp::ElemMul, a, b),
+            Token::Div(a, b) => binary(BinaryOp::Div, a, b),
+            Token::Pow(a, b) => binary(BinaryOp::Pow, a, b),
+            Token::PlusMinus(a, b) => binary(BinaryOp::PlusMinus, a, b),
+            Token::In(a, b) => binary(BinaryOp::In, a, b),
+            Token::BitAnd(a, b) => binary(BinaryOp::BitAnd, a, b),
+            Token::BitOr(a, b) => binary(BinaryOp::BitOr, a, b),
+            Token::BitXor(a, b) => binary(BinaryOp::BitXor, a, b),
+            Token::Shl(a, b) => binary(BinaryOp::Shl, a, b),
+            Token::Shr(a, b) => binary(BinaryOp::Shr, a, b),
+            Token::Eq(a, b) => binary(BinaryOp::Eq, a, b),
+            Token::Ne(a, b) => binary(BinaryOp::Ne, a, b),
+            Token::Lt(a, b) => binary(BinaryOp::Lt, a, b),
+            Token::Le(a, b) => binary(BinaryOp::Le, a, b),
+            Token::Gt(a, b) => binary(BinaryOp::Gt, a, b),
+            Token::Ge(a, b) => binary(BinaryOp::Ge, a, b),
+            Token::And(a, b) => binary(BinaryOp::And, a, b),
+            Token::Or(a, b) => binary(BinaryOp::Or, a, b),
+            Token::If(c, a, b) => ExprKind::If(expr(c), expr(a), expr(b)),
+            Token::Range(from, to, step) => {
+                let step = (**step != Token::I(1)).then(|| expr(step));
+                ExprKind::Range(expr(from), expr(to), step)
+            }
+            Token::Lambda(name, body) => ExprKind::Lambda(name.clone(), expr(body)),
+            Token::Equation(a, b) => ExprKind::Equation(expr(a), expr(b)),
+            Token::Call(function, args) => ExprKind::Call(*function, exprs(args)),
+            Token::Group(tokens) => ExprKind::Group(exprs(tokens)),
+            Token::List(tokens) => ExprKind::List(exprs(tokens)),
+            token => match Value::try_from(token.clone()) {
+                Ok(value) => ExprKind::Value(value),
+                Err(error) => unreachable!("{error}"),
+            },
+        };
+        Expr::new(kind, Span::default())
+    }
+}
@@ -0,0 +1,275 @@
+use dashu_float::{round::mode::HalfAway, Context as Precision, DBig};
+
+use crate::{context::Context, function::Function, token::Token};
+
+/// More digits for the steps, the result is rounded to the precision
+const GUARD: usize = 20;
+
+/// The biggest power of ten of a `pow` result, the digits of bigger numbers take too long
+/// to print and the exponents can overflow
+const MAX_POWER: f64 = 100_000.0;
+
+/// A number as a big float, a float is the shortest decimal that gives the same float,
+/// so `0.1` is exactly one tenth
+pub fn to_big(token: &Token) -> Option<DBig> {
+    match token {
+        Token::Big(num) => Some(num.clone()),
+        Token::I(num) => Some(DBig::from(*num)),
+        Token::F(num) if num.is_finite() => num.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// An operator with a float or a big float is calculated with the precision, integers
+/// stay integers
+pub fn is_big(token: &Token) -> bool {
+    matches!(token, Token::Big(_) | Token::F(_))
+}
+
+fn working(digits: usize) -> Precision<HalfAway> {
+    Precision::new(digits + GUARD)
+}
+
+fn round(num: DBig, digits: usize) -> DBig {
+    num.with_precision(digits).value()
+}
+
+/// About the power of ten of the number, `isize::MIN` for zero
+fn magnitude(num: &DBig) -> isize {
+    let repr = num.repr();
+    if repr.is_zero() {
+        isize::MIN
+    } else {
+        repr.exponent() + repr.digits() as isize
+    }
+}
+
+pub fn add(a: &DBig, b: &DBig, digits: usize) -> Result<DBig, String> {
+    Ok(round(
+        working(digits).add(a.repr(), b.repr()).value(),
+        digits,
+    ))
+}
+
+pub fn sub(a: &DBig, b: &DBig, digits: usize) -> Result<DBig, String> {
+    Ok(round(
+        working(digits).sub(a.repr(), b.repr()).value(),
+        digits,
+    ))
+}
+
+pub fn mul(a: &DBig, b: &DBig, digits: usize) -> Result<DBig, String> {
+    Ok(round(
+        working(digits).mul(a.repr(), b.repr()).value(),
+        digits,
+    ))
+}
+
+/// Like with the other numbers, the division by 0 is 0
+pub fn div(a: &DBig, b: &DBig, digits: usize) -> Result<DBig, String> {
+    if b.repr().is_zero() {
+        return Ok(DBig::ZERO);
+    }
+    Ok(round(
+        working(digits).div(a.repr(), b.repr()).value(),
+        digits,
+    ))
+}
+
+pub fn pow(a: &DBig, b: &DBig, digits: usize) -> Result<DBig, String> {
+    let context = working(digits);
+    let negative = *b < DBig::ZERO;
+    if a.repr().is_zero() {
+        return match b.repr().is_zero() {
+            true => Ok(DBig::ONE),
+            false if negative => Err(format!("Cannot calculate: pow({a}, {b})")),
+            false => Ok(DBig::ZERO),
+        };
+    }
+    // about the power of ten of the result, `a` can be too big or too small for a float
+    let log = match a.to_f64().value().abs() {
+        x if x.is_normal() => x.log10(),
+        _ => magnitude(a) as f64,
+    };
+    check_power(b.to_f64().value() * log, || format!("pow({a}, {b})"))?;
+    let value = if b.repr().is_int() {
+        context.powi(a.repr(), b.to_int().value()).value()
+    } else if *a < DBig::ZERO {
+        return Err(format!(
+            "Cannot calculate: pow({a}, {b}), a negative number to a fraction"
+        ));
+    } else {
+        let exponent = context
+            .mul(b.repr(), ln_reduced(a, &context).repr())
+            .value();
+        context.exp(exponent.repr()).value()
+    };
+    Ok(round(value, digits))
+}
+
+pub fn sqrt(x: &DBig, digits: usize) -> Result<DBig, String> {
+    if *x < DBig::ZERO {
+        return Err(format!("Cannot calculate: sqrt({x})"));
+    }
+    Ok(round(working(digits).sqrt(x.repr()).value(), digits))
+}
+
+/// An error when the result is too far from 1, the power is about its power of ten
+fn check_power(power: f64, formula: impl Fn() -> String) -> Result<(), String> {
+    if power.abs() <= MAX_POWER {
+        Ok(())
+    } else {
+        Err(format!(
+            "Cannot calculate: {}, the result needs a power of ten over {MAX_POWER}",
+            formula()
+        ))
+    }
+}
+
+pub fn exp(x: &DBig, digits: usize) -> Result<DBig, String> {
+    check_power(x.to_f64().value() * std::f64::consts::LOG10_E, || {
+        format!("exp({x})")
+    })?;
+    Ok(round(working(digits).exp(x.repr()).value(), digits))
+}
+
+/// `ln(m * 10^k)` as `ln(m) + k ln(10)` with `m` between 0.1 and 1, the logarithm is
+/// slow for numbers far from 1
+fn ln_reduced(x: &DBig, context: &Precision<HalfAway>) -> DBig {
+    let k = magnitude(x);
+    let repr = x.repr();
+    let m = DBig::from_parts(repr.significand().clone(), repr.exponent() - k);
+    let ten = context.ln(DBig::from(10).repr()).value();
+    let shift = context.mul(DBig::from(k).repr(), ten.repr()).value();
+    context
+        .add(context.ln(m.repr()).value().repr(), shift.repr())
+        .value()
+}
+
+pub fn ln(x: &DBig, digits: usize) -> Result<DBig, String> {
+    if *x <= DBig::ZERO {
+        return Err(format!("Cannot calculate: ln({x})"));
+    }
+    Ok(round(ln_reduced(x, &working(digits)), digits))
+}
+
+/// `atan(1 / n)` with its series
+fn atan_inverse(n: i64, context: &Precision<HalfAway>, digits: usize) -> DBig {
+    let n = DBig::from(n);
+    let square = context.mul(n.repr(), n.repr()).value();
+    let mut power = context.inv(n.repr()).value();
+    let mut sum = power.clone();
+    for k in 1.. {
+        power = context.div(power.repr(), square.repr()).value();
+        if magnitude(&power) < -(digits as isize) {
+            break;
+        }
+        let term = context
+            .div(power.repr(), DBig::from(2 * k + 1).repr())
+            .value();
+        sum = if k % 2 == 1 {
+            context.sub(sum.repr(), term.repr()).value()
+        } else {
+            context.add(sum.repr(), term.repr()).value()
+        };
+    }
+    sum
+}
+
+/// Machin's formula `pi = 16 atan(1 / 5) - 4 atan(1 / 239)`
+pub fn pi(digits: usize) -> DBig {
+    let context = working(digits);
+    let a = atan_inverse(5, &context, digits + GUARD);
+    let b = atan_inverse(239, &context, digits + GUARD);
+    let pi = context
+        .sub(
+            context.mul(DBig::from(16).repr(), a.repr()).value().repr(),
+            context.mul(DBig::from(4).repr(), b.repr()).value().repr(),
+        )
+        .value();
+    round(pi, digits)
+}
+
+/// The Taylor series of `sin` or `cos` after the angle is moved between -pi and pi
+fn periodic(x: &DBig, digits: usize, sin: bool) -> DBig {
+    // a big angle needs more digits of pi
+    let extra = magnitude(x).max(0) as usize;
+    let context = working(digits + extra);
+    let tau = context
+        .mul(DBig::from(2).repr(), pi(digits + GUARD + extra).repr())
+        .value();
+    let turns = context.div(x.repr(), tau.repr()).value().round();
+    let x = context
+        .sub(
+            x.repr(),
+            context.mul(turns.repr(), tau.repr()).value().repr(),
+        )
+        .value();
+
+    let context = working(digits);
+    let minus_square = -context.mul(x.repr(), x.repr()).value();
+    let (mut term, mut k) = if sin { (x, 1) } else { (DBig::ONE, 0) };
+    let mut sum = term.clone();
+    loop {
+        let factor = DBig::from((k + 1) * (k + 2));
+        term = context
+            .div(
+                context.mul(term.repr(), minus_square.repr()).value().repr(),
+                factor.repr(),
+            )
+            .value();
+        k += 2;
+        if term.repr().is_zero() || magnitude(&term) < magnitude(&sum) - (digits + GUARD) as isize {
+            break;
+        }
+        sum = context.add(sum.repr(), term.repr()).value();
+    }
+    round(sum, digits)
+}
+
+pub fn sin(x: &DBig, digits: usize) -> Result<DBig, String> {
+    Ok(periodic(x, digits, true))
+}
+
+pub fn cos(x: &DBig, digits: usize) -> Result<DBig, String> {
+    Ok(periodic(x, digits, false))
+}
+
+/// `pi`, `exp()` and `ln()` of a number or a function with a big float
+pub fn is_big_call(function: Function, args: &[Token]) -> bool {
+    match (function, args) {
+        (Function::Pi, []) => true,
+        (Function::Exp | Function::Ln, [x]) if to_big(x).is_some() => true,
+        _ => has_big(args),
+    }
+}
+
+/// `pi`, `exp()` and `ln()` with the precision, other functions get the big floats as floats
+pub fn call(function: Function, args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    let digits = context.precision.unwrap_or_default();
+    let x = args.first().and_then(to_big);
+    match (function, x) {
+        (Function::Pi, _) if args.is_empty() => Ok(Token::Big(pi(digits))),
+        (Function::Exp, Some(x)) if args.len() == 1 => Ok(Token::Big(exp(&x, digits)?)),
+        (Function::Ln, Some(x)) if args.len() == 1 => Ok(Token::Big(ln(&x, digits)?)),
+        _ => function.call(args.iter().map(to_float).collect(), context),
+    }
+}
+
+/// A big float as a float, lists are changed too
+fn to_float(token: &Token) -> Token {
+    match token {
+        Token::Big(num) => Token::F(num.to_f64().value()),
+        Token::List(list) => Token::List(list.iter().map(to_float).collect()),
+        _ => token.clone(),
+    }
+}
+
+/// A big float is in the arguments or in their lists
+pub fn has_big(args: &[Token]) -> bool {
+    args.iter().any(|arg| match arg {
+        Token::Big(_) => true,
+        Token::List(list) => has_big(list),
+        _ => false,
+    })
+}
@@ -0,0 +1,80 @@
+use crate::{function::Function, token::Token};
+
+impl Token {
+    /// The derivative by the variable, the result is simplified
+    ///
+    /// `sin(pow(x, 2))` gives `2 * x * cos(pow(x, 2))`
+    pub fn derivative(&self, var: &str) -> Result<Token, String> {
+        Ok(derivative(self, var)?.simplify())
+    }
+
+    /// The variable is used inside of this token
+    pub fn contains_var(&self, name: &str) -> bool {
+        match self {
+            Token::Var(var) => var == name,
+            Token::Lambda(var, _) if var == name => false,
+            _ => self.children().into_iter().any(|t| t.contains_var(name)),
+        }
+    }
+}
+
+fn derivative(token: &Token, var: &str) -> Result<Token, String> {
+    let d = |token: &Token| derivative(token, var);
+    // the constants are floats, `1 / x` is not the integer division at `x = 2`
+    Ok(match token {
+        Token::I(_) | Token::F(_) => Token::F(0.0),
+        Token::Var(name) if name == var => Token::F(1.0),
+        Token::Var(_) => Token::F(0.0),
+        Token::Group(tokens) if tokens.len() == 1 => d(&tokens[0])?,
+        Token::Add(a, b) => Token::a(d(a)?, d(b)?),
+        Token::Sub(a, b) => Token::s(d(a)?, d(b)?),
+        Token::Neg(a) => Token::Neg(Box::new(d(a)?)),
+        // (a * b)' = a' * b + a * b'
+        Token::Mul(a, b) => Token::a(
+            Token::m(d(a)?, b.as_ref().clone()),
+            Token::m(a.as_ref().clone(), d(b)?),
+        ),
+        // (a / b)' = (a' * b - a * b') / pow(b, 2)
+        Token::Div(a, b) => Token::d(
+            Token::s(
+                Token::m(d(a)?, b.as_ref().clone()),
+                Token::m(a.as_ref().clone(), d(b)?),
+            ),
+            Token::pow(b.as_ref().clone(), Token::I(2)),
+        ),
+        Token::Pow(a, b) if !b.contains_var(var) => Token::m(
+            Token::m(
+                b.as_ref().clone(),
+                Token::pow(
+                    a.as_ref().clone(),
+                    Token::s(b.as_ref().clone(), Token::I(1)),
+                ),
+            ),
+            d(a)?,
+        ),
+        // pow(a, b)' = pow(a, b) * (b' * ln(a) + b * a' / a)
+        Token::Pow(a, b) => Token::m(
+            token.clone(),
+            Token::a(
+                Token::m(d(b)?, ln(a.as_ref().clone())),
+                Token::d(Token::m(b.as_ref().clone(), d(a)?), a.as_ref().clone()),
+            ),
+        ),
+        Token::Sin(a) => Token::m(d(a)?, Token::cos(a.as_ref().clone())),
+        Token::Cos(a) => Token::m(d(a)?, Token::Neg(Box::new(Token::sin(a.as_ref().clone())))),
+        // sqrt(a)' = a' / (2 * sqrt(a))
+        Token::Sqrt(a) => Token::d(d(a)?, Token::m(Token::I(2), token.clone())),
+        Token::Call(Function::Ln, args) if args.len() == 1 => {
+            Token::d(d(&args[0])?, args[0].clone())
+        }
+        Token::Call(Function::Exp, args) if args.len() == 1 => {
+            Token::m(d(&args[0])?, token.clone())
+        }
+        _ if !token.contains_var(var) => Token::F(0.0),
+        _ => return Err(format!("Cannot differentiate: {token}")),
+    })
+}
+
+fn ln(token: Token) -> Token {
+    Token::Call(Function::Ln, vec![token])
+}
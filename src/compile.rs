@@ -0,0 +1,374 @@
+use std::{cmp::Ordering, fmt::Display};
+
+use crate::{
+    ast::{BinaryOp, Expr, ExprKind, UnaryOp},
+    context::{Context, Width},
+    function::Function,
+    token::Token,
+    value::Value,
+};
+
+/// A part of a compiled formula, the values are only integers, floats and booleans
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Value(Token),
+    /// The index in the variables of `Compiled::eval`
+    Var(usize),
+    Unary(UnaryOp, Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    If(Box<Node>, Box<Node>, Box<Node>),
+    /// `ln`, `exp`, `min` and `max`, the other functions only get constant arguments
+    Call(Function, Vec<Node>),
+}
+
+/// The values of a variable for every row of `Compiled::eval_batch`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column<'a> {
+    Float(&'a [f64]),
+    Int(&'a [i64]),
+    Bool(&'a [bool]),
+}
+
+impl Column<'_> {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Float(values) => values.len(),
+            Column::Int(values) => values.len(),
+            Column::Bool(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, row: usize) -> Token {
+        match self {
+            Column::Float(values) => Token::F(values[row]),
+            Column::Int(values) => Token::I(values[row]),
+            Column::Bool(values) => Token::Bool(values[row]),
+        }
+    }
+}
+
+impl<'a> From<&'a [f64]> for Column<'a> {
+    fn from(values: &'a [f64]) -> Self {
+        Column::Float(values)
+    }
+}
+
+impl<'a> From<&'a [i64]> for Column<'a> {
+    fn from(values: &'a [i64]) -> Self {
+        Column::Int(values)
+    }
+}
+
+impl<'a> From<&'a [bool]> for Column<'a> {
+    fn from(values: &'a [bool]) -> Self {
+        Column::Bool(values)
+    }
+}
+
+/// A formula that is parsed and checked once and calculated with many values of the variables,
+/// made by `kalc_kman::compile`
+///
+/// The parts without variables are calculated when the formula is compiled, `eval` does not
+/// allocate unless it fails
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compiled {
+    expr: Expr,
+    node: Node,
+    vars: Vec<String>,
+}
+
+impl Compiled {
+    pub fn new(expr: Expr) -> Result<Self, String> {
+        let mut vars = Vec::new();
+        let node = lower(&expr, &mut vars, &Context::default())?;
+        Ok(Self { expr, node, vars })
+    }
+
+    /// The names of the variables in the order they are written first, `eval` gets their values
+    /// in this order
+    pub fn vars(&self) -> &[String] {
+        &self.vars
+    }
+
+    /// The index of the variable in the values of `eval`
+    pub fn var(&self, name: &str) -> Option<usize> {
+        self.vars.iter().position(|var| var == name)
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Calculates the formula, `vars` has a value for every name in `vars()`
+    pub fn eval(&self, vars: &[Value]) -> Result<Value, String> {
+        self.expect(vars.len())?;
+        let token = run(&self.node, &|i| match &vars[i] {
+            Value::Int(num) => Ok(Token::I(*num)),
+            Value::Float(num) => Ok(Token::F(*num)),
+            Value::Bool(b) => Ok(Token::Bool(*b)),
+            value => Err(format!(
+                "The variable {} can only be a number or a boolean, got: {value}",
+                self.vars[i]
+            )),
+        })?;
+        Value::try_from(token)
+    }
+
+    /// Calculates the formula for every row of the columns, there is a column for every name in
+    /// `vars()` and a row that fails does not stop the other rows
+    ///
+    /// The columns need the same length, without variables there is one row. With the `parallel`
+    /// feature the rows are calculated on the threads of rayon
+    pub fn eval_batch(&self, columns: &[Column]) -> Result<Vec<Result<Value, String>>, String> {
+        self.expect(columns.len())?;
+        let rows = columns.first().map_or(1, Column::len);
+        for (var, column) in self.vars.iter().zip(columns) {
+            if column.len() != rows {
+                return Err(format!(
+                    "The column of {var} has {} rows, expected: {rows}",
+                    column.len()
+                ));
+            }
+        }
+        let row = |row: usize| {
+            let token = run(&self.node, &|i| Ok(columns[i].get(row)))?;
+            Value::try_from(token)
+        };
+        #[cfg(feature = "parallel")]
+        {
+            use rayon::prelude::*;
+            Ok((0..rows).into_par_iter().map(row).collect())
+        }
+        #[cfg(not(feature = "parallel"))]
+        Ok((0..rows).map(row).collect())
+    }
+
+    fn expect(&self, len: usize) -> Result<(), String> {
+        if len != self.vars.len() {
+            return Err(format!(
+                "Expected {} variables: {}, got: {len}",
+                self.vars.len(),
+                self.vars.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Display for Compiled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+/// The expression as a node, the variables are added to `vars`
+fn lower(expr: &Expr, vars: &mut Vec<String>, context: &Context) -> Result<Node, String> {
+    let mut lower = |expr: &Expr| lower(expr, vars, context).map(Box::new);
+    let node = match &expr.kind {
+        ExprKind::Value(value) => Node::Value(scalar(expr, Token::from(value.clone()))?),
+        ExprKind::Var(name) => {
+            let index = vars.iter().position(|var| var == name);
+            Node::Var(index.unwrap_or_else(|| {
+                vars.push(name.clone());
+                vars.len() - 1
+            }))
+        }
+        ExprKind::Group(exprs) if exprs.len() == 1 => return lower(&exprs[0]).map(|node| *node),
+        ExprKind::Unary(op, a) => Node::Unary(*op, lower(a)?),
+        ExprKind::Binary(BinaryOp::PlusMinus, ..) => return constant(expr, context, "±"),
+        ExprKind::Binary(BinaryOp::In, ..) => return constant(expr, context, "in"),
+        ExprKind::Binary(op, a, b) => Node::Binary(*op, lower(a)?, lower(b)?),
+        ExprKind::If(c, a, b) => match *lower(c)? {
+            // a constant condition only keeps its branch
+            Node::Value(c) => match c.get_bool()? {
+                true => *lower(a)?,
+                false => *lower(b)?,
+            },
+            c => Node::If(Box::new(c), lower(a)?, lower(b)?),
+        },
+        ExprKind::Call(function @ (Function::Ln | Function::Exp), args) if args.len() == 1 => {
+            Node::Call(*function, vec![*lower(&args[0])?])
+        }
+        ExprKind::Call(function @ (Function::Min | Function::Max), args)
+            if !args.is_empty() && args.iter().all(|arg| !is_list(arg)) =>
+        {
+            let args = args.iter().map(|arg| lower(arg).map(|node| *node));
+            Node::Call(*function, args.collect::<Result<_, _>>()?)
+        }
+        ExprKind::Call(function, _) if !function.is_deterministic() => {
+            return Err(format!(
+                "Cannot compile: {expr}, {} gives a different result every time",
+                function.name()
+            ))
+        }
+        ExprKind::Call(function, _) => return constant(expr, context, function.name()),
+        ExprKind::List(_) => return constant(expr, context, "a list"),
+        ExprKind::Range(..) => return constant(expr, context, "a range"),
+        ExprKind::Lambda(..) => return constant(expr, context, "a lambda"),
+        ExprKind::Equation(..) => return constant(expr, context, "an equation"),
+        ExprKind::Group(_) => return constant(expr, context, "a group"),
+    };
+    // the parts without variables are calculated once
+    let constant = match &node {
+        Node::Unary(_, a) => is_value(a),
+        Node::Binary(_, a, b) => is_value(a) && is_value(b),
+        Node::Call(_, args) => args.iter().all(is_value),
+        Node::Value(_) | Node::Var(_) | Node::If(..) => false,
+    };
+    if constant {
+        let token = run(&node, &|_| unreachable!())?;
+        return Ok(Node::Value(scalar(expr, token)?));
+    }
+    Ok(node)
+}
+
+/// A part that is calculated by the executor, it cannot have variables
+fn constant(expr: &Expr, context: &Context, name: &str) -> Result<Node, String> {
+    let mut has_var = false;
+    expr.walk(&mut |node| has_var |= matches!(node.kind, ExprKind::Var(_)));
+    match expr.eval(context) {
+        Ok(value) => Ok(Node::Value(scalar(expr, Token::from(value))?)),
+        // the variables of a lambda like `x -> x * x` are not free
+        Err(_) if has_var => Err(format!(
+            "Cannot compile: {expr}, {name} cannot be calculated with variables"
+        )),
+        Err(err) => Err(err),
+    }
+}
+
+fn scalar(expr: &Expr, token: Token) -> Result<Token, String> {
+    match token {
+        Token::I(_) | Token::F(_) | Token::Bool(_) => Ok(token),
+        Token::Inf => Ok(Token::F(f64::INFINITY)),
+        token => Err(format!(
+            "Cannot compile: {expr}, only numbers and booleans can be calculated, got: {token}"
+        )),
+    }
+}
+
+fn is_value(node: &Node) -> bool {
+    matches!(node, Node::Value(_))
+}
+
+/// `min([1, 2])` gets the numbers of a list
+fn is_list(expr: &Expr) -> bool {
+    match &expr.kind {
+        ExprKind::List(_) | ExprKind::Range(..) => true,
+        ExprKind::Group(exprs) => exprs.iter().any(is_list),
+        _ => false,
+    }
+}
+
+fn run(node: &Node, var: &impl Fn(usize) -> Result<Token, String>) -> Result<Token, String> {
+    match node {
+        Node::Value(token) => Ok(token.clone()),
+        Node::Var(i) => var(*i),
+        Node::Unary(op, a) => unary(*op, run(a, var)?),
+        Node::Binary(op, a, b) => binary(*op, run(a, var)?, run(b, var)?),
+        // only the branch that is taken
+        Node::If(c, a, b) => match run(c, var)?.get_bool()? {
+            true => run(a, var),
+            false => run(b, var),
+        },
+        Node::Call(function @ (Function::Ln | Function::Exp), args) => {
+            let x = number(*function, &run(&args[0], var)?)?;
+            Ok(Token::F(match function {
+                Function::Ln => x.ln(),
+                _ => x.exp(),
+            }))
+        }
+        Node::Call(function, args) => {
+            let order = match function {
+                Function::Min => Ordering::Less,
+                _ => Ordering::Greater,
+            };
+            let mut best = run(&args[0], var)?;
+            number(*function, &best)?;
+            for arg in &args[1..] {
+                let token = run(arg, var)?;
+                number(*function, &token)?;
+                if compare(&token, &best)? == order {
+                    best = token;
+                }
+            }
+            Ok(best)
+        }
+    }
+}
+
+/// Like `Token::compare` for numbers and booleans
+fn compare(a: &Token, b: &Token) -> Result<Ordering, String> {
+    match (a, b) {
+        (Token::I(a), Token::I(b)) => Ok(a.cmp(b)),
+        (Token::Bool(a), Token::Bool(b)) => Ok(a.cmp(b)),
+        _ if a.is_num() && b.is_num() => a
+            .get_f64()
+            .partial_cmp(&b.get_f64())
+            .ok_or_else(|| format!("Cannot compare: {a} with {b}")),
+        _ => Err(format!("Cannot compare: {a} with {b}")),
+    }
+}
+
+fn number(function: Function, token: &Token) -> Result<f64, String> {
+    match token {
+        Token::I(_) | Token::F(_) => Ok(token.get_f64()),
+        token => Err(format!("{} needs numbers, got: {token}", function.name())),
+    }
+}
+
+fn unary(op: UnaryOp, a: Token) -> Result<Token, String> {
+    Ok(match (op, &a) {
+        // `-(-2^63)` does not fit and is an error below
+        (UnaryOp::Neg, Token::I(a)) if *a != i64::MIN => Token::I(-a),
+        (UnaryOp::Neg, Token::F(a)) => Token::F(-a),
+        (UnaryOp::Neg, Token::I(_)) => return Err(op.token()(Box::new(a)).overflow()),
+        (UnaryOp::Not, _) => Token::Bool(!a.get_bool()?),
+        (UnaryOp::BitNot, _) => Token::I(!a.get_int()?),
+        (UnaryOp::Sin, _) if a.is_num() => Token::F(a.get_f64().sin()),
+        (UnaryOp::Cos, _) if a.is_num() => Token::F(a.get_f64().cos()),
+        (UnaryOp::Sqrt, _) if a.is_num() => Token::F(a.get_f64().sqrt()),
+        _ => return Err(format!("Cannot calculate: {}", op.token()(Box::new(a)))),
+    })
+}
+
+/// Like `Token::calculate` without the programmer mode
+fn binary(op: BinaryOp, a: Token, b: Token) -> Result<Token, String> {
+    let nums = a.is_num() && b.is_num();
+    let shift =
+        |b: &Token| u32::try_from(b.get_int()?).map_err(|_| format!("Cannot shift by: {b}"));
+    let error = |a: Token, b: Token| {
+        let token = op.token()(Box::new(a), Box::new(b));
+        format!("Cannot calculate: {token}")
+    };
+    // integers that overflow are an error for the row
+    let checked = |result: Option<Token>, a: Token, b: Token| {
+        result.ok_or_else(|| op.token()(Box::new(a), Box::new(b)).overflow())
+    };
+    Ok(match op {
+        BinaryOp::Add if nums => checked(a.clone().checked_add(b.clone()), a, b)?,
+        BinaryOp::Sub if nums => checked(a.clone().checked_sub(b.clone()), a, b)?,
+        BinaryOp::Mul | BinaryOp::ElemMul if nums => {
+            checked(a.clone().checked_mul(b.clone()), a, b)?
+        }
+        BinaryOp::Div if nums => checked(a.clone().checked_div(b.clone()), a, b)?,
+        BinaryOp::Pow if nums => Token::F(a.get_f64().powf(b.get_f64())),
+        BinaryOp::BitAnd => Token::I(a.get_int()? & b.get_int()?),
+        BinaryOp::BitOr => Token::I(a.get_int()? | b.get_int()?),
+        BinaryOp::BitXor => Token::I(a.get_int()? ^ b.get_int()?),
+        BinaryOp::Shl => Token::I(Width::I64.shl(a.get_int()?, shift(&b)?)),
+        BinaryOp::Shr => Token::I(Width::I64.shr(a.get_int()?, shift(&b)?)),
+        BinaryOp::Eq => Token::Bool(compare(&a, &b)?.is_eq()),
+        BinaryOp::Ne => Token::Bool(compare(&a, &b)?.is_ne()),
+        BinaryOp::Lt => Token::Bool(compare(&a, &b)?.is_lt()),
+        BinaryOp::Le => Token::Bool(compare(&a, &b)?.is_le()),
+        BinaryOp::Gt => Token::Bool(compare(&a, &b)?.is_gt()),
+        BinaryOp::Ge => Token::Bool(compare(&a, &b)?.is_ge()),
+        BinaryOp::And => Token::Bool(a.get_bool()? && b.get_bool()?),
+        BinaryOp::Or => Token::Bool(a.get_bool()? || b.get_bool()?),
+        _ => return Err(error(a, b)),
+    })
+}
@@ -0,0 +1,327 @@
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::money::Rates;
+
+/// Settings used while a formula is calculated
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    /// Programmer mode, when set every integer is wrapped to this width
+    pub width: Option<Width>,
+    /// Used by `rand()`, `randint()`, `randn()` and `choice()`
+    pub rng: Rng,
+    /// Interval mode, when set a list of two numbers like `[1.9, 2.1]` is an interval
+    pub intervals: bool,
+    /// The fixed time zone of `today` and `now`, in minutes east of UTC
+    pub utc_offset: i32,
+    /// The exchange rates of the money, given by the application
+    pub rates: Option<Arc<dyn Rates>>,
+    /// The significant digits of the big floats, when set the floats are big floats
+    #[cfg(feature = "bigfloat")]
+    pub precision: Option<usize>,
+}
+
+impl Context {
+    pub fn programmer(width: Width) -> Self {
+        Self {
+            width: Some(width),
+            ..Self::default()
+        }
+    }
+
+    /// `[1.9, 2.1] * 3` is calculated with the interval from 1.9 to 2.1
+    pub fn with_intervals(mut self) -> Self {
+        self.intervals = true;
+        self
+    }
+
+    /// Floats are calculated with this many significant digits, like `pi` or `sqrt(2)`
+    #[cfg(feature = "bigfloat")]
+    pub fn set_precision(&mut self, digits: usize) {
+        self.precision = Some(digits.max(1));
+    }
+
+    #[cfg(feature = "bigfloat")]
+    pub fn with_precision(mut self, digits: usize) -> Self {
+        self.set_precision(digits);
+        self
+    }
+
+    /// `now` is the time in the fixed time zone, like `-300` for UTC-5
+    pub fn with_utc_offset(mut self, minutes: i32) -> Self {
+        self.utc_offset = minutes;
+        self
+    }
+
+    /// `12.50 EUR + 3 USD` converts the dollars with these rates
+    pub fn with_rates(mut self, rates: impl Rates + 'static) -> Self {
+        self.rates = Some(Arc::new(rates));
+        self
+    }
+
+    /// The random functions give the same results for the same seed
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng = Rng::new(seed);
+        self
+    }
+}
+
+/// SplitMix64 random number generator, the state is atomic so that the context can be shared
+#[derive(Debug)]
+pub struct Rng {
+    state: AtomicU64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: AtomicU64::new(seed),
+        }
+    }
+
+    pub fn next_u64(&self) -> u64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9E37_79B9_7F4A_7C15, Ordering::Relaxed)
+            .wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Between 0 and 1, 1 is excluded
+    pub fn next_f64(&self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Between `a` and `b`, both included
+    pub fn range(&self, a: i64, b: i64) -> i64 {
+        let span = b.wrapping_sub(a) as u64 as u128 + 1;
+        // the multiply and shift method, the bias is too small to matter
+        let offset = (self.next_u64() as u128 * span) >> 64;
+        a.wrapping_add(offset as i64)
+    }
+
+    /// Standard normal distribution, with the Box-Muller transform
+    pub fn normal(&self) -> f64 {
+        let u1 = 1.0 - self.next_f64();
+        let u2 = self.next_f64();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+/// Seeded from the clock
+impl Default for Rng {
+    fn default() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::new(nanos)
+    }
+}
+
+/// The clone continues from the same state
+impl Clone for Rng {
+    fn clone(&self) -> Self {
+        Self::new(self.state.load(Ordering::Relaxed))
+    }
+}
+
+/// Fixed width integer type used by the programmer mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum Width {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+}
+
+impl Width {
+    pub fn bits(self) -> u32 {
+        match self {
+            Width::U8 | Width::I8 => 8,
+            Width::U16 | Width::I16 => 16,
+            Width::U32 | Width::I32 => 32,
+            Width::U64 | Width::I64 => 64,
+        }
+    }
+
+    pub fn is_signed(self) -> bool {
+        matches!(self, Width::I8 | Width::I16 | Width::I32 | Width::I64)
+    }
+
+    pub fn mask(self) -> u64 {
+        u64::MAX >> (64 - self.bits())
+    }
+
+    /// Truncates the number to the width, the result is sign extended for signed widths
+    ///
+    /// `U64` numbers are stored with the same bits inside of an `i64`
+    pub fn wrap(self, num: i64) -> i64 {
+        let shift = 64 - self.bits();
+        if self.is_signed() {
+            (num << shift) >> shift
+        } else {
+            ((num as u64) & self.mask()) as i64
+        }
+    }
+
+    pub fn add(self, a: i64, b: i64) -> i64 {
+        self.wrap(a.wrapping_add(b))
+    }
+
+    pub fn sub(self, a: i64, b: i64) -> i64 {
+        self.wrap(a.wrapping_sub(b))
+    }
+
+    pub fn mul(self, a: i64, b: i64) -> i64 {
+        self.wrap(a.wrapping_mul(b))
+    }
+
+    pub fn div(self, a: i64, b: i64) -> i64 {
+        let (a, b) = (self.wrap(a), self.wrap(b));
+        if b == 0 {
+            0
+        } else if self == Width::U64 {
+            ((a as u64) / (b as u64)) as i64
+        } else {
+            self.wrap(a.wrapping_div(b))
+        }
+    }
+
+    /// Shifting by the width or more gives 0
+    pub fn shl(self, a: i64, b: u32) -> i64 {
+        if b >= self.bits() {
+            0
+        } else {
+            self.wrap(a << b)
+        }
+    }
+
+    /// Arithmetic shift for signed widths and logical shift for unsigned widths
+    pub fn shr(self, a: i64, b: u32) -> i64 {
+        let a = self.wrap(a);
+        let b = b.min(63);
+        if self.is_signed() {
+            a >> b
+        } else {
+            ((a as u64) >> b) as i64
+        }
+    }
+}
+
+/// The base used to print an integer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum Radix {
+    Bin,
+    Oct,
+    Dec,
+    Hex,
+    /// Any other base from 2 to 36, printed like `36#1Z`
+    Base(u32),
+}
+
+impl Radix {
+    /// `base(16)` is the same as `hex`
+    pub fn from_base(base: i64) -> Result<Self, String> {
+        match base {
+            2 => Ok(Radix::Bin),
+            8 => Ok(Radix::Oct),
+            10 => Ok(Radix::Dec),
+            16 => Ok(Radix::Hex),
+            3..=36 => Ok(Radix::Base(base as u32)),
+            _ => Err(format!("The base needs to be from 2 to 36, got: {base}")),
+        }
+    }
+
+    /// The name after `to`, like `255 to hex`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bin" => Some(Radix::Bin),
+            "oct" => Some(Radix::Oct),
+            "dec" => Some(Radix::Dec),
+            "hex" => Some(Radix::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn base(self) -> u32 {
+        match self {
+            Radix::Bin => 2,
+            Radix::Oct => 8,
+            Radix::Dec => 10,
+            Radix::Hex => 16,
+            Radix::Base(base) => base,
+        }
+    }
+
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Radix::Bin => "0b",
+            Radix::Oct => "0o",
+            Radix::Dec | Radix::Base(_) => "",
+            Radix::Hex => "0x",
+        }
+    }
+
+    /// Formats the number, with a width the number is printed as the bits of that width
+    pub fn format(self, num: i64, width: Option<Width>) -> String {
+        let (negative, num) = match width {
+            Some(width) if self == Radix::Dec && width.is_signed() => {
+                let num = width.wrap(num);
+                (num < 0, num.unsigned_abs())
+            }
+            Some(width) => (false, num as u64 & width.mask()),
+            None => (num < 0, num.unsigned_abs()),
+        };
+        let sign = if negative { "-" } else { "" };
+        let prefix = self.prefix();
+        match self {
+            Radix::Bin => format!("{sign}{prefix}{num:b}"),
+            Radix::Oct => format!("{sign}{prefix}{num:o}"),
+            Radix::Dec => format!("{sign}{num}"),
+            Radix::Hex => format!("{sign}{prefix}{num:X}"),
+            Radix::Base(base) => {
+                let mut digits = Vec::new();
+                let mut rest = num;
+                loop {
+                    let digit = (rest % base as u64) as u32;
+                    digits.push(
+                        char::from_digit(digit, base)
+                            .unwrap_or('?')
+                            .to_ascii_uppercase(),
+                    );
+                    rest /= base as u64;
+                    if rest == 0 {
+                        break;
+                    }
+                }
+                let digits: String = digits.into_iter().rev().collect();
+                format!("{sign}{base}#{digits}")
+            }
+        }
+    }
+}
+
+impl Display for Radix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Radix::Bin => f.write_str("bin"),
+            Radix::Oct => f.write_str("oct"),
+            Radix::Dec => f.write_str("dec"),
+            Radix::Hex => f.write_str("hex"),
+            Radix::Base(base) => write!(f, "base({base})"),
+        }
+    }
+}
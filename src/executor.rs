@@ -1,45 +1,172 @@
-use crate::{lexer::Lexer, token::Token};
+use crate::{
+    context::{Context, Radix},
+    format::Formatter,
+    lexer::Lexer,
+    token::Token,
+    trace::{Step, Trace},
+};
+
+/// An expression as a function of one of its variables, like `pow(x, 2)` of `x`
+pub struct Binding<'a> {
+    pub expr: Token,
+    pub var: &'a str,
+    pub context: &'a Context,
+}
+
+impl Binding<'_> {
+    /// Calculates the expression with the number in the place of the variable
+    pub fn eval(&self, x: f64) -> Result<f64, String> {
+        let token = self.expr.substitute(self.var, &Token::F(x));
+        let value = Executor::evaluate(token, self.context)?;
+        if value.is_num() {
+            Ok(value.get_f64())
+        } else {
+            Err(format!(
+                "Needs an expression with numbers for {}, got: {value}",
+                self.var
+            ))
+        }
+    }
+}
 
 pub struct Executor {
     group: Token,
+    context: Context,
 }
 
 impl Executor {
     pub fn new(lexer: Lexer) -> Self {
+        Self::with_context(lexer, Context::default())
+    }
+
+    pub fn with_context(lexer: Lexer, context: Context) -> Self {
         Self {
-            group: Token::Group(lexer.tokens),
+            group: Token::from(&lexer.expr()),
+            context,
         }
     }
 
-    /// Returns the steps
-    pub fn execute(&mut self) -> Vec<Token> {
-        let mut results = Self::enter(&self.group);
-        // use sort by priority
-        results.sort_by(|a, b| a.1.cmp(&b.1));
-        results.reverse();
-        let mut steps = Vec::with_capacity(results.len());
-        steps.push(self.group.clone());
-        for depth in results {
-            Self::calculate(&mut self.group, depth.0);
-            if let Some(last) = steps.last() {
-                if *last == self.group {
-                    continue;
+    /// Calculates a token that is not from the lexer, like an expression with a number
+    /// in the place of its variable
+    pub fn evaluate(token: Token, context: &Context) -> Result<Token, String> {
+        let mut executor = Self {
+            group: token,
+            context: context.clone(),
+        };
+        executor.execute()?;
+        Ok(executor.group)
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// The result after `execute`
+    pub fn get(&self) -> &Token {
+        &self.group
+    }
+
+    /// Calculates the expression, every token after the tokens inside of it
+    pub fn execute(&mut self) -> Result<(), String> {
+        Self::eval(&mut self.group, &self.context)?;
+        self.check()
+    }
+
+    /// Calculates like `execute` and returns the expression after every step, it is slower
+    /// because the whole expression is copied for every step
+    pub fn steps(&mut self) -> Result<Vec<Token>, String> {
+        self.run(None)
+    }
+
+    /// Calculates like `execute`, the trace has the part that every step calculated
+    pub fn trace(&mut self) -> Result<Trace, String> {
+        let expression = self.group.clone();
+        let mut steps = Vec::new();
+        self.run(Some(&mut steps))?;
+        Ok(Trace::new(&expression, steps))
+    }
+
+    /// The token is calculated again until it does not change, like an `If` that gives its branch
+    fn eval(token: &mut Token, context: &Context) -> Result<(), String> {
+        loop {
+            match token {
+                // the branches are calculated after the condition
+                Token::If(condition, _, _) => Self::eval(condition, context)?,
+                Token::Lambda(..) => return Ok(()),
+                _ => {
+                    for child in token.children_mut() {
+                        Self::eval(child, context)?
+                    }
+                }
+            }
+            let before = token.clone();
+            token.calculate(context)?;
+            if token.same(&before) {
+                return Ok(());
+            }
+        }
+    }
+
+    fn run(&mut self, mut trace: Option<&mut Vec<Step>>) -> Result<Vec<Token>, String> {
+        let mut steps = vec![self.group.clone()];
+        loop {
+            let len = steps.len();
+            let mut results = Self::enter(&self.group);
+            // use sort by priority
+            results.sort_by_key(|a| a.1);
+            results.reverse();
+            for (indexes, _) in results {
+                let before = match trace {
+                    Some(_) => Self::find(&self.group, &indexes).cloned(),
+                    None => None,
+                };
+                Self::calculate(&mut self.group, indexes.clone(), &self.context)?;
+                if let Some(last) = steps.last() {
+                    if last.same(&self.group) {
+                        continue;
+                    }
+                }
+                if let (Some(trace), Some(before)) = (trace.as_deref_mut(), before) {
+                    let after = Self::find(&self.group, &indexes).cloned();
+                    if let Some(step) =
+                        after.and_then(|after| Step::new(before, after, &self.group))
+                    {
+                        trace.push(step)
+                    }
                 }
+                steps.push(self.group.clone())
+            }
+            // an `If` is only entered after the condition is calculated
+            if steps.len() == len {
+                break;
             }
-            steps.push(self.group.clone())
         }
 
         #[cfg(feature = "debug")]
         for (i, step) in steps.iter().enumerate() {
             println!("Step{i}: {step}")
         }
-        steps
+
+        self.check()?;
+        Ok(steps)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if let Some(var) = self.group.find_var() {
+            return Err(format!("Unknown variable: {var}"));
+        }
+        // a lambda is a function that is not called, like the result of `diff(pow(x, 2), x)`
+        if !self.group.is_value() && !matches!(self.group, Token::Lambda(..)) {
+            return Err(format!("Cannot calculate: {}", self.group));
+        }
+        Ok(())
     }
 
     pub fn get_i64(&self) -> i64 {
         match self.group {
-            Token::I(num) => num,
+            Token::I(num) | Token::Radix(num, ..) => num,
             Token::F(num) => num as i64,
+            Token::Bool(b) => b as i64,
             _ => {
                 eprintln!("You need to call execute first!");
                 0
@@ -49,8 +176,11 @@ impl Executor {
 
     pub fn get_f64(&self) -> f64 {
         match self.group {
-            Token::I(num) => num as f64,
+            Token::I(num) | Token::Radix(num, ..) => num as f64,
             Token::F(num) => num,
+            Token::Bool(b) => b as i64 as f64,
+            #[cfg(feature = "bigfloat")]
+            Token::Big(ref num) => num.to_f64().value(),
             _ => {
                 eprintln!("You need to call execute first!");
                 0.0
@@ -58,28 +188,46 @@ impl Executor {
         }
     }
 
-    pub fn enter(token: &Token) -> Vec<(Vec<usize>, usize)> {
-        let mut results = vec![(vec![], 0)];
-        let mut tokens = Vec::new();
-        match token {
-            Token::Add(t1, t2)
-            | Token::Div(t1, t2)
-            | Token::Sub(t1, t2)
-            | Token::Mul(t1, t2)
-            | Token::Pow(t1, t2) => {
-                tokens.push((t1.as_ref(), 0, 0));
-                tokens.push((t2.as_ref(), 1, 0));
+    pub fn get_bool(&self) -> bool {
+        match self.group {
+            Token::I(num) => num != 0,
+            Token::F(num) => num != 0.0,
+            Token::Bool(b) => b,
+            _ => {
+                eprintln!("You need to call execute first!");
+                false
             }
+        }
+    }
 
-            Token::Sin(t0) | Token::Cos(t0) | Token::Sqrt(t0) => tokens.push((t0.as_ref(), 0, 0)),
+    /// Formats the integer result, in programmer mode with the bits of the selected width
+    pub fn get_radix(&self, radix: Radix) -> String {
+        radix.format(self.get_i64(), self.context.width)
+    }
 
-            Token::Group(group) => {
-                for (index, token) in group.iter().enumerate() {
-                    tokens.push((token, index, 1))
-                }
-            }
-            _ => {}
-        }
+    /// The result written by the formatter, like with fixed decimals or a decimal comma
+    pub fn format(&self, formatter: &Formatter) -> String {
+        formatter.format(&self.group)
+    }
+
+    pub fn enter(token: &Token) -> Vec<(Vec<usize>, usize)> {
+        let mut results = vec![(vec![], 0)];
+        let tokens = match token {
+            // the branches are entered after the condition is calculated
+            Token::If(t0, _, _) => vec![(t0.as_ref(), 0, 0)],
+            Token::Lambda(..) => Vec::new(),
+            Token::Group(group) => group
+                .iter()
+                .enumerate()
+                .map(|(index, token)| (token, index, 1))
+                .collect(),
+            _ => token
+                .children()
+                .into_iter()
+                .enumerate()
+                .map(|(index, token)| (token, index, 0))
+                .collect(),
+        };
         for (token, index, current_preority) in tokens {
             for (mut d, preority) in Self::enter(token) {
                 d.push(index);
@@ -92,31 +240,29 @@ impl Executor {
         results
     }
 
-    fn calculate(token: &mut Token, mut indexes: Vec<usize>) {
+    /// The token at the indexes of `enter`, the last index is the first child
+    fn find<'a>(token: &'a Token, indexes: &[usize]) -> Option<&'a Token> {
+        match indexes.split_last() {
+            Some((index, indexes)) => {
+                Self::find(token.children().into_iter().nth(*index)?, indexes)
+            }
+            None => Some(token),
+        }
+    }
+
+    fn calculate(
+        token: &mut Token,
+        mut indexes: Vec<usize>,
+        context: &Context,
+    ) -> Result<(), String> {
         let index = indexes.pop();
         if let Some(index) = index {
-            match token {
-                Token::Add(t0, t1)
-                | Token::Div(t0, t1)
-                | Token::Sub(t0, t1)
-                | Token::Mul(t0, t1)
-                | Token::Pow(t0, t1) => {
-                    if index == 0 {
-                        Self::calculate(t0, indexes)
-                    } else {
-                        Self::calculate(t1, indexes)
-                    }
-                }
-                Token::Sin(t0) | Token::Cos(t0) | Token::Sqrt(t0) => Self::calculate(t0, indexes),
-                Token::Group(tokens) => {
-                    if let Some(token) = tokens.get_mut(index) {
-                        Self::calculate(token, indexes)
-                    }
-                }
-                _ => {}
+            match token.children_mut().into_iter().nth(index) {
+                Some(token) => Self::calculate(token, indexes, context),
+                None => Ok(()),
             }
         } else {
-            token.calculate();
+            token.calculate(context)
         }
     }
 }
@@ -0,0 +1,110 @@
+//! Time value of money, with the same argument order and signs as spreadsheets
+//!
+//! Money paid is negative and money received is positive, `when` is 0 when the payments are
+//! made at the end of the periods and 1 when they are made at the beginning
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-10;
+
+/// The factor of the payments, `(1 + rate * when) * ((1 + rate) ^ nper - 1) / rate`
+fn annuity(rate: f64, nper: f64, when: f64) -> f64 {
+    if rate == 0.0 {
+        nper
+    } else {
+        (1.0 + rate * when) * ((1.0 + rate).powf(nper) - 1.0) / rate
+    }
+}
+
+pub fn fv(rate: f64, nper: f64, pmt: f64, pv: f64, when: f64) -> f64 {
+    -(pv * (1.0 + rate).powf(nper) + pmt * annuity(rate, nper, when))
+}
+
+pub fn pv(rate: f64, nper: f64, pmt: f64, fv: f64, when: f64) -> f64 {
+    -(fv + pmt * annuity(rate, nper, when)) / (1.0 + rate).powf(nper)
+}
+
+pub fn pmt(rate: f64, nper: f64, pv: f64, fv: f64, when: f64) -> f64 {
+    -(fv + pv * (1.0 + rate).powf(nper)) / annuity(rate, nper, when)
+}
+
+pub fn nper(rate: f64, pmt: f64, pv: f64, fv: f64, when: f64) -> Result<f64, String> {
+    if rate == 0.0 {
+        if pmt == 0.0 {
+            return Err("nper needs a payment when the rate is 0".into());
+        }
+        return Ok(-(pv + fv) / pmt);
+    }
+    let pmt = pmt * (1.0 + rate * when);
+    let nper = ((pmt - fv * rate) / (pmt + pv * rate)).ln() / (1.0 + rate).ln();
+    if nper.is_finite() {
+        Ok(nper)
+    } else {
+        Err("nper has no solution for this payment".into())
+    }
+}
+
+/// Newton's method with a numerical derivative
+fn newton(f: impl Fn(f64) -> f64, guess: f64, name: &str) -> Result<f64, String> {
+    let mut x = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let y = f(x);
+        let h = 1e-7 * x.abs().max(1.0);
+        let slope = (f(x + h) - f(x - h)) / (2.0 * h);
+        if slope == 0.0 || !slope.is_finite() {
+            break;
+        }
+        let next = x - y / slope;
+        if (next - x).abs() < TOLERANCE {
+            return Ok(next);
+        }
+        x = next;
+    }
+    Err(format!("{name} did not find a solution, try another guess"))
+}
+
+pub fn rate(nper: f64, pmt: f64, pv: f64, fv: f64, when: f64, guess: f64) -> Result<f64, String> {
+    newton(
+        |rate| pv * (1.0 + rate).powf(nper) + pmt * annuity(rate, nper, when) + fv,
+        guess,
+        "rate",
+    )
+}
+
+/// The first value is discounted by one period
+pub fn npv(rate: f64, values: &[f64]) -> f64 {
+    values
+        .iter()
+        .zip(1..)
+        .map(|(value, i)| value / (1.0 + rate).powi(i))
+        .sum()
+}
+
+/// The first value is not discounted, so it is usually the negative investment
+pub fn irr(values: &[f64], guess: f64) -> Result<f64, String> {
+    if !values.iter().any(|v| *v > 0.0) || !values.iter().any(|v| *v < 0.0) {
+        return Err("irr needs at least one positive and one negative value".into());
+    }
+    newton(|rate| npv(rate, values) * (1.0 + rate), guess, "irr")
+}
+
+/// Only the interest, `principal * rate * time`
+pub fn simple_interest(principal: f64, rate: f64, time: f64) -> f64 {
+    principal * rate * time
+}
+
+/// Only the interest, compounded `periods` times for every unit of time
+pub fn compound_interest(principal: f64, rate: f64, time: f64, periods: f64) -> f64 {
+    principal * ((1.0 + rate / periods).powf(periods * time) - 1.0)
+}
+
+/// Rounds to the closest multiple of `step`, like `round_to(1.23, 0.05)` = 1.25
+pub fn round_to(x: f64, step: f64) -> Result<f64, String> {
+    if step <= 0.0 {
+        return Err(format!("round_to needs a positive step, got: {step}"));
+    }
+    let rounded = (x / step).round() * step;
+    // removes the floating point noise like 1.2500000000000002
+    let decimals = (-step.log10().floor()).clamp(0.0, 15.0) as i32 + 1;
+    let scale = 10f64.powi(decimals);
+    Ok((rounded * scale).round() / scale)
+}
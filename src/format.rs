@@ -0,0 +1,227 @@
+use crate::token::Token;
+
+/// How the numbers are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// The shortest digits that give the same float, with an exponent below `1e-7` and
+    /// from `1e21`, integers are written with all their digits
+    Auto,
+    /// At most this many significant figures without the zeros at the end, like `%g`,
+    /// integers are written with all their digits
+    General(usize),
+    /// This many decimals, `Fixed(2)` gives `3.14`
+    Fixed(usize),
+    /// This many significant figures, the zeros at the end are kept, `Significant(3)` gives `2.50`
+    Significant(usize),
+    /// `1.23e6` with this many significant figures
+    Scientific(usize),
+    /// Like scientific, the exponent is a multiple of 3, `12.3e3`
+    Engineering(usize),
+}
+
+/// Writes the results for people, like `Formatter::default().with_notation(Notation::Fixed(2))`
+///
+/// Lists are written with `; ` between the elements when the decimal mark is a comma,
+/// other values are written like their `Display`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Formatter {
+    notation: Notation,
+    grouping: bool,
+    decimal_mark: char,
+    separator: char,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self {
+            notation: Notation::Auto,
+            grouping: false,
+            decimal_mark: '.',
+            separator: ',',
+        }
+    }
+}
+
+/// The decimal mark and the thousands separator of the languages
+const LOCALES: &[(&[&str], char, char)] = &[
+    (&["de-ch", "fr-ch", "it-ch", "rm"], '.', '\''),
+    (
+        &["en", "ja", "zh", "ko", "th", "he", "hi", "ms", "ga"],
+        '.',
+        ',',
+    ),
+    (
+        &[
+            "de", "es", "it", "nl", "pt", "da", "id", "tr", "el", "ro", "hr", "sl", "vi",
+        ],
+        ',',
+        '.',
+    ),
+    (
+        &[
+            "fr", "ru", "pl", "cs", "sk", "sv", "fi", "nb", "no", "uk", "hu", "bg", "et", "lv",
+            "lt",
+        ],
+        ',',
+        '\u{a0}',
+    ),
+];
+
+impl Formatter {
+    pub fn with_notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    /// The integer part is written in groups of three digits, like `1,234,567`
+    pub fn with_grouping(mut self) -> Self {
+        self.grouping = true;
+        self
+    }
+
+    /// The decimal mark and the thousands separator
+    pub fn with_marks(mut self, decimal_mark: char, separator: char) -> Self {
+        self.decimal_mark = decimal_mark;
+        self.separator = separator;
+        self
+    }
+
+    /// The marks of a language like `de` or `de-CH`, `fr_FR` is the same as `fr-FR`
+    pub fn with_locale(self, locale: &str) -> Result<Self, String> {
+        let tag = locale.to_ascii_lowercase().replace('_', "-");
+        let language = tag.split('-').next().unwrap_or_default();
+        // the region is more exact than the language
+        let found = [tag.as_str(), language]
+            .into_iter()
+            .find_map(|name| LOCALES.iter().find(|(names, _, _)| names.contains(&name)));
+        match found {
+            Some((_, decimal_mark, separator)) => Ok(self.with_marks(*decimal_mark, *separator)),
+            None => Err(format!("Unknown locale: {locale}")),
+        }
+    }
+
+    pub fn format(&self, token: &Token) -> String {
+        match token {
+            Token::I(num) => match self.notation {
+                Notation::Auto | Notation::General(_) => {
+                    let sign = if *num < 0 { "-" } else { "" };
+                    format!("{sign}{}", self.localize(&num.unsigned_abs().to_string()))
+                }
+                _ => self.format_f64(*num as f64),
+            },
+            Token::F(num) => self.format_f64(*num),
+            #[cfg(feature = "bigfloat")]
+            Token::Big(num) => match self.notation {
+                Notation::Auto => self.localize(&num.to_string()),
+                _ => self.format_f64(num.to_f64().value()),
+            },
+            Token::List(list) => {
+                let separator = if self.decimal_mark == ',' { "; " } else { ", " };
+                let list: Vec<String> = list.iter().map(|token| self.format(token)).collect();
+                format!("[{}]", list.join(separator))
+            }
+            _ => token.to_string(),
+        }
+    }
+
+    pub fn format_f64(&self, num: f64) -> String {
+        if !num.is_finite() {
+            return Token::F(num).to_string();
+        }
+        let sign = if num.is_sign_negative() && num != 0.0 {
+            "-"
+        } else {
+            ""
+        };
+        let num = num.abs();
+        let digits = match self.notation {
+            Notation::Auto if num != 0.0 && !(1e-7..1e21).contains(&num) => format!("{num:e}"),
+            Notation::Auto => num.to_string(),
+            Notation::General(figures) => {
+                let figures = figures.max(1);
+                let (_, exponent) = scientific(num, figures);
+                if exponent < -4 || exponent >= figures as i32 {
+                    trim(&format!("{num:.*e}", figures - 1))
+                } else {
+                    let decimals = (figures as i32 - 1 - exponent).max(0) as usize;
+                    trim(&format!("{num:.decimals$}"))
+                }
+            }
+            Notation::Fixed(decimals) => format!("{num:.decimals$}"),
+            Notation::Significant(figures) => {
+                let figures = figures.max(1);
+                let (rounded, exponent) = scientific(num, figures);
+                let decimals = (figures as i32 - 1 - exponent).max(0) as usize;
+                format!("{rounded:.decimals$}")
+            }
+            Notation::Scientific(figures) => format!("{num:.*e}", figures.max(1) - 1),
+            Notation::Engineering(figures) => engineering(num, figures.max(1)),
+        };
+        format!("{sign}{}", self.localize(&digits))
+    }
+
+    /// Digits like `1234.5e3` with the marks and the groups
+    fn localize(&self, digits: &str) -> String {
+        let (mantissa, exponent) = match digits.split_once('e') {
+            Some((mantissa, exponent)) => (mantissa, format!("e{exponent}")),
+            None => (digits, String::new()),
+        };
+        let (whole, fraction) = match mantissa.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (mantissa, None),
+        };
+        let mut result = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if self.grouping && i > 0 && (whole.len() - i) % 3 == 0 {
+                result.push(self.separator);
+            }
+            result.push(digit);
+        }
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_mark);
+            result.push_str(fraction);
+        }
+        result + &exponent
+    }
+}
+
+/// The number rounded to the significant figures and its power of ten
+fn scientific(num: f64, figures: usize) -> (f64, i32) {
+    let digits = format!("{num:.*e}", figures - 1);
+    let (_, exponent) = digits.split_once('e').unwrap_or_default();
+    (
+        digits.parse().unwrap_or(num),
+        exponent.parse().unwrap_or_default(),
+    )
+}
+
+/// Without the zeros at the end of the decimals
+fn trim(digits: &str) -> String {
+    let (mantissa, exponent) = match digits.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, format!("e{exponent}")),
+        None => (digits, String::new()),
+    };
+    let mantissa = match mantissa.contains('.') {
+        true => mantissa.trim_end_matches('0').trim_end_matches('.'),
+        false => mantissa,
+    };
+    format!("{mantissa}{exponent}")
+}
+
+fn engineering(num: f64, figures: usize) -> String {
+    let digits = format!("{num:.*e}", figures - 1);
+    let (mantissa, exponent) = digits.split_once('e').unwrap_or_default();
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    let mut digits = mantissa.replace('.', "");
+    let shown = exponent.rem_euclid(3) as usize + 1;
+    // `1e4` with one figure is `10e3`
+    while digits.len() < shown {
+        digits.push('0');
+    }
+    let (whole, fraction) = digits.split_at(shown);
+    let exponent = exponent - exponent.rem_euclid(3);
+    match fraction.is_empty() {
+        true => format!("{whole}e{exponent}"),
+        false => format!("{whole}.{fraction}e{exponent}"),
+    }
+}
@@ -0,0 +1,906 @@
+use std::{cmp::Ordering, fmt::Display};
+
+#[cfg(feature = "bigfloat")]
+use crate::bigfloat;
+use crate::{
+    context::{Context, Radix},
+    executor::Executor,
+    finance, interval,
+    matrix::{self, Matrix},
+    number, numeric, polynomial, roots, stats, time,
+    token::Token,
+    uncertain,
+};
+
+/// Functions that are called with a list of arguments like `sum(1, 2, 3)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd)]
+pub enum Function {
+    Sum,
+    Product,
+    Mean,
+    Median,
+    Min,
+    Max,
+    Count,
+    Map,
+    Filter,
+
+    Var,
+    VarP,
+    Stdev,
+    StdevP,
+    Mode,
+    Percentile,
+    Quantile,
+    Covariance,
+    Correlation,
+    Linreg,
+    Zscore,
+    NormPdf,
+    NormCdf,
+    NormInv,
+
+    Pv,
+    Fv,
+    Pmt,
+    Nper,
+    Rate,
+    Npv,
+    Irr,
+    SimpleInterest,
+    CompoundInterest,
+    RoundTo,
+
+    Gcd,
+    Lcm,
+    IsPrime,
+    NextPrime,
+    Factor,
+    ModPow,
+    ModInv,
+    Totient,
+    Divisors,
+    Base,
+
+    Rand,
+    RandInt,
+    RandN,
+    Choice,
+
+    Det,
+    Inv,
+    Transpose,
+    Dot,
+    Cross,
+    Norm,
+    Identity,
+    Solve,
+
+    Ln,
+    Exp,
+    Pi,
+    Diff,
+    Integrate,
+    NDeriv,
+
+    Expand,
+    Coeffs,
+    Deg,
+    PolyDiv,
+    Roots,
+
+    Today,
+    Now,
+    Weekday,
+
+    Interval,
+    Lower,
+    Upper,
+    Mid,
+}
+
+impl Function {
+    pub const ALL: &'static [Function] = &[
+        Function::Sum,
+        Function::Product,
+        Function::Mean,
+        Function::Median,
+        Function::Min,
+        Function::Max,
+        Function::Count,
+        Function::Map,
+        Function::Filter,
+        Function::Var,
+        Function::VarP,
+        Function::Stdev,
+        Function::StdevP,
+        Function::Mode,
+        Function::Percentile,
+        Function::Quantile,
+        Function::Covariance,
+        Function::Correlation,
+        Function::Linreg,
+        Function::Zscore,
+        Function::NormPdf,
+        Function::NormCdf,
+        Function::NormInv,
+        Function::Pv,
+        Function::Fv,
+        Function::Pmt,
+        Function::Nper,
+        Function::Rate,
+        Function::Npv,
+        Function::Irr,
+        Function::SimpleInterest,
+        Function::CompoundInterest,
+        Function::RoundTo,
+        Function::Gcd,
+        Function::Lcm,
+        Function::IsPrime,
+        Function::NextPrime,
+        Function::Factor,
+        Function::ModPow,
+        Function::ModInv,
+        Function::Totient,
+        Function::Divisors,
+        Function::Base,
+        Function::Rand,
+        Function::RandInt,
+        Function::RandN,
+        Function::Choice,
+        Function::Det,
+        Function::Inv,
+        Function::Transpose,
+        Function::Dot,
+        Function::Cross,
+        Function::Norm,
+        Function::Identity,
+        Function::Solve,
+        Function::Ln,
+        Function::Exp,
+        Function::Pi,
+        Function::Diff,
+        Function::Integrate,
+        Function::NDeriv,
+        Function::Expand,
+        Function::Coeffs,
+        Function::Deg,
+        Function::PolyDiv,
+        Function::Roots,
+        Function::Today,
+        Function::Now,
+        Function::Weekday,
+        Function::Interval,
+        Function::Lower,
+        Function::Upper,
+        Function::Mid,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sum => "sum",
+            Function::Product => "product",
+            Function::Mean => "mean",
+            Function::Median => "median",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Count => "count",
+            Function::Map => "map",
+            Function::Filter => "filter",
+            Function::Var => "var",
+            Function::VarP => "varp",
+            Function::Stdev => "stdev",
+            Function::StdevP => "stdevp",
+            Function::Mode => "mode",
+            Function::Percentile => "percentile",
+            Function::Quantile => "quantile",
+            Function::Covariance => "covariance",
+            Function::Correlation => "correlation",
+            Function::Linreg => "linreg",
+            Function::Zscore => "zscore",
+            Function::NormPdf => "normpdf",
+            Function::NormCdf => "normcdf",
+            Function::NormInv => "norminv",
+            Function::Pv => "pv",
+            Function::Fv => "fv",
+            Function::Pmt => "pmt",
+            Function::Nper => "nper",
+            Function::Rate => "rate",
+            Function::Npv => "npv",
+            Function::Irr => "irr",
+            Function::SimpleInterest => "simple_interest",
+            Function::CompoundInterest => "compound_interest",
+            Function::RoundTo => "round_to",
+            Function::Gcd => "gcd",
+            Function::Lcm => "lcm",
+            Function::IsPrime => "isprime",
+            Function::NextPrime => "nextprime",
+            Function::Factor => "factor",
+            Function::ModPow => "modpow",
+            Function::ModInv => "modinv",
+            Function::Totient => "totient",
+            Function::Divisors => "divisors",
+            Function::Rand => "rand",
+            Function::RandInt => "randint",
+            Function::RandN => "randn",
+            Function::Choice => "choice",
+            Function::Base => "base",
+            Function::Det => "det",
+            Function::Inv => "inv",
+            Function::Transpose => "transpose",
+            Function::Dot => "dot",
+            Function::Cross => "cross",
+            Function::Norm => "norm",
+            Function::Identity => "identity",
+            Function::Solve => "solve",
+            Function::Ln => "ln",
+            Function::Exp => "exp",
+            Function::Pi => "pi",
+            Function::Diff => "diff",
+            Function::Integrate => "integrate",
+            Function::NDeriv => "nderiv",
+            Function::Expand => "expand",
+            Function::Coeffs => "coeffs",
+            Function::Deg => "deg",
+            Function::PolyDiv => "polydiv",
+            Function::Roots => "roots",
+            Function::Today => "today",
+            Function::Now => "now",
+            Function::Weekday => "weekday",
+            Function::Interval => "interval",
+            Function::Lower => "lower",
+            Function::Upper => "upper",
+            Function::Mid => "mid",
+        }
+    }
+
+    /// Gives a different result every time, the result cannot be cached
+    pub fn is_deterministic(self) -> bool {
+        !matches!(
+            self,
+            Function::Rand
+                | Function::RandInt
+                | Function::RandN
+                | Function::Choice
+                | Function::Today
+                | Function::Now
+        )
+    }
+
+    /// Is called with the arguments as they are written, like `diff(pow(x, 2), x)`
+    pub fn is_symbolic(self, args: &[Token]) -> bool {
+        match self {
+            Function::Diff | Function::Integrate | Function::NDeriv => true,
+            Function::Expand
+            | Function::Coeffs
+            | Function::Deg
+            | Function::PolyDiv
+            | Function::Roots => true,
+            // `factor(n)` of an integer is not symbolic
+            Function::Factor => args.iter().any(|arg| arg.find_var().is_some()),
+            // the series `sum(i, 1, n, expr)` binds `i`
+            Function::Sum | Function::Product => {
+                matches!(args, [Token::Var(_), _, _, _])
+            }
+            // `solve(A, b)` with matrices is not symbolic
+            Function::Solve => matches!(args.get(1), Some(Token::Var(_))),
+            _ => false,
+        }
+    }
+
+    /// The arguments that are needed are calculated
+    pub fn is_ready(self, args: &[Token]) -> bool {
+        match self {
+            Function::Solve | Function::Integrate | Function::NDeriv if self.is_symbolic(args) => {
+                args.iter().skip(2).all(Token::is_value)
+            }
+            Function::Sum | Function::Product if self.is_symbolic(args) => {
+                args[1].is_value() && args[2].is_value()
+            }
+            _ if self.is_symbolic(args) => true,
+            _ => args
+                .iter()
+                .all(|arg| arg.is_value() || matches!(arg, Token::Lambda(..))),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Function> {
+        match name {
+            // the short name of the series `prod(i, 1, n, expr)`
+            "prod" => Some(Function::Product),
+            _ => Self::ALL
+                .iter()
+                .copied()
+                .find(|function| function.name() == name),
+        }
+    }
+
+    /// Is called when all the arguments are calculated
+    ///
+    /// The result can still need calculation, like the list returned by `map`
+    pub fn call(self, mut args: Vec<Token>, context: &Context) -> Result<Token, String> {
+        match self {
+            Function::Sum | Function::Product if self.is_symbolic(&args) => {
+                series(self, args, context)
+            }
+            #[cfg(feature = "bigfloat")]
+            _ if context.precision.is_some() && bigfloat::is_big_call(self, &args) => {
+                bigfloat::call(self, args, context)
+            }
+            // a lambda is called with the uncertain values, like in `map`
+            _ if uncertain::has_uncertain(&args)
+                && !self.is_symbolic(&args)
+                && !args.iter().any(|arg| matches!(arg, Token::Lambda(..))) =>
+            {
+                uncertain::call(self, args, context)
+            }
+            Function::Sum
+            | Function::Product
+            | Function::Mean
+            | Function::Min
+            | Function::Max
+            | Function::Ln
+            | Function::Exp
+            | Function::Median
+                if has_interval(&args) =>
+            {
+                interval::call(self, args)
+            }
+            // the list is the first argument, like in `percentile(list, 25)`
+            Function::Percentile | Function::Quantile
+                if args.get(..1).is_some_and(has_interval) =>
+            {
+                interval::call(self, args)
+            }
+            Function::Sum => {
+                let list = numbers(self, args)?;
+                fold(self, &list, Token::I(0), Token::checked_add)
+            }
+            Function::Product => {
+                let list = numbers(self, args)?;
+                fold(self, &list, Token::I(1), Token::checked_mul)
+            }
+            Function::Mean => {
+                let list = not_empty(self, numbers(self, args)?)?;
+                let sum: f64 = list.iter().map(Token::get_f64).sum();
+                Ok(Token::F(sum / list.len() as f64))
+            }
+            Function::Median => {
+                let mut list = not_empty(self, numbers(self, args)?)?;
+                list.sort_by(|a, b| a.get_f64().total_cmp(&b.get_f64()));
+                let middle = list.len() / 2;
+                if list.len() % 2 == 1 {
+                    Ok(list.swap_remove(middle))
+                } else {
+                    let (a, b) = (list[middle - 1].get_f64(), list[middle].get_f64());
+                    Ok(Token::F((a + b) / 2.0))
+                }
+            }
+            Function::Min | Function::Max => {
+                let list = not_empty(self, numbers(self, args)?)?;
+                let order = if self == Function::Min {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                };
+                let mut best = list[0].clone();
+                for token in &list[1..] {
+                    if token.compare(&best, context)? == order {
+                        best = token.clone();
+                    }
+                }
+                Ok(best)
+            }
+            Function::Count => Ok(Token::I(list(self, args)?.len() as i64)),
+            Function::Map => {
+                let [list, lambda] = arguments::<2>(self, args)?;
+                let list = list.into_list(self)?;
+                Ok(Token::List(
+                    list.into_iter()
+                        .map(|token| lambda.apply(token))
+                        .collect::<Result<_, _>>()?,
+                ))
+            }
+            Function::Filter => {
+                let [list, predicate] = arguments::<2>(self, args)?;
+                let list = list.into_list(self)?;
+                match predicate {
+                    // first every element is given to the lambda, the results are calculated
+                    // and after that the elements are selected
+                    Token::Lambda(..) => {
+                        let results = list
+                            .iter()
+                            .map(|token| predicate.apply(token.clone()))
+                            .collect::<Result<_, _>>()?;
+                        args = vec![Token::List(list), Token::List(results)];
+                        Ok(Token::Call(self, args))
+                    }
+                    Token::List(results) => {
+                        let mut filtered = Vec::new();
+                        for (token, keep) in list.into_iter().zip(results) {
+                            if keep.get_bool()? {
+                                filtered.push(token)
+                            }
+                        }
+                        Ok(Token::List(filtered))
+                    }
+                    _ => Err(format!(
+                        "filter needs a lambda like: x -> x > 1, got: {predicate}"
+                    )),
+                }
+            }
+
+            Function::Var | Function::VarP => {
+                let sample = self == Function::Var;
+                Ok(Token::F(stats::variance(&floats(self, args)?, sample)?))
+            }
+            Function::Stdev | Function::StdevP => {
+                let sample = self == Function::Stdev;
+                Ok(Token::F(stats::stdev(&floats(self, args)?, sample)?))
+            }
+            Function::Mode => {
+                let list = not_empty(self, numbers(self, args)?)?;
+                let mode = stats::mode(&list.iter().map(Token::get_f64).collect::<Vec<_>>());
+                // keeps integers as integers, `NaN` is not equal to itself
+                list.into_iter()
+                    .find(|t| t.get_f64() == mode)
+                    .ok_or_else(|| format!("{self} only works with numbers, got: {mode}"))
+            }
+            Function::Percentile | Function::Quantile => {
+                let [list, q] = arguments::<2>(self, args)?;
+                let list = not_empty(self, numbers(self, vec![list])?)?;
+                let list: Vec<f64> = list.iter().map(Token::get_f64).collect();
+                let mut q = float(self, &q)?;
+                if self == Function::Percentile {
+                    q /= 100.0;
+                }
+                Ok(Token::F(stats::quantile(&list, q)?))
+            }
+            Function::Covariance | Function::Correlation | Function::Linreg => {
+                let [xs, ys] = arguments::<2>(self, args)?;
+                let (xs, ys) = (floats(self, vec![xs])?, floats(self, vec![ys])?);
+                match self {
+                    Function::Covariance => Ok(Token::F(stats::covariance(&xs, &ys)?)),
+                    Function::Correlation => Ok(Token::F(stats::correlation(&xs, &ys)?)),
+                    _ => {
+                        let (slope, intercept) = stats::linreg(&xs, &ys)?;
+                        Ok(Token::List(vec![Token::F(slope), Token::F(intercept)]))
+                    }
+                }
+            }
+            Function::Zscore => match args.as_slice() {
+                [Token::List(_)] => {
+                    let list = floats(self, args)?;
+                    let (mean, stdev) = (stats::mean(&list), stats::stdev(&list, true)?);
+                    Ok(Token::List(
+                        list.iter().map(|x| Token::F((x - mean) / stdev)).collect(),
+                    ))
+                }
+                [x, mean, stdev] => {
+                    let (x, mean, stdev) =
+                        (float(self, x)?, float(self, mean)?, float(self, stdev)?);
+                    Ok(Token::F((x - mean) / stdev))
+                }
+                _ => Err(format!(
+                    "{self} needs a list or 3 arguments: zscore(x, mean, stdev)"
+                )),
+            },
+            Function::NormPdf | Function::NormCdf | Function::NormInv => {
+                let (x, mu, sigma) = match args.as_slice() {
+                    [x] => (float(self, x)?, 0.0, 1.0),
+                    [x, mu, sigma] => (float(self, x)?, float(self, mu)?, float(self, sigma)?),
+                    _ => {
+                        return Err(format!(
+                            "{self} needs 1 or 3 arguments: {self}(x, mean, stdev)"
+                        ))
+                    }
+                };
+                if sigma <= 0.0 {
+                    return Err(format!("{self} needs a positive stdev, got: {sigma}"));
+                }
+                match self {
+                    Function::NormPdf => Ok(Token::F(stats::normal_pdf(x, mu, sigma))),
+                    Function::NormCdf => Ok(Token::F(stats::normal_cdf(x, mu, sigma))),
+                    _ => Ok(Token::F(stats::normal_inv(x, mu, sigma)?)),
+                }
+            }
+
+            Function::Pv | Function::Fv | Function::Pmt | Function::Nper => {
+                let [a, b, c, d, when] = optional(self, args, [0.0, 0.0, 0.0, 0.0, 0.0], 3)?;
+                Ok(Token::F(match self {
+                    Function::Pv => finance::pv(a, b, c, d, when),
+                    Function::Fv => finance::fv(a, b, c, d, when),
+                    Function::Pmt => finance::pmt(a, b, c, d, when),
+                    _ => finance::nper(a, b, c, d, when)?,
+                }))
+            }
+            Function::Rate => {
+                let [nper, pmt, pv, fv, when, guess] =
+                    optional(self, args, [0.0, 0.0, 0.0, 0.0, 0.0, 0.1], 3)?;
+                Ok(Token::F(finance::rate(nper, pmt, pv, fv, when, guess)?))
+            }
+            Function::Npv => {
+                if args.len() < 2 {
+                    return Err(format!(
+                        "{self} needs a rate and the values: npv(rate, values)"
+                    ));
+                }
+                let rate = float(self, &args.remove(0))?;
+                Ok(Token::F(finance::npv(rate, &floats(self, args)?)))
+            }
+            Function::Irr => {
+                let guess = match args.len() {
+                    1 => 0.1,
+                    2 => float(self, &args.pop().unwrap())?,
+                    len => return Err(format!("{self} needs 1 or 2 arguments, got: {len}")),
+                };
+                Ok(Token::F(finance::irr(&floats(self, args)?, guess)?))
+            }
+            Function::SimpleInterest => {
+                let [principal, rate, time] = optional(self, args, [0.0; 3], 3)?;
+                Ok(Token::F(finance::simple_interest(principal, rate, time)))
+            }
+            Function::CompoundInterest => {
+                let [principal, rate, time, periods] =
+                    optional(self, args, [0.0, 0.0, 0.0, 1.0], 3)?;
+                Ok(Token::F(finance::compound_interest(
+                    principal, rate, time, periods,
+                )))
+            }
+            Function::RoundTo => {
+                let [x, step] = optional(self, args, [0.0; 2], 2)?;
+                Ok(Token::F(finance::round_to(x, step)?))
+            }
+
+            Function::Gcd | Function::Lcm => {
+                let list = not_empty(self, integers(self, args)?)?;
+                let too_big = || format!("{self} is too big for an integer");
+                let result = match self {
+                    Function::Gcd => list.iter().fold(0, |result, num| number::gcd(result, *num)),
+                    _ => {
+                        let first = list[0].checked_abs().ok_or_else(too_big)?;
+                        list[1..]
+                            .iter()
+                            .try_fold(first, |result, num| number::lcm(result, *num))?
+                    }
+                };
+                // the gcd of `-2^63` and 0 is 2^63
+                if result < 0 {
+                    return Err(too_big());
+                }
+                Ok(Token::I(result))
+            }
+            Function::IsPrime => {
+                let [n] = arguments::<1>(self, args)?;
+                Ok(Token::Bool(number::is_prime(integer(self, &n)?)))
+            }
+            Function::NextPrime => {
+                let [n] = arguments::<1>(self, args)?;
+                Ok(Token::I(number::next_prime(integer(self, &n)?)?))
+            }
+            Function::Factor if self.is_symbolic(&args) => polynomial::call(self, args, context),
+            Function::Factor | Function::Divisors | Function::Totient => {
+                let [n] = arguments::<1>(self, args)?;
+                let n = integer(self, &n)?;
+                let list = match self {
+                    Function::Factor => number::factor(n)?,
+                    Function::Divisors => number::divisors(n)?,
+                    _ => return Ok(Token::I(number::totient(n)?)),
+                };
+                Ok(Token::List(list.into_iter().map(Token::I).collect()))
+            }
+            // the base after `to`, like `255 to base(36)`
+            Function::Base => {
+                let [base] = arguments::<1>(self, args)?;
+                Ok(Token::Base(Radix::from_base(integer(self, &base)?)?))
+            }
+            Function::ModPow => {
+                let [base, exp, m] = arguments::<3>(self, args)?;
+                let (base, exp, m) = (
+                    integer(self, &base)?,
+                    integer(self, &exp)?,
+                    integer(self, &m)?,
+                );
+                Ok(Token::I(number::modpow(base, exp, m)?))
+            }
+            Function::ModInv => {
+                let [a, m] = arguments::<2>(self, args)?;
+                let (a, m) = (integer(self, &a)?, integer(self, &m)?);
+                Ok(Token::I(number::modinv(a, m)?))
+            }
+
+            Function::Rand => {
+                arguments::<0>(self, args)?;
+                Ok(Token::F(context.rng.next_f64()))
+            }
+            Function::RandInt => {
+                let [a, b] = arguments::<2>(self, args)?;
+                let (a, b) = (integer(self, &a)?, integer(self, &b)?);
+                if a > b {
+                    return Err(format!("{self} needs a <= b, got: {a} and {b}"));
+                }
+                Ok(Token::I(context.rng.range(a, b)))
+            }
+            Function::RandN => {
+                let [mu, sigma] = optional(self, args, [0.0, 1.0], 0)?;
+                if sigma < 0.0 {
+                    return Err(format!("{self} needs a positive stdev, got: {sigma}"));
+                }
+                Ok(Token::F(mu + sigma * context.rng.normal()))
+            }
+            Function::Choice => {
+                let mut list = not_empty(self, list(self, args)?)?;
+                let index = context.rng.range(0, list.len() as i64 - 1);
+                Ok(list.swap_remove(index as usize))
+            }
+
+            Function::Det => {
+                let [a] = arguments::<1>(self, args)?;
+                Ok(Token::F(to_matrix(self, a)?.det()?))
+            }
+            Function::Inv => {
+                let [a] = arguments::<1>(self, args)?;
+                Ok(Token::Matrix(to_matrix(self, a)?.inv()?))
+            }
+            Function::Transpose => match arguments::<1>(self, args)? {
+                // a vector becomes a column
+                [Token::List(list)] => {
+                    let column = floats(self, vec![Token::List(list)])?;
+                    Ok(Token::Matrix(Matrix::new(column.len(), 1, column)?))
+                }
+                [a] => Ok(Token::Matrix(to_matrix(self, a)?.transpose())),
+            },
+            Function::Dot | Function::Cross => {
+                let [a, b] = arguments::<2>(self, args)?;
+                let (a, b) = (vector(self, a)?, vector(self, b)?);
+                if self == Function::Dot {
+                    Ok(Token::F(matrix::dot(&a, &b)?))
+                } else {
+                    let cross = matrix::cross(&a, &b)?;
+                    Ok(Token::List(cross.into_iter().map(Token::F).collect()))
+                }
+            }
+            Function::Norm => match arguments::<1>(self, args)? {
+                [Token::Matrix(a)] => Ok(Token::F(a.norm())),
+                [a] => {
+                    let a = vector(self, a)?;
+                    Ok(Token::F(matrix::dot(&a, &a)?.sqrt()))
+                }
+            },
+            Function::Identity => {
+                let [n] = arguments::<1>(self, args)?;
+                let n = integer(self, &n)?;
+                if !(1..=MAX_MATRIX).contains(&n) {
+                    return Err(format!(
+                        "{self} needs a size between 1 and {MAX_MATRIX}, got: {n}"
+                    ));
+                }
+                Ok(Token::Matrix(Matrix::identity(n as usize)))
+            }
+            Function::Solve if self.is_symbolic(&args) => roots::solve(args, context),
+            Function::Solve => {
+                let [a, b] = arguments::<2>(self, args)?;
+                let a = to_matrix(self, a)?;
+                match b {
+                    Token::Matrix(b) => Ok(Token::Matrix(a.solve(&b)?)),
+                    b => {
+                        let b = vector(self, b)?;
+                        let x = a.solve_vector(&b)?;
+                        Ok(Token::List(x.into_iter().map(Token::F).collect()))
+                    }
+                }
+            }
+
+            Function::Ln => {
+                let [x] = arguments::<1>(self, args)?;
+                Ok(Token::F(float(self, &x)?.ln()))
+            }
+            Function::Exp => {
+                let [x] = arguments::<1>(self, args)?;
+                Ok(Token::F(float(self, &x)?.exp()))
+            }
+            Function::Pi => {
+                arguments::<0>(self, args)?;
+                Ok(Token::F(std::f64::consts::PI))
+            }
+            // `diff(expr, x)` gives the lambda `x -> derivative`, with a third argument
+            // the derivative is calculated at that point
+            Function::Diff => {
+                let (expr, var, at) = match args.as_slice() {
+                    [Token::Lambda(var, expr)] => (expr.as_ref(), var.as_str(), None),
+                    [Token::Lambda(var, expr), Token::Var(name)] if var == name => {
+                        (expr.as_ref(), var.as_str(), None)
+                    }
+                    [Token::Lambda(var, expr), at] => (expr.as_ref(), var.as_str(), Some(at)),
+                    [expr, Token::Var(var)] => (expr, var.as_str(), None),
+                    [expr, Token::Var(var), at] => (expr, var.as_str(), Some(at)),
+                    _ => {
+                        return Err(format!(
+                            "{self} needs an expression and a variable: diff(pow(x, 2), x)"
+                        ))
+                    }
+                };
+                let derivative = Token::Lambda(var.to_string(), Box::new(expr.derivative(var)?));
+                match at {
+                    Some(at) => derivative.apply(at.clone()),
+                    None => Ok(derivative),
+                }
+            }
+            Function::Integrate | Function::NDeriv => numeric::call(self, args, context),
+            Function::Expand
+            | Function::Coeffs
+            | Function::Deg
+            | Function::PolyDiv
+            | Function::Roots => polynomial::call(self, args, context),
+            Function::Today | Function::Now | Function::Weekday => time::call(self, args, context),
+            Function::Interval | Function::Lower | Function::Upper | Function::Mid => {
+                interval::call(self, args)
+            }
+        }
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Takes exactly `N` arguments
+pub fn arguments<const N: usize>(
+    function: Function,
+    args: Vec<Token>,
+) -> Result<[Token; N], String> {
+    let len = args.len();
+    args.try_into()
+        .map_err(|_| format!("{function} needs {N} arguments, got: {len}"))
+}
+
+/// The arguments can be a single list or more values, `sum([1, 2])` is the same as `sum(1, 2)`
+pub fn list(function: Function, mut args: Vec<Token>) -> Result<Vec<Token>, String> {
+    if args.len() == 1 && matches!(args[0], Token::List(_)) {
+        args.pop().unwrap().into_list(function)
+    } else {
+        Ok(args)
+    }
+}
+
+/// An interval is in the arguments or in the list that is the only argument
+fn has_interval(args: &[Token]) -> bool {
+    args.iter().any(|arg| match arg {
+        Token::Interval(_) => true,
+        Token::List(list) => {
+            args.len() == 1 && list.iter().any(|t| matches!(t, Token::Interval(_)))
+        }
+        _ => false,
+    })
+}
+
+pub fn numbers(function: Function, args: Vec<Token>) -> Result<Vec<Token>, String> {
+    let list = list(function, args)?;
+    match list.iter().find(|token| !token.is_num()) {
+        Some(token) => Err(format!("{function} only works with numbers, got: {token}")),
+        None => Ok(list),
+    }
+}
+
+pub fn float(function: Function, token: &Token) -> Result<f64, String> {
+    if token.is_num() {
+        Ok(token.get_f64())
+    } else {
+        Err(format!("{function} only works with numbers, got: {token}"))
+    }
+}
+
+pub fn floats(function: Function, args: Vec<Token>) -> Result<Vec<f64>, String> {
+    Ok(numbers(function, args)?
+        .iter()
+        .map(Token::get_f64)
+        .collect())
+}
+
+/// Takes at least `required` numbers, the missing ones are taken from `defaults`
+pub fn optional<const N: usize>(
+    function: Function,
+    args: Vec<Token>,
+    mut defaults: [f64; N],
+    required: usize,
+) -> Result<[f64; N], String> {
+    let len = args.len();
+    if len < required || len > N {
+        return Err(if required == N {
+            format!("{function} needs {N} arguments, got: {len}")
+        } else {
+            format!("{function} needs {required} to {N} arguments, got: {len}")
+        });
+    }
+    for (default, token) in defaults.iter_mut().zip(&args) {
+        *default = float(function, token)?;
+    }
+    Ok(defaults)
+}
+
+pub fn integer(function: Function, token: &Token) -> Result<i64, String> {
+    match token {
+        Token::I(num) => Ok(*num),
+        _ => Err(format!("{function} only works with integers, got: {token}")),
+    }
+}
+
+pub fn integers(function: Function, args: Vec<Token>) -> Result<Vec<i64>, String> {
+    list(function, args)?
+        .iter()
+        .map(|token| integer(function, token))
+        .collect()
+}
+
+/// The biggest size for `identity(n)`
+const MAX_MATRIX: i64 = 1000;
+
+pub fn to_matrix(function: Function, token: Token) -> Result<Matrix, String> {
+    match token {
+        Token::Matrix(matrix) => Ok(matrix),
+        _ => Err(format!(
+            "{function} needs a matrix like [[1, 2], [3, 4]], got: {token}"
+        )),
+    }
+}
+
+pub fn vector(function: Function, token: Token) -> Result<Vec<f64>, String> {
+    match token {
+        Token::List(list) => floats(function, vec![Token::List(list)]),
+        _ => Err(format!(
+            "{function} needs a vector like [1, 2, 3], got: {token}"
+        )),
+    }
+}
+
+pub fn not_empty<T>(function: Function, list: Vec<T>) -> Result<Vec<T>, String> {
+    if list.is_empty() {
+        Err(format!("{function} needs at least one number"))
+    } else {
+        Ok(list)
+    }
+}
+
+/// Adds or multiplies two numbers, `None` when integers overflow
+type Checked = fn(Token, Token) -> Option<Token>;
+
+/// The most terms of a series like `sum(i, 1, n, expr)`
+const MAX_SERIES: i64 = 100_000;
+
+/// `sum(i, 1, n, expr)` adds and `prod(i, 1, n, expr)` multiplies the expression
+/// for every integer from the start to the end, an empty series is 0 or 1
+fn series(function: Function, args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    let [Token::Var(var), start, end, expr] = arguments::<4>(function, args)? else {
+        return Err(format!(
+            "{function} needs a variable first like: {function}(i, 1, 10, i * i)"
+        ));
+    };
+    let (start, end) = (integer(function, &start)?, integer(function, &end)?);
+    let terms = (end as i128 - start as i128 + 1).max(0);
+    if terms > MAX_SERIES as i128 {
+        return Err(format!(
+            "{function} can have at most {MAX_SERIES} terms, got: {terms}"
+        ));
+    }
+    let (init, f): (Token, Checked) = match function {
+        Function::Product => (Token::I(1), Token::checked_mul),
+        _ => (Token::I(0), Token::checked_add),
+    };
+    (start..=end).try_fold(init, |result, i| {
+        let term = Executor::evaluate(expr.substitute(&var, &Token::I(i)), context)?;
+        if !term.is_num() {
+            return Err(format!("{function} only works with numbers, got: {term}"));
+        }
+        f(result, term).ok_or_else(|| format!("The {function} is too big for an integer"))
+    })
+}
+
+/// An error when integers overflow, like `product(1..30)`
+fn fold(function: Function, list: &[Token], init: Token, f: Checked) -> Result<Token, String> {
+    list.iter()
+        .cloned()
+        .try_fold(init, f)
+        .ok_or_else(|| format!("The {function} is too big for an integer"))
+}
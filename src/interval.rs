@@ -0,0 +1,467 @@
+use std::{
+    f64::consts::{FRAC_PI_2, PI, TAU},
+    fmt::Display,
+    ops::{Add, Mul, Neg, Sub},
+};
+
+use crate::{
+    function::{arguments, float, list, not_empty, Function},
+    stats,
+    token::Token,
+};
+
+/// A closed interval of floats, every operation rounds the bounds outwards so that
+/// the exact result is always inside
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Result<Self, String> {
+        if lo.is_nan() || hi.is_nan() || lo > hi {
+            return Err(format!(
+                "An interval needs the lower bound first, got: {lo} and {hi}"
+            ));
+        }
+        Ok(Self { lo, hi })
+    }
+
+    /// An interval with only one number
+    pub fn point(x: f64) -> Self {
+        Self { lo: x, hi: x }
+    }
+
+    /// Every number, the result of dividing by an interval with 0 inside
+    pub fn entire() -> Self {
+        Self {
+            lo: f64::NEG_INFINITY,
+            hi: f64::INFINITY,
+        }
+    }
+
+    pub fn lo(&self) -> f64 {
+        self.lo
+    }
+
+    pub fn hi(&self) -> f64 {
+        self.hi
+    }
+
+    /// Halves first, the sum of the bounds can be too big for a float
+    pub fn mid(&self) -> f64 {
+        self.lo / 2.0 + self.hi / 2.0
+    }
+
+    pub fn width(&self) -> f64 {
+        (self.hi - self.lo).next_up()
+    }
+
+    pub fn contains(&self, x: f64) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    /// Moves the bounds `ulps` floats outwards, the functions of the standard library
+    /// like `sin` are off by a few floats
+    fn outward(lo: f64, hi: f64, ulps: usize) -> Self {
+        let (mut lo, mut hi) = (lo, hi);
+        for _ in 0..ulps {
+            lo = lo.next_down();
+            hi = hi.next_up();
+        }
+        Self { lo, hi }
+    }
+
+    /// The smallest interval around every value
+    fn hull(values: &[f64], ulps: usize) -> Self {
+        let lo = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let hi = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Self::outward(lo, hi, ulps)
+    }
+
+    /// When the divisor has 0 inside the result can be half of the numbers or every
+    /// number, only dividing by exactly 0 is an error
+    pub fn divide(self, other: Self) -> Result<Self, String> {
+        if other.lo == 0.0 && other.hi == 0.0 {
+            return Err(format!("Cannot divide {self} by {other}"));
+        }
+        if !other.contains(0.0) {
+            let quotients = [
+                quotient(self.lo, other.lo),
+                quotient(self.lo, other.hi),
+                quotient(self.hi, other.lo),
+                quotient(self.hi, other.hi),
+            ];
+            return Ok(Self {
+                lo: quotients.iter().map(|q| q.0).fold(f64::INFINITY, f64::min),
+                hi: quotients
+                    .iter()
+                    .map(|q| q.1)
+                    .fold(f64::NEG_INFINITY, f64::max),
+            });
+        }
+        if self.contains(0.0) || (other.lo < 0.0 && other.hi > 0.0) {
+            return Ok(Self::entire());
+        }
+        let (lo, hi) = match (other.lo == 0.0, self.lo > 0.0) {
+            // divided by `[0, hi]`
+            (true, true) => (quotient(self.lo, other.hi).0, f64::INFINITY),
+            (true, false) => (f64::NEG_INFINITY, quotient(self.hi, other.hi).1),
+            // divided by `[lo, 0]`
+            (false, true) => (f64::NEG_INFINITY, quotient(self.lo, other.lo).1),
+            (false, false) => (quotient(self.hi, other.lo).0, f64::INFINITY),
+        };
+        Ok(Self { lo, hi })
+    }
+
+    pub fn powi(self, n: i64) -> Result<Self, String> {
+        if n.unsigned_abs() > u32::MAX as u64 {
+            return Err(format!("The power is too big for an interval, got: {n}"));
+        }
+        if n < 0 {
+            return Self::point(1.0).divide(self.powi(-n)?);
+        }
+        // squaring the bounds keeps the rounding of every product
+        let power = |x: f64| {
+            let (mut result, mut base, mut n) = (Self::point(1.0), Self::point(x), n);
+            while n > 0 {
+                if n & 1 == 1 {
+                    result = result * base;
+                }
+                base = base * base;
+                n >>= 1;
+            }
+            result
+        };
+        Ok(if n % 2 == 1 || self.lo >= 0.0 {
+            Self {
+                lo: power(self.lo).lo,
+                hi: power(self.hi).hi,
+            }
+        } else if self.hi <= 0.0 {
+            Self {
+                lo: power(self.hi).lo,
+                hi: power(self.lo).hi,
+            }
+        } else {
+            // an even power of an interval with 0 inside
+            Self {
+                lo: 0.0,
+                hi: power(self.lo.abs().max(self.hi)).hi,
+            }
+        })
+    }
+    /// `pow(a, b)` for a positive base, the biggest and smallest values are at the
+    /// corners because the power only goes up or only goes down for each argument
+    pub fn pow(self, exp: Self) -> Result<Self, String> {
+        if exp.lo == exp.hi && exp.lo.fract() == 0.0 && exp.lo.abs() < 1e9 {
+            return self.powi(exp.lo as i64);
+        }
+        if self.lo < 0.0 {
+            return Err(format!(
+                "pow needs a base that is not negative for the power {exp}, got: {self}"
+            ));
+        }
+        if self.lo == 0.0 && exp.lo <= 0.0 {
+            return Err(format!("pow of {self} by {exp} is not finite"));
+        }
+        let corner = |x: f64, y: f64| match (x, y) {
+            // exact powers
+            (1.0, _) | (_, 0.0) => Self::point(1.0),
+            (_, 1.0) => Self::point(x),
+            _ => Self::outward(x.powf(y), x.powf(y), 4),
+        };
+        let corners = [
+            corner(self.lo, exp.lo),
+            corner(self.lo, exp.hi),
+            corner(self.hi, exp.lo),
+            corner(self.hi, exp.hi),
+        ];
+        Ok(Self {
+            lo: corners
+                .iter()
+                .map(|c| c.lo)
+                .fold(f64::INFINITY, f64::min)
+                .max(0.0),
+            hi: corners
+                .iter()
+                .map(|c| c.hi)
+                .fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+
+    pub fn sqrt(self) -> Result<Self, String> {
+        if self.hi < 0.0 {
+            return Err(format!(
+                "sqrt needs numbers that are not negative, got: {self}"
+            ));
+        }
+        let root = |x: f64| {
+            let s = x.sqrt();
+            // the exact root minus `s` has the sign of `x - s * s`
+            let error = (-s).mul_add(s, x);
+            (down(s, error), up(s, error))
+        };
+        Ok(Self {
+            lo: root(self.lo.max(0.0)).0.max(0.0),
+            hi: root(self.hi).1,
+        })
+    }
+
+    pub fn ln(self) -> Result<Self, String> {
+        if self.hi <= 0.0 {
+            return Err(format!("ln needs positive numbers, got: {self}"));
+        }
+        let result = Self::outward(self.lo.ln(), self.hi.ln(), 2);
+        Ok(Self {
+            // `ln(0)` is `-inf` and `ln(1)` is exactly 0
+            lo: match self.lo {
+                lo if lo <= 0.0 => f64::NEG_INFINITY,
+                1.0 => 0.0,
+                _ => result.lo,
+            },
+            hi: if self.hi == 1.0 { 0.0 } else { result.hi },
+        })
+    }
+
+    pub fn exp(self) -> Self {
+        let result = Self::outward(self.lo.exp(), self.hi.exp(), 2);
+        Self {
+            // `exp(0)` is exactly 1
+            lo: if self.lo == 0.0 {
+                1.0
+            } else {
+                result.lo.max(0.0)
+            },
+            hi: if self.hi == 0.0 { 1.0 } else { result.hi },
+        }
+    }
+
+    /// The quantile of the lower bounds to the quantile of the upper bounds, a quantile
+    /// only goes up when one of the numbers goes up
+    pub fn quantile(list: &[Self], q: f64) -> Result<Self, String> {
+        let bounds: (Vec<f64>, Vec<f64>) = list.iter().map(|x| (x.lo, x.hi)).unzip();
+        let (lo, hi) = (
+            stats::quantile(&bounds.0, q)?,
+            stats::quantile(&bounds.1, q)?,
+        );
+        // only the interpolation between two numbers is not exact
+        let exact = (q * (list.len() - 1) as f64).fract() == 0.0;
+        Ok(Self::outward(lo, hi, if exact { 0 } else { 2 }))
+    }
+
+    pub fn sin(self) -> Self {
+        // sin has its biggest value at pi / 2 and its smallest at -pi / 2
+        self.periodic(f64::sin, FRAC_PI_2, -FRAC_PI_2)
+    }
+
+    pub fn cos(self) -> Self {
+        self.periodic(f64::cos, 0.0, PI)
+    }
+
+    /// A function with the period 2 pi, `max` and `min` are where it is 1 and -1
+    fn periodic(self, f: fn(f64) -> f64, max: f64, min: f64) -> Self {
+        let everything = Self { lo: -1.0, hi: 1.0 };
+        if !self.lo.is_finite() || !self.hi.is_finite() || self.hi - self.lo >= TAU {
+            return everything;
+        }
+        // pi is not exact, so the points are checked with some room
+        let room = 1e-12 * self.lo.abs().max(self.hi.abs()).max(1.0);
+        let has = |point: f64| {
+            let k = ((self.lo - room - point) / TAU).ceil();
+            point + k * TAU <= self.hi + room
+        };
+        let result = Self::hull(&[f(self.lo), f(self.hi)], 2);
+        Self {
+            lo: if has(min) { -1.0 } else { result.lo.max(-1.0) },
+            hi: if has(max) { 1.0 } else { result.hi.min(1.0) },
+        }
+    }
+
+    /// The smaller number of every pair of numbers from the intervals
+    pub fn min(self, other: Self) -> Self {
+        Self {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.min(other.hi),
+        }
+    }
+
+    /// The bigger number of every pair of numbers from the intervals
+    pub fn max(self, other: Self) -> Self {
+        Self {
+            lo: self.lo.max(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lo: down(self.lo + other.lo, add_error(self.lo, other.lo)),
+            hi: up(self.hi + other.hi, add_error(self.hi, other.hi)),
+        }
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, other: Self) -> Self {
+        self + -other
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, other: Self) -> Self {
+        let product = |a: f64, b: f64| match a * b {
+            // `0 * inf` is `NaN`, but the product of 0 with any number in the interval is 0
+            p if p.is_nan() => (0.0, 0.0),
+            p => (down(p, a.mul_add(b, -p)), up(p, a.mul_add(b, -p))),
+        };
+        let products = [
+            product(self.lo, other.lo),
+            product(self.lo, other.hi),
+            product(self.hi, other.lo),
+            product(self.hi, other.hi),
+        ];
+        Self {
+            lo: products.iter().map(|p| p.0).fold(f64::INFINITY, f64::min),
+            hi: products
+                .iter()
+                .map(|p| p.1)
+                .fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Self {
+        Self {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+/// The exact result of `a + b` minus the rounded one, with the TwoSum algorithm
+fn add_error(a: f64, b: f64) -> f64 {
+    let sum = a + b;
+    let b_part = sum - a;
+    (a - (sum - b_part)) + (b - b_part)
+}
+
+/// The floats around `a / b`, the exact quotient minus the rounded one has the
+/// sign of the remainder over `b`
+fn quotient(a: f64, b: f64) -> (f64, f64) {
+    let q = a / b;
+    let error = (-q).mul_add(b, a) * b.signum();
+    (down(q, error), up(q, error))
+}
+
+/// The float at or below the exact result, `error` is the exact result minus the
+/// rounded one and `NaN` when it is not known
+fn down(result: f64, error: f64) -> f64 {
+    if error < 0.0 || error.is_nan() {
+        result.next_down()
+    } else {
+        result
+    }
+}
+
+fn up(result: f64, error: f64) -> f64 {
+    if error > 0.0 || error.is_nan() {
+        result.next_up()
+    } else {
+        result
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "interval({}, {})", self.lo, self.hi)
+    }
+}
+
+/// The interval from the numbers as they are written, a float like 0.1 is not
+/// exact so its bound is moved outwards
+pub fn from_bounds(lo: &Token, hi: &Token) -> Option<Result<Interval, String>> {
+    let bound = |token: &Token, outward: fn(f64) -> f64| match token {
+        Token::F(num) => Some(Interval::point(outward(*num))),
+        _ => token.to_interval(),
+    };
+    let (lo, hi) = (bound(lo, f64::next_down)?, bound(hi, f64::next_up)?);
+    Some(Interval::new(lo.lo(), hi.hi()))
+}
+
+/// `interval(lo, hi)`, `lower`, `upper`, `mid` and the functions that also work
+/// with intervals like `sum`, `ln` and `median`
+pub fn call(function: Function, args: Vec<Token>) -> Result<Token, String> {
+    let to_interval = |token: &Token| {
+        token.to_interval().ok_or_else(|| {
+            format!("{function} only works with numbers and intervals, got: {token}")
+        })
+    };
+    if function == Function::Interval {
+        let (lo, hi) = match args.as_slice() {
+            [x] => (x, x),
+            [lo, hi] => (lo, hi),
+            _ => {
+                return Err(format!(
+                    "{function} needs a number or the bounds like: interval(1.9, 2.1)"
+                ))
+            }
+        };
+        return match from_bounds(lo, hi) {
+            Some(interval) => Ok(Token::Interval(interval?)),
+            None => Err(format!(
+                "{function} only works with numbers, got: {lo} and {hi}"
+            )),
+        };
+    }
+    if matches!(function, Function::Percentile | Function::Quantile) {
+        let [values, q] = arguments::<2>(function, args)?;
+        let list = not_empty(function, list(function, vec![values])?)?
+            .iter()
+            .map(to_interval)
+            .collect::<Result<Vec<Interval>, String>>()?;
+        let mut q = float(function, &q)?;
+        if function == Function::Percentile {
+            q /= 100.0;
+        }
+        return Ok(Token::Interval(Interval::quantile(&list, q)?));
+    }
+    let list = not_empty(function, list(function, args)?)?
+        .iter()
+        .map(to_interval)
+        .collect::<Result<Vec<Interval>, String>>()?;
+    let one = |list: &[Interval]| match list {
+        [x] => Ok(*x),
+        _ => Err(format!("{function} needs 1 argument, got: {}", list.len())),
+    };
+    Ok(match function {
+        Function::Lower => Token::F(one(&list)?.lo()),
+        Function::Upper => Token::F(one(&list)?.hi()),
+        Function::Mid => Token::F(one(&list)?.mid()),
+        Function::Ln => Token::Interval(one(&list)?.ln()?),
+        Function::Exp => Token::Interval(one(&list)?.exp()),
+        Function::Median => Token::Interval(Interval::quantile(&list, 0.5)?),
+        Function::Product => Token::Interval(list[1..].iter().fold(list[0], |a, b| a * *b)),
+        Function::Min => Token::Interval(list[1..].iter().fold(list[0], |a, b| a.min(*b))),
+        Function::Max => Token::Interval(list[1..].iter().fold(list[0], |a, b| a.max(*b))),
+        _ => {
+            let sum = list[1..].iter().fold(list[0], |a, b| a + *b);
+            match function {
+                Function::Mean => Token::Interval(sum.divide(Interval::point(list.len() as f64))?),
+                _ => Token::Interval(sum),
+            }
+        }
+    })
+}
@@ -1,256 +1,907 @@
-use crate::token::Token;
+use std::fmt::Display;
+
+use crate::{
+    ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp},
+    context::Radix,
+    function::Function,
+    money::{Currency, Money, Rational},
+    time::{self, Duration},
+    value::Value,
+};
+
+/// An operator, a bracket or a keyword that is not in the tree yet, like `+` or `(`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Symbol {
+    Add,
+    Div,
+    Sub,
+    Mul,
+    ElemMul,
+    PlusMinus,
+    In,
+
+    Sin,
+    Cos,
+    Pow,
+    Sqrt,
+
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+    BitNot,
+
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Not,
+
+    If,
+    Question,
+    Colon,
+
+    Neg,
+    ListBegin,
+    ListEnd,
+    Range,
+    Step,
+    Arrow,
+    Equation,
+    Call(Function),
+
+    GroupBegin,
+    GroupEnd,
+}
+
+/// A token of the lexer, the symbols are reduced to expressions while the formula is parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Lexeme {
+    Symbol(Symbol, Span),
+    Expr(Expr),
+}
+
+impl Lexeme {
+    pub fn span(&self) -> Span {
+        match self {
+            Lexeme::Symbol(_, span) => *span,
+            Lexeme::Expr(expr) => expr.span,
+        }
+    }
+
+    pub fn is_expr(&self) -> bool {
+        matches!(self, Lexeme::Expr(_))
+    }
+
+    fn symbol(&self) -> Option<Symbol> {
+        match self {
+            Lexeme::Symbol(symbol, _) => Some(*symbol),
+            Lexeme::Expr(_) => None,
+        }
+    }
+
+    fn kind(&self) -> Option<&ExprKind> {
+        match self {
+            Lexeme::Expr(expr) => Some(&expr.kind),
+            Lexeme::Symbol(..) => None,
+        }
+    }
+}
 
 #[derive(Default, Debug)]
 pub struct Lexer {
-    pub tokens: Vec<Token>,
+    /// The symbols that are not reduced yet and the expressions, after `parse` only
+    /// expressions are left
+    pub lexemes: Vec<Lexeme>,
     pub data: String,
     pub number_memory: String,
     pub memory: String,
     pub i: usize,
+    /// A space was found after the last word or number
+    pub separated: bool,
+    /// No value was added after the last comma, like before the `(2)` in `[1, (2)]`
+    pub comma: bool,
+    /// The number is after a duration, like the 20 in `3h 20min`
+    pub compound: bool,
+    /// The characters of the number and of the word in the memory
+    number_span: Span,
+    word_span: Span,
 }
 
 impl Lexer {
     pub fn parse(&mut self, data: &str) -> Result<(), String> {
         self.data.push_str(data);
-        for (i, char) in data.chars().enumerate() {
+        let mut chars = data.chars().enumerate().peekable();
+        while let Some((i, char)) = chars.next() {
             self.i = i;
+            let separated = std::mem::replace(&mut self.separated, char == ' ');
+            let span = Span::new(i, i + 1);
+            // the span of an operator with two characters like `<=`
+            let long = Span::new(i, i + 2);
             match char {
+                // digits can be part of a word like `x2`
+                '0'..='9' | '_' if !self.memory.is_empty() && !separated => self.letter(i, char),
                 '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' => {
-                    self.number_memory.push(char)
+                    if !self.memory.is_empty() {
+                        self.process()?;
+                    }
+                    if self.number_memory.is_empty() {
+                        self.compound =
+                            !self.comma && matches!(self.last_value(), Some(Value::Duration(_)));
+                    }
+                    self.digit(i, char)
+                }
+                // only right after the `0`, the `o` of `0 to hex` is part of a word
+                'x' | 'b' | 'o'
+                    if self.number_memory == "0" && self.memory.is_empty() && !separated =>
+                {
+                    self.digit(i, char)
+                }
+                _ if self.number_memory.starts_with("0x")
+                    && char.is_ascii_hexdigit()
+                    && !separated =>
+                {
+                    self.digit(i, char)
+                }
+                // a float with an exponent like `1e21` or `2.5e-3`
+                'e' | 'E'
+                    if !separated
+                        && !self.number_memory.is_empty()
+                        && self
+                            .number_memory
+                            .bytes()
+                            .all(|b| b.is_ascii_digit() || b == b'.') =>
+                {
+                    let mut next = chars.clone().map(|(_, next)| next);
+                    let exponent = match next.next() {
+                        Some('+' | '-') => next.next(),
+                        first => first,
+                    };
+                    if exponent.is_some_and(|digit| digit.is_ascii_digit()) {
+                        self.digit(i, 'e');
+                        if let Some((i, sign)) =
+                            chars.next_if(|(_, next)| matches!(next, '+' | '-'))
+                        {
+                            self.digit(i, sign);
+                        }
+                    } else {
+                        self.letter(i, char);
+                    }
                 }
                 ',' => {
                     self.process()?;
+                    self.equation();
+                    self.lambda();
+                    self.comma = true;
                 }
                 '_' | ' ' => {}
-                '.' => self.number_memory.push(char),
+                '.' if chars.next_if(|(_, next)| *next == '*').is_some() => {
+                    self.process()?;
+                    self.symbol(Symbol::ElemMul, long);
+                }
+                '.' => {
+                    if self.number_memory.ends_with('.') {
+                        self.number_memory.pop();
+                        self.number_span.end -= 1;
+                        self.process()?;
+                        self.symbol(Symbol::Range, Span::new(i - 1, i + 1));
+                    } else {
+                        self.digit(i, char)
+                    }
+                }
+                // an ISO 8601 date like `2026-10-18` or `2026-10-18T14:30`
+                '-' if self.number_memory.len() == 4 => {
+                    let rest: String = std::iter::once(char)
+                        .chain(chars.clone().map(|(_, next)| next))
+                        .take(16)
+                        .collect();
+                    match time::parse(&self.number_memory, &rest) {
+                        Some(date) => {
+                            let (value, len) = date?;
+                            self.number_memory.clear();
+                            let span = Span::new(self.number_span.start, i + len);
+                            self.push(ExprKind::Value(value), span);
+                            chars.nth(len - 2);
+                        }
+                        None => {
+                            self.process()?;
+                            self.symbol(Symbol::Sub, span);
+                        }
+                    }
+                }
                 '-' => {
                     self.process()?;
-                    self.tokens.push(Token::SSub);
+                    if chars.next_if(|(_, next)| *next == '>').is_some() {
+                        self.symbol(Symbol::Arrow, long);
+                    } else if self.comma || !self.lexemes.last().is_some_and(Lexeme::is_expr) {
+                        self.symbol(Symbol::Neg, span);
+                    } else {
+                        self.symbol(Symbol::Sub, span);
+                    }
+                }
+                // `+-` is the ascii `±`, `2 + -3` needs the space
+                '+' if chars.next_if(|(_, next)| *next == '-').is_some() => {
+                    self.process()?;
+                    self.symbol(Symbol::PlusMinus, long);
                 }
                 '+' => {
                     self.process()?;
-                    self.tokens.push(Token::SAdd);
+                    self.symbol(Symbol::Add, span);
+                }
+                '±' => {
+                    self.process()?;
+                    self.symbol(Symbol::PlusMinus, span);
                 }
                 '*' => {
                     self.process()?;
-                    self.tokens.push(Token::SMul);
+                    self.symbol(Symbol::Mul, span);
                 }
                 '/' => {
                     self.process()?;
-                    self.tokens.push(Token::SDiv);
+                    self.symbol(Symbol::Div, span);
+                }
+                '&' => {
+                    self.process()?;
+                    if chars.next_if(|(_, next)| *next == '&').is_some() {
+                        self.symbol(Symbol::And, long);
+                    } else {
+                        self.symbol(Symbol::BitAnd, span);
+                    }
+                }
+                '|' => {
+                    self.process()?;
+                    if chars.next_if(|(_, next)| *next == '|').is_some() {
+                        self.symbol(Symbol::Or, long);
+                    } else {
+                        self.symbol(Symbol::BitOr, span);
+                    }
+                }
+                '^' => {
+                    self.process()?;
+                    self.symbol(Symbol::BitXor, span);
+                }
+                '~' => {
+                    self.process()?;
+                    self.symbol(Symbol::BitNot, span);
+                }
+                '<' | '>' => {
+                    self.process()?;
+                    let next = chars.next_if(|(_, next)| *next == char || *next == '=');
+                    let span = if next.is_some() { long } else { span };
+                    let symbol = match (char, next.map(|(_, next)| next)) {
+                        ('<', Some('<')) => Symbol::Shl,
+                        ('<', Some('=')) => Symbol::Le,
+                        ('<', _) => Symbol::Lt,
+                        (_, Some('>')) => Symbol::Shr,
+                        (_, Some('=')) => Symbol::Ge,
+                        _ => Symbol::Gt,
+                    };
+                    self.symbol(symbol, span);
+                }
+                '=' => {
+                    self.process()?;
+                    if chars.next_if(|(_, next)| *next == '=').is_some() {
+                        self.symbol(Symbol::Eq, long);
+                    } else {
+                        self.symbol(Symbol::Equation, span);
+                    }
+                }
+                '!' => {
+                    self.process()?;
+                    if chars.next_if(|(_, next)| *next == '=').is_some() {
+                        self.symbol(Symbol::Ne, long);
+                    } else {
+                        self.symbol(Symbol::Not, span);
+                    }
+                }
+                '?' => {
+                    self.process()?;
+                    self.symbol(Symbol::Question, span);
+                }
+                ':' => {
+                    self.process()?;
+                    self.symbol(Symbol::Colon, span);
                 }
                 '(' => {
-                    match self.memory.trim() {
-                        "sin" => self.tokens.push(Token::SSin),
-                        "cos" => self.tokens.push(Token::SCos),
-                        "pow" => self.tokens.push(Token::SPow),
-                        "sqrt" => self.tokens.push(Token::SSqrt),
-                        _ => {
-                            if !self.memory.is_empty() {
-                                return Err(format!(
-                                    "Invalid function: \"{}\", at: {}",
-                                    self.memory, self.i
-                                ));
-                            }
-                            self.memory.clear();
-                            self.process()?;
-                            self.tokens.push(Token::SGroupBeagin);
-                            self.process()?;
+                    let memory = std::mem::take(&mut self.memory);
+                    let function = match memory.trim() {
+                        "sin" => Some(Symbol::Sin),
+                        "cos" => Some(Symbol::Cos),
+                        "pow" => Some(Symbol::Pow),
+                        "sqrt" => Some(Symbol::Sqrt),
+                        "if" => Some(Symbol::If),
+                        name => Function::from_name(name).map(Symbol::Call),
+                    };
+                    if let Some(function) = function {
+                        let name = self.word_span;
+                        self.process()?;
+                        if self.lexemes.last().is_some_and(Lexeme::is_expr) && !self.comma {
+                            self.symbol(Symbol::Mul, Span::new(name.start, name.start));
+                        }
+                        self.symbol(function, name);
+                    } else {
+                        let word = memory.trim();
+                        if !word.is_empty() && Self::parse_word(word, Span::default()).is_none() {
+                            return Err(format!("Invalid function: \"{memory}\", at: {i}"));
                         }
+                        self.memory = memory;
+                        self.process()?;
                     }
-                    self.memory.clear();
+                    self.symbol(Symbol::GroupBegin, span);
+                    self.process()?;
                 }
                 ')' => {
                     self.process()?;
-                    self.tokens.push(Token::SGroupEnd);
+                    self.equation();
+                    self.lambda();
+                    self.symbol(Symbol::GroupEnd, span);
+                    self.process()?;
+                }
+                '[' => {
+                    self.process()?;
+                    self.symbol(Symbol::ListBegin, span);
+                }
+                ']' => {
+                    self.process()?;
+                    self.equation();
+                    self.lambda();
+                    self.symbol(Symbol::ListEnd, span);
                     self.process()?;
                 }
-                _ => self.memory.push(char),
+                _ => {
+                    // a letter ends the word before the space, the number before is kept
+                    // for a unit like `45 days`
+                    if separated && !self.memory.is_empty() {
+                        self.process()?;
+                    }
+                    self.letter(i, char)
+                }
             }
         }
         self.process()?;
-        Ok(())
+        self.equation();
+        self.lambda();
+        match self.lexemes.iter().find(|lexeme| !lexeme.is_expr()) {
+            Some(Lexeme::Symbol(symbol, span)) => Err(format!(
+                "Expected an expression around \"{symbol}\", at: {}",
+                span.start
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The whole formula as a group, after `parse`
+    pub fn expr(&self) -> Expr {
+        let exprs = self
+            .lexemes
+            .iter()
+            .filter_map(|lexeme| match lexeme {
+                Lexeme::Expr(expr) => Some(expr.clone()),
+                Lexeme::Symbol(..) => None,
+            })
+            .collect();
+        Expr::new(
+            ExprKind::Group(exprs),
+            Span::new(0, self.data.chars().count()),
+        )
+    }
+
+    /// Returns `None` when the word is not a keyword
+    fn parse_word(word: &str, span: Span) -> Option<Lexeme> {
+        let symbol = |symbol| Some(Lexeme::Symbol(symbol, span));
+        let expr = |kind| Some(Lexeme::Expr(Expr::new(kind, span)));
+        match word {
+            "true" => expr(ExprKind::Value(Value::Bool(true))),
+            "false" => expr(ExprKind::Value(Value::Bool(false))),
+            "and" => symbol(Symbol::And),
+            "or" => symbol(Symbol::Or),
+            "not" => symbol(Symbol::Not),
+            "step" => symbol(Symbol::Step),
+            // `12.50 EUR to USD` is the same as `in`
+            "in" | "to" => symbol(Symbol::In),
+            "pi" => expr(ExprKind::Call(Function::Pi, Vec::new())),
+            "today" => expr(ExprKind::Call(Function::Today, Vec::new())),
+            "now" => expr(ExprKind::Call(Function::Now, Vec::new())),
+            _ => None,
+        }
+    }
+
+    fn digit(&mut self, i: usize, char: char) {
+        if self.number_memory.is_empty() {
+            self.number_span.start = i;
+        }
+        self.number_span.end = i + 1;
+        self.number_memory.push(char)
+    }
+
+    fn letter(&mut self, i: usize, char: char) {
+        if self.memory.is_empty() {
+            self.word_span.start = i;
+        }
+        self.word_span.end = i + 1;
+        self.memory.push(char)
     }
 
     fn process(&mut self) -> Result<(), String> {
         let i = self.i;
-        if !self.number_memory.is_empty() {
-            let memory = std::mem::take(&mut self.number_memory);
-            match Self::parse_group(memory) {
-                Ok(res) => self.tokens.push(res),
-                Err(err) => return Err(format!("Cannot parse number at: {i}, error: {err}")),
-            }
+        // a number before a word that is not a unit is processed first, like in `2 and 3`
+        let word = self.memory.trim();
+        let is_unit = Duration::unit(word).is_some() || Currency::from_code(word).is_some();
+        if !self.number_memory.is_empty() && !word.is_empty() && !is_unit {
+            let memory = std::mem::take(&mut self.memory);
+            self.process()?;
+            self.memory = memory;
+            return self.process();
         }
 
-        if let Some(token) = self.tokens.last() {
-            match token {
-                Token::SGroupBeagin => {
-                    let t = self.tokens.pop().unwrap();
-                    if let Some(token) = self.tokens.last() {
-                        match token {
-                            Token::SAdd
-                            | Token::SDiv
-                            | Token::SSub
-                            | Token::SMul
-                            | Token::SSin
-                            | Token::SCos
-                            | Token::SPow
-                            | Token::SSqrt
-                            | Token::SGroupBeagin => {}
-                            _ => self.tokens.push(Token::SMul),
-                        }
-                    }
+        let memory = std::mem::take(&mut self.memory);
+        let word = memory.trim();
+        let number = std::mem::take(&mut self.number_memory);
+        let (number_span, word_span) = (self.number_span, self.word_span);
+        if is_unit && (!number.is_empty() || self.last_symbol() == Some(Symbol::In)) {
+            let span = match number.is_empty() {
+                true => word_span,
+                false => number_span.to(word_span),
+            };
+            let expr = self
+                .unit(&number, word, span)
+                .map_err(|err| format!("Cannot parse number at: {i}, error: {err}"))?;
+            self.lexeme(Lexeme::Expr(expr));
+        } else {
+            if !number.is_empty() {
+                match Self::parse_group(number) {
+                    Ok(value) => self.push(ExprKind::Value(value), number_span),
+                    Err(err) => return Err(format!("Cannot parse number at: {i}, error: {err}")),
+                }
+            }
+            // the base of `255 to hex`
+            let radix = Radix::from_name(word).filter(|_| self.last_symbol() == Some(Symbol::In));
+            if let Some(radix) = radix {
+                self.push(ExprKind::Value(Value::Base(radix)), word_span);
+            } else if !word.is_empty() {
+                match Self::parse_word(word, word_span) {
+                    Some(lexeme) => self.lexeme(lexeme),
+                    None => self.push(ExprKind::Var(word.to_string()), word_span),
+                }
+            }
+        }
 
-                    self.tokens.push(t);
+        match self.last_symbol() {
+            Some(Symbol::GroupBegin) => {
+                let begin = self.lexemes.pop().unwrap();
+                // a group after a value is a multiplication, but not after a comma
+                if self.lexemes.last().is_some_and(Lexeme::is_expr) && !self.comma {
+                    let start = begin.span().start;
+                    self.symbol(Symbol::Mul, Span::new(start, start))
                 }
-                Token::SGroupEnd => {
-                    self.tokens.pop();
-                    let mut buffer = Vec::new();
-                    's: {
-                        while let Some(token) = self.tokens.pop() {
-                            if let Token::SGroupBeagin = token {
+                self.lexemes.push(begin);
+            }
+            Some(Symbol::GroupEnd) => {
+                let end = self.lexemes.pop().unwrap().span();
+                let mut buffer = Vec::new();
+                's: {
+                    while let Some(lexeme) = self.lexemes.pop() {
+                        match lexeme {
+                            Lexeme::Symbol(Symbol::GroupBegin, begin) => {
                                 buffer.reverse();
-                                self.tokens.push(Token::Group(buffer));
+                                let exprs = Self::exprs(buffer)?;
+                                self.push(ExprKind::Group(exprs), begin.to(end));
                                 break 's;
                             }
-                            buffer.push(token)
-                        }
-                        while let Some(t) = buffer.pop() {
-                            self.tokens.push(t)
+                            Lexeme::Symbol(Symbol::ListBegin, _) => {
+                                return Err(format!("Expected \"]\", found \")\", at: {i}"));
+                            }
+                            lexeme => buffer.push(lexeme),
                         }
                     }
+                    while let Some(lexeme) = buffer.pop() {
+                        self.lexemes.push(lexeme)
+                    }
                 }
-                _ => {}
             }
-        }
-
-        for i in 1..self.tokens.len() + 1 {
-            let i = self.tokens.len() - i;
-            if let Some(token) = self.tokens.get(i) {
-                if self.tokens.len() > i + 1 {
-                    let a = self.tokens.get(i + 1).unwrap();
-                    if a.is_calculabile() {
-                        match token {
-                            Token::SAdd => self.add(i),
-                            Token::SDiv => self.div(i),
-                            Token::SSub => self.sub(i),
-                            Token::SMul => self.mul(i),
-                            Token::SSin => self.sin(i),
-                            Token::SCos => self.cos(i),
-                            Token::SPow => self.pow(i),
-                            Token::SSqrt => self.sqrt(i),
-                            _ => continue,
+            Some(Symbol::ListEnd) => {
+                let end = self.lexemes.pop().unwrap().span();
+                let mut buffer = Vec::new();
+                let begin = loop {
+                    match self.lexemes.pop() {
+                        Some(Lexeme::Symbol(Symbol::ListBegin, begin)) => break begin,
+                        Some(Lexeme::Symbol(Symbol::GroupBegin, _)) | None => {
+                            return Err(format!("Expected \")\", found \"]\", at: {i}"))
                         }
-                        break;
+                        Some(lexeme) => buffer.push(lexeme),
                     }
-                }
+                };
+                buffer.reverse();
+                let exprs = Self::exprs(buffer)?;
+                self.push(ExprKind::List(exprs), begin.to(end));
+            }
+            _ => {}
+        }
+
+        // every operator that has all its expressions is reduced
+        loop {
+            let len = self.lexemes.len();
+            self.reduce()?;
+            if self.lexemes.len() == len {
+                break;
             }
         }
 
         #[cfg(feature = "debug")]
-        println!("Lexer State: {}", Token::Group(self.tokens.clone()));
+        println!("Lexer State: {}", self.expr());
         Ok(())
     }
 
-    fn parse_group(data: String) -> Result<Token, String> {
-        if data.contains('.') {
+    fn reduce(&mut self) -> Result<(), String> {
+        for i in (0..self.lexemes.len()).rev() {
+            let Some(symbol) = self.lexemes[i].symbol() else {
+                continue;
+            };
+            if !self.lexemes.get(i + 1).is_some_and(Lexeme::is_expr) {
+                continue;
+            }
+            match symbol {
+                Symbol::Add => self.binary(i, BinaryOp::Add),
+                Symbol::Div => self.binary(i, BinaryOp::Div),
+                Symbol::Sub => self.binary(i, BinaryOp::Sub),
+                Symbol::Mul => self.binary(i, BinaryOp::Mul),
+                Symbol::ElemMul => self.binary(i, BinaryOp::ElemMul),
+                Symbol::PlusMinus => self.binary(i, BinaryOp::PlusMinus),
+                Symbol::In => self.binary(i, BinaryOp::In),
+                Symbol::Sin => self.unary(i, UnaryOp::Sin),
+                Symbol::Cos => self.unary(i, UnaryOp::Cos),
+                Symbol::Pow => self.pow(i)?,
+                Symbol::Sqrt => self.unary(i, UnaryOp::Sqrt),
+                Symbol::BitAnd => self.binary(i, BinaryOp::BitAnd),
+                Symbol::BitOr => self.binary(i, BinaryOp::BitOr),
+                Symbol::BitXor => self.binary(i, BinaryOp::BitXor),
+                Symbol::Shl => self.binary(i, BinaryOp::Shl),
+                Symbol::Shr => self.binary(i, BinaryOp::Shr),
+                Symbol::BitNot => self.unary(i, UnaryOp::BitNot),
+                Symbol::Eq => self.binary(i, BinaryOp::Eq),
+                Symbol::Ne => self.binary(i, BinaryOp::Ne),
+                Symbol::Lt => self.binary(i, BinaryOp::Lt),
+                Symbol::Le => self.binary(i, BinaryOp::Le),
+                Symbol::Gt => self.binary(i, BinaryOp::Gt),
+                Symbol::Ge => self.binary(i, BinaryOp::Ge),
+                Symbol::And => self.binary(i, BinaryOp::And),
+                Symbol::Or => self.binary(i, BinaryOp::Or),
+                Symbol::Not => self.unary(i, UnaryOp::Not),
+                Symbol::If => self.if_group(i)?,
+                Symbol::Colon => self.ternary(i),
+                Symbol::Neg => self.unary(i, UnaryOp::Neg),
+                Symbol::Range => self.range(i),
+                Symbol::Step => self.step(i),
+                Symbol::Call(function) => self.call(i, function),
+                _ => continue,
+            }
+            break;
+        }
+        Ok(())
+    }
+
+    /// A number with a unit like `45 days`, `3h 20min` or `12.50 EUR`, or the unit after `in`
+    fn unit(&mut self, number: &str, word: &str, mut span: Span) -> Result<Expr, String> {
+        if let Some(currency) = Currency::from_code(word) {
+            // the amount is exact, `0.10 EUR` is not a float
+            let amount = match number {
+                "" => Rational::integer(1),
+                _ => number.parse()?,
+            };
+            let money = Value::Money(Money::new(amount, currency));
+            return Ok(Expr::new(ExprKind::Value(money), span));
+        }
+        let unit = Duration::unit(word).unwrap_or_default();
+        if number.is_empty() {
+            return Ok(Expr::new(ExprKind::Value(Value::Duration(unit)), span));
+        }
+        let number = Self::parse_group(number.to_string())?
+            .as_f64()
+            .unwrap_or_default();
+        let mut duration = unit * number;
+        // `3h 20min` is one duration
+        if let (true, Some(Value::Duration(before))) = (self.compound, self.last_value()) {
+            duration = *before + duration;
+            span = self.lexemes.pop().unwrap().span().to(span);
+        }
+        Ok(Expr::new(ExprKind::Value(Value::Duration(duration)), span))
+    }
+
+    fn parse_group(data: String) -> Result<Value, String> {
+        let radix = match data.get(..2) {
+            Some("0x") => 16,
+            Some("0b") => 2,
+            Some("0o") => 8,
+            _ => 10,
+        };
+        if radix != 10 {
+            // parsed as unsigned so that all the 64 bits can be written
+            match u64::from_str_radix(&data[2..], radix) {
+                Ok(num) => Ok(Value::Int(num as i64)),
+                Err(_) => Err("Cannot parse number".into()),
+            }
+        } else if data.contains('.') || data.contains('e') {
             match data.parse::<f64>() {
-                Ok(num) => Ok(Token::F(num)),
+                Ok(num) => Ok(Value::Float(num)),
                 Err(_) => Err("Cannot parse number".into()),
             }
         } else {
             match data.parse::<i64>() {
-                Ok(num) => Ok(Token::I(num)),
+                Ok(num) => Ok(Value::Int(num)),
                 Err(_) => Err("Cannot parse number".into()),
             }
         }
     }
 
-    fn push(&mut self, token: Token) {
-        self.tokens.push(token)
+    /// The lexemes of a group or a list, they are all expressions after the bracket is closed
+    fn exprs(lexemes: Vec<Lexeme>) -> Result<Vec<Expr>, String> {
+        lexemes
+            .into_iter()
+            .map(|lexeme| match lexeme {
+                Lexeme::Expr(expr) => Ok(expr),
+                Lexeme::Symbol(symbol, span) => Err(format!(
+                    "Expected an expression around \"{symbol}\", at: {}",
+                    span.start
+                )),
+            })
+            .collect()
     }
 
-    fn add(&mut self, i: usize) {
-        if let Some((a, b)) = self.get_ab(i) {
-            self.push(Token::a(a, b))
+    fn lexeme(&mut self, lexeme: Lexeme) {
+        if lexeme.is_expr() {
+            self.comma = false;
         }
+        self.lexemes.push(lexeme)
     }
 
-    fn sub(&mut self, i: usize) {
-        if let Some((a, b)) = self.get_ab(i) {
-            self.push(Token::s(a, b))
+    fn symbol(&mut self, symbol: Symbol, span: Span) {
+        self.lexeme(Lexeme::Symbol(symbol, span))
+    }
+
+    fn push(&mut self, kind: ExprKind, span: Span) {
+        self.lexeme(Lexeme::Expr(Expr::new(kind, span)))
+    }
+
+    fn last_symbol(&self) -> Option<Symbol> {
+        self.lexemes.last().and_then(Lexeme::symbol)
+    }
+
+    /// The value that was written last, like the duration before `20min` in `3h 20min`
+    fn last_value(&self) -> Option<&Value> {
+        match self.lexemes.last().and_then(Lexeme::kind) {
+            Some(ExprKind::Value(value)) => Some(value),
+            _ => None,
         }
     }
 
-    fn mul(&mut self, i: usize) {
+    fn binary(&mut self, i: usize, op: BinaryOp) {
         if let Some((a, b)) = self.get_ab(i) {
-            self.push(Token::m(a, b))
+            let span = a.span.to(b.span);
+            self.push(ExprKind::Binary(op, Box::new(a), Box::new(b)), span)
         }
     }
 
-    fn div(&mut self, i: usize) {
-        if let Some((a, b)) = self.get_ab(i) {
-            self.push(Token::d(a, b))
+    fn unary(&mut self, i: usize, op: UnaryOp) {
+        if let Some((span, a)) = self.get_a(i) {
+            let span = span.to(a.span);
+            self.push(ExprKind::Unary(op, Box::new(a)), span)
         }
     }
 
-    fn sin(&mut self, i: usize) {
-        if let Some(a) = self.get_a(i) {
-            self.push(Token::sin(a))
+    /// The arguments of `pow(a, b)`, `if(c, a, b)` or a function after the symbol at `i`
+    fn arguments(&mut self, i: usize) -> Option<(Span, Vec<Expr>)> {
+        let Some(ExprKind::Group(_)) = self.lexemes.get(i + 1).and_then(Lexeme::kind) else {
+            return None;
+        };
+        let (span, group) = self.get_a(i)?;
+        let ExprKind::Group(args) = group.kind else {
+            unreachable!()
+        };
+        Some((span.to(group.span), args))
+    }
+
+    fn pow(&mut self, i: usize) -> Result<(), String> {
+        if let Some(ExprKind::Group(args)) = self.lexemes.get(i + 1).and_then(Lexeme::kind) {
+            if args.len() != 2 {
+                return Err(format!("pow needs 2 arguments, got: {}", args.len()));
+            }
+            let (span, mut args) = self.arguments(i).unwrap();
+            let b = args.pop().unwrap();
+            let a = args.pop().unwrap();
+            self.push(
+                ExprKind::Binary(BinaryOp::Pow, Box::new(a), Box::new(b)),
+                span,
+            )
         }
+        Ok(())
     }
 
-    fn cos(&mut self, i: usize) {
-        if let Some(a) = self.get_a(i) {
-            self.push(Token::cos(a))
+    fn if_group(&mut self, i: usize) -> Result<(), String> {
+        if let Some(ExprKind::Group(args)) = self.lexemes.get(i + 1).and_then(Lexeme::kind) {
+            if args.len() != 3 {
+                return Err(format!(
+                    "if needs 3 arguments: if(condition, then, else), got: {}",
+                    args.len()
+                ));
+            }
+            let (span, mut args) = self.arguments(i).unwrap();
+            let c = args.remove(0);
+            let a = args.remove(0);
+            let b = args.remove(0);
+            self.push(ExprKind::If(Box::new(c), Box::new(a), Box::new(b)), span)
         }
+        Ok(())
     }
 
-    fn pow(&mut self, i: usize) {
-        if let Some((a, b)) = self.get_ab_liniar(i) {
-            self.push(Token::pow(a, b))
+    fn call(&mut self, i: usize, function: Function) {
+        if let Some((span, args)) = self.arguments(i) {
+            self.push(ExprKind::Call(function, args), span)
         }
     }
 
-    fn sqrt(&mut self, i: usize) {
-        if let Some(a) = self.get_a(i) {
-            self.push(Token::sqrt(a))
+    fn range(&mut self, i: usize) {
+        if let Some((a, b)) = self.get_ab(i) {
+            let span = a.span.to(b.span);
+            self.push(ExprKind::Range(Box::new(a), Box::new(b), None), span)
         }
     }
 
-    fn get_ab_liniar(&mut self, i: usize) -> Option<(Token, Token)> {
-        let Some(a) = self.tokens.get(i + 1) else {return None};
-        let Some(b) = self.tokens.get(i + 2) else {return None};
-        if a.is_calculabile() && b.is_calculabile() && self.tokens.len() > 2 {
-            self.tokens.remove(i);
-            let a = self.tokens.remove(i);
-            let b = self.tokens.remove(i);
-            return Some((a, b));
+    /// `1..10 step 2`, the step is added to the range before it
+    fn step(&mut self, i: usize) {
+        if i > 0 && matches!(self.lexemes[i - 1].kind(), Some(ExprKind::Range(..))) {
+            if let Some((range, step)) = self.get_ab(i) {
+                let span = range.span.to(step.span);
+                let ExprKind::Range(from, to, _) = range.kind else {
+                    unreachable!()
+                };
+                self.push(ExprKind::Range(from, to, Some(Box::new(step))), span)
+            }
+        }
+    }
+
+    /// `a = b` is reduced when the argument ends, so both sides can have operators
+    fn equation(&mut self) {
+        let len = self.lexemes.len();
+        if len >= 3
+            && self.lexemes[len - 3].is_expr()
+            && self.lexemes[len - 2].symbol() == Some(Symbol::Equation)
+            && self.lexemes[len - 1].is_expr()
+        {
+            let Some((Lexeme::Expr(a), Lexeme::Expr(b))) = self.last_three() else {
+                unreachable!()
+            };
+            let span = a.span.to(b.span);
+            self.push(ExprKind::Equation(Box::new(a), Box::new(b)), span)
+        }
+    }
+
+    /// `x -> body` is reduced when the argument ends, so the body can have operators
+    fn lambda(&mut self) {
+        let len = self.lexemes.len();
+        if len >= 3
+            && matches!(self.lexemes[len - 3].kind(), Some(ExprKind::Var(_)))
+            && self.lexemes[len - 2].symbol() == Some(Symbol::Arrow)
+            && self.lexemes[len - 1].is_expr()
+        {
+            let Some((Lexeme::Expr(var), Lexeme::Expr(body))) = self.last_three() else {
+                unreachable!()
+            };
+            let ExprKind::Var(name) = var.kind else {
+                unreachable!()
+            };
+            let span = var.span.to(body.span);
+            self.push(ExprKind::Lambda(name, Box::new(body)), span)
         }
-        None
     }
 
-    fn get_ab(&mut self, i: usize) -> Option<(Token, Token)> {
+    /// The first and the last of the last three lexemes, the one in the middle is the symbol
+    fn last_three(&mut self) -> Option<(Lexeme, Lexeme)> {
+        let b = self.lexemes.pop()?;
+        self.lexemes.pop()?;
+        let a = self.lexemes.pop()?;
+        Some((a, b))
+    }
+
+    /// `condition ? a : b`, is reduced when the `:` has an expression on both sides
+    fn ternary(&mut self, i: usize) {
+        if i < 3 || self.lexemes[i - 2].symbol() != Some(Symbol::Question) {
+            return;
+        }
+        if self.lexemes[i - 1].is_expr() && self.lexemes[i - 3].is_expr() {
+            let mut exprs = self
+                .lexemes
+                .drain(i - 3..i + 2)
+                .filter_map(|lexeme| match lexeme {
+                    Lexeme::Expr(expr) => Some(expr),
+                    Lexeme::Symbol(..) => None,
+                });
+            let (Some(c), Some(a), Some(b)) = (exprs.next(), exprs.next(), exprs.next()) else {
+                unreachable!()
+            };
+            drop(exprs);
+            let span = c.span.to(b.span);
+            self.push(ExprKind::If(Box::new(c), Box::new(a), Box::new(b)), span)
+        }
+    }
+
+    /// The expressions around the symbol at `i`, the symbol is removed too
+    fn get_ab(&mut self, i: usize) -> Option<(Expr, Expr)> {
         if i > 0 {
-            let Some(a) = self.tokens.get(i + 1) else {return None};
-            let Some(b) = self.tokens.get(i - 1) else {return None};
-            if a.is_calculabile() && b.is_calculabile() && self.tokens.len() > 2 {
-                let a = self.tokens.remove(i - 1);
-                self.tokens.remove(i - 1);
-                let b = self.tokens.remove(i - 1);
-                return Some((a, b));
+            let a = self.lexemes.get(i + 1)?;
+            let b = self.lexemes.get(i - 1)?;
+            if a.is_expr() && b.is_expr() && self.lexemes.len() > 2 {
+                let a = self.lexemes.remove(i - 1);
+                self.lexemes.remove(i - 1);
+                let b = self.lexemes.remove(i - 1);
+                if let (Lexeme::Expr(a), Lexeme::Expr(b)) = (a, b) {
+                    return Some((a, b));
+                }
             }
         }
         None
     }
 
-    fn get_a(&mut self, i: usize) -> Option<Token> {
-        if self.tokens.len() > 1 {
-            self.tokens.remove(i);
-            let a = self.tokens.remove(i);
-            Some(a)
+    /// The symbol at `i` and the expression after it
+    fn get_a(&mut self, i: usize) -> Option<(Span, Expr)> {
+        if self.lexemes.len() > 1 {
+            let symbol = self.lexemes.remove(i);
+            match self.lexemes.remove(i) {
+                Lexeme::Expr(a) => Some((symbol.span(), a)),
+                Lexeme::Symbol(..) => None,
+            }
         } else {
             None
         }
     }
 }
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Symbol::Add => f.write_str("+"),
+            Symbol::Div => f.write_str("/"),
+            Symbol::Sub => f.write_str("-"),
+            Symbol::Mul => f.write_str("*"),
+            Symbol::ElemMul => f.write_str(".*"),
+            Symbol::PlusMinus => f.write_str("±"),
+            Symbol::In => f.write_str("in"),
+            Symbol::Sin => f.write_str("sin"),
+            Symbol::Cos => f.write_str("cos"),
+            Symbol::Pow => f.write_str("pow"),
+            Symbol::Sqrt => f.write_str("sqrt"),
+            Symbol::BitAnd => f.write_str("&"),
+            Symbol::BitOr => f.write_str("|"),
+            Symbol::BitXor => f.write_str("^"),
+            Symbol::Shl => f.write_str("<<"),
+            Symbol::Shr => f.write_str(">>"),
+            Symbol::BitNot => f.write_str("~"),
+            Symbol::Eq => f.write_str("=="),
+            Symbol::Ne => f.write_str("!="),
+            Symbol::Lt => f.write_str("<"),
+            Symbol::Le => f.write_str("<="),
+            Symbol::Gt => f.write_str(">"),
+            Symbol::Ge => f.write_str(">="),
+            Symbol::And => f.write_str("and"),
+            Symbol::Or => f.write_str("or"),
+            Symbol::Not => f.write_str("not"),
+            Symbol::If => f.write_str("if"),
+            Symbol::Question => f.write_str("?"),
+            Symbol::Colon => f.write_str(":"),
+            Symbol::Neg => f.write_str("-"),
+            Symbol::ListBegin => f.write_str("["),
+            Symbol::ListEnd => f.write_str("]"),
+            Symbol::Range => f.write_str(".."),
+            Symbol::Step => f.write_str("step"),
+            Symbol::Arrow => f.write_str("->"),
+            Symbol::Equation => f.write_str("="),
+            Symbol::Call(function) => write!(f, "{function}"),
+            Symbol::GroupBegin => f.write_str("("),
+            Symbol::GroupEnd => f.write_str(")"),
+        }
+    }
+}
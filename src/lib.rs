@@ -1,21 +1,80 @@
+mod ast;
+#[cfg(feature = "bigfloat")]
+mod bigfloat;
+mod calculus;
+mod compile;
+mod context;
 mod executor;
+mod finance;
+mod format;
+mod function;
+mod interval;
 mod lexer;
+mod matrix;
+mod money;
+mod number;
+mod numeric;
+mod polynomial;
+mod roots;
+mod simplify;
+mod stats;
 #[cfg(test)]
 mod tests;
+mod time;
 mod token;
+mod trace;
+mod uncertain;
+mod value;
 
+pub use ast::{BinaryOp, Expr, ExprKind, Span, UnaryOp};
+pub use compile::{Column, Compiled};
+pub use context::{Context, Radix, Rng, Width};
 pub use executor::Executor;
-pub use lexer::Lexer;
+pub use format::{Formatter, Notation};
+pub use function::Function;
+pub use interval::Interval;
+pub use lexer::{Lexeme, Lexer, Symbol};
+pub use matrix::Matrix;
+pub use money::{Currency, Money, RateTable, Rates, Rational};
+pub use polynomial::Polynomial;
+pub use simplify::Rule;
+pub use time::{Date, DateTime, Duration};
 pub use token::Token;
+pub use trace::{Step, Trace};
+pub use uncertain::Uncertain;
+pub use value::Value;
 
 pub fn kalc(formula: &str) -> Result<Executor, String> {
+    kalc_with(formula, Context::default())
+}
+
+pub fn kalc_with(formula: &str, context: Context) -> Result<Executor, String> {
     let mut lexer = Lexer::default();
     lexer.parse(formula)?;
-    let mut executor = Executor::new(lexer);
-    executor.execute();
+    let mut executor = Executor::with_context(lexer, context);
+    executor.execute()?;
     Ok(executor)
 }
 
+/// The result with every step that calculated it
+pub fn kalc_trace(formula: &str) -> Result<Trace, String> {
+    let mut lexer = Lexer::default();
+    lexer.parse(formula)?;
+    Executor::new(lexer).trace()
+}
+
+/// The syntax tree of the formula, every node has the characters it comes from
+pub fn parse(formula: &str) -> Result<Expr, String> {
+    let mut lexer = Lexer::default();
+    lexer.parse(formula)?;
+    Ok(lexer.expr())
+}
+
+/// The formula parsed once, `eval` calculates it with the values of the variables
+pub fn compile(formula: &str) -> Result<Compiled, String> {
+    Compiled::new(parse(formula)?)
+}
+
 pub fn kalc_i64(formula: &str) -> Result<i64, String> {
     Ok(kalc(formula)?.get_i64())
 }
@@ -23,3 +82,21 @@ pub fn kalc_i64(formula: &str) -> Result<i64, String> {
 pub fn kalc_f64(formula: &str) -> Result<f64, String> {
     Ok(kalc(formula)?.get_f64())
 }
+
+pub fn kalc_bool(formula: &str) -> Result<bool, String> {
+    Ok(kalc(formula)?.get_bool())
+}
+
+/// The simplified derivative of the formula by the variable, like `sin(pow(x, 2))` by `x`
+pub fn derivative(formula: &str, var: &str) -> Result<Token, String> {
+    let mut lexer = Lexer::default();
+    lexer.parse(formula)?;
+    Token::from(&lexer.expr()).derivative(var)
+}
+
+/// The formula simplified, the variables are kept like in `(2 * x) + (3 * x)` = `5 * x`
+pub fn simplify(formula: &str) -> Result<Token, String> {
+    let mut lexer = Lexer::default();
+    lexer.parse(formula)?;
+    Ok(Token::from(&lexer.expr()).simplify())
+}
@@ -1,8 +1,12 @@
+use kalc_kman::{Formatter, Notation};
+
 fn main() {
     let args = std::env::args().skip(1).collect::<String>();
-    match kalc_kman::kalc_f64(&args) {
+    // 12 significant figures hide the rounding errors of the floats, like in `0.1 + 0.2`
+    let formatter = Formatter::default().with_notation(Notation::General(12));
+    match kalc_kman::kalc(&args) {
         Ok(res) => {
-            println!("{res}")
+            println!("{}", res.format(&formatter))
         }
         Err(error) => {
             eprintln!("Error: {error}")
@@ -0,0 +1,293 @@
+use std::fmt::Display;
+
+use crate::token::Token;
+
+/// A matrix of floats, stored row after row
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    data: Vec<f64>,
+}
+
+impl Matrix {
+    /// The numbers are row after row, there need to be `rows * cols` of them
+    pub fn new(rows: usize, cols: usize, data: Vec<f64>) -> Result<Self, String> {
+        if rows == 0 || cols == 0 {
+            return Err("A matrix needs at least one row and one column".into());
+        }
+        if rows.checked_mul(cols) != Some(data.len()) {
+            return Err(format!(
+                "A {rows}x{cols} matrix needs {} numbers, got: {}",
+                rows.saturating_mul(cols),
+                data.len()
+            ));
+        }
+        Ok(Self { rows, cols, data })
+    }
+
+    pub fn identity(n: usize) -> Self {
+        let mut data = vec![0.0; n * n];
+        for i in 0..n {
+            data[i * n + i] = 1.0;
+        }
+        Self {
+            rows: n,
+            cols: n,
+            data,
+        }
+    }
+
+    /// A list of lists of numbers with the same length, like `[[1, 2], [3, 4]]`
+    pub fn from_rows(rows: &[Token]) -> Option<Self> {
+        let cols = match rows.first()? {
+            Token::List(row) if !row.is_empty() => row.len(),
+            _ => return None,
+        };
+        let mut data = Vec::with_capacity(rows.len() * cols);
+        for row in rows {
+            match row {
+                Token::List(row) if row.len() == cols && row.iter().all(Token::is_num) => {
+                    data.extend(row.iter().map(Token::get_f64))
+                }
+                _ => return None,
+            }
+        }
+        Some(Self {
+            rows: rows.len(),
+            cols,
+            data,
+        })
+    }
+
+    /// The rows as lists
+    pub fn into_rows(self) -> Vec<Token> {
+        self.data
+            .chunks(self.cols)
+            .map(|row| Token::List(row.iter().copied().map(Token::F).collect()))
+            .collect()
+    }
+
+    pub fn into_data(self) -> Vec<f64> {
+        self.data
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.data[row * self.cols + col]
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.cols
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut data = Vec::with_capacity(self.data.len());
+        for col in 0..self.cols {
+            for row in 0..self.rows {
+                data.push(self.get(row, col))
+            }
+        }
+        Self {
+            rows: self.cols,
+            cols: self.rows,
+            data,
+        }
+    }
+
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Self {
+        Self {
+            data: self.data.iter().copied().map(f).collect(),
+            ..*self
+        }
+    }
+
+    /// Element by element, the shapes need to be the same
+    pub fn zip(&self, other: &Matrix, f: impl Fn(f64, f64) -> f64) -> Result<Self, String> {
+        if self.shape() != other.shape() {
+            return Err(format!(
+                "Matrices need the same shape, got: {} and {}",
+                self.shape_name(),
+                other.shape_name()
+            ));
+        }
+        let data = self.data.iter().zip(&other.data).map(|(a, b)| f(*a, *b));
+        Self::new(self.rows, self.cols, data.collect())
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Result<Self, String> {
+        if self.cols != other.rows {
+            return Err(format!(
+                "Cannot multiply a {} matrix with a {} matrix",
+                self.shape_name(),
+                other.shape_name()
+            ));
+        }
+        let mut data = vec![0.0; self.rows * other.cols];
+        for row in 0..self.rows {
+            for col in 0..other.cols {
+                data[row * other.cols + col] = (0..self.cols)
+                    .map(|k| self.get(row, k) * other.get(k, col))
+                    .sum();
+            }
+        }
+        Self::new(self.rows, other.cols, data)
+    }
+
+    /// The matrix times a column vector
+    pub fn mul_vector(&self, vector: &[f64]) -> Result<Vec<f64>, String> {
+        let column = Self::new(vector.len(), 1, vector.to_vec())?;
+        Ok(self.mul(&column)?.data)
+    }
+
+    /// Frobenius norm
+    pub fn norm(&self) -> f64 {
+        self.data.iter().map(|x| x * x).sum::<f64>().sqrt()
+    }
+
+    /// LU decomposition with partial pivoting, returns the factors, the permutation
+    /// and the sign of the permutation, `None` when the matrix is singular
+    fn lu(&self) -> Option<(Matrix, Vec<usize>, f64)> {
+        let n = self.rows;
+        let mut lu = self.clone();
+        let mut perm: Vec<usize> = (0..n).collect();
+        let mut sign = 1.0;
+        // the rounding errors grow with the size of the entries, `[[1e-13]]` is not singular
+        let tiny = n as f64 * f64::EPSILON * self.norm();
+        for k in 0..n {
+            let pivot =
+                (k..n).max_by(|a, b| lu.get(*a, k).abs().total_cmp(&lu.get(*b, k).abs()))?;
+            if lu.get(pivot, k).abs() <= tiny {
+                return None;
+            }
+            if pivot != k {
+                for col in 0..n {
+                    lu.data.swap(k * n + col, pivot * n + col);
+                }
+                perm.swap(k, pivot);
+                sign = -sign;
+            }
+            for row in k + 1..n {
+                let factor = lu.get(row, k) / lu.get(k, k);
+                lu.data[row * n + k] = factor;
+                for col in k + 1..n {
+                    lu.data[row * n + col] -= factor * lu.get(k, col);
+                }
+            }
+        }
+        Some((lu, perm, sign))
+    }
+
+    fn square(&self, name: &str) -> Result<(), String> {
+        if self.is_square() {
+            Ok(())
+        } else {
+            Err(format!(
+                "{name} needs a square matrix, got: {}",
+                self.shape_name()
+            ))
+        }
+    }
+
+    pub fn det(&self) -> Result<f64, String> {
+        self.square("det")?;
+        Ok(match self.lu() {
+            Some((lu, _, sign)) => (0..self.rows).map(|i| lu.get(i, i)).product::<f64>() * sign,
+            None => 0.0,
+        })
+    }
+
+    /// Solves `self * x = b` for every column of `b`
+    pub fn solve(&self, b: &Matrix) -> Result<Self, String> {
+        self.square("solve")?;
+        if b.rows != self.rows {
+            return Err(format!(
+                "solve needs {} values for a {} matrix, got: {}",
+                self.rows,
+                self.shape_name(),
+                b.shape_name()
+            ));
+        }
+        let (lu, perm, _) = self.lu().ok_or("The matrix is singular")?;
+        let n = self.rows;
+        let mut x = vec![0.0; n * b.cols];
+        for col in 0..b.cols {
+            let mut y: Vec<f64> = perm.iter().map(|row| b.get(*row, col)).collect();
+            for row in 0..n {
+                y[row] -= (0..row).map(|k| lu.get(row, k) * y[k]).sum::<f64>();
+            }
+            for row in (0..n).rev() {
+                y[row] -= (row + 1..n).map(|k| lu.get(row, k) * y[k]).sum::<f64>();
+                y[row] /= lu.get(row, row);
+            }
+            for row in 0..n {
+                x[row * b.cols + col] = y[row];
+            }
+        }
+        Self::new(n, b.cols, x)
+    }
+
+    pub fn solve_vector(&self, b: &[f64]) -> Result<Vec<f64>, String> {
+        let column = Self::new(b.len(), 1, b.to_vec())?;
+        Ok(self.solve(&column)?.data)
+    }
+
+    pub fn inv(&self) -> Result<Self, String> {
+        self.square("inv")?;
+        self.solve(&Self::identity(self.rows))
+    }
+
+    /// Like `2x3`
+    fn shape_name(&self) -> String {
+        format!("{}x{}", self.rows, self.cols)
+    }
+}
+
+impl Display for Matrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+        for (i, row) in self.data.chunks(self.cols).enumerate() {
+            if i > 0 {
+                write!(f, ", ")?
+            }
+            write!(f, "[")?;
+            for (j, num) in row.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?
+                }
+                write!(f, "{num}")?
+            }
+            write!(f, "]")?
+        }
+        write!(f, "]")
+    }
+}
+
+pub fn dot(a: &[f64], b: &[f64]) -> Result<f64, String> {
+    if a.len() != b.len() {
+        return Err(format!(
+            "Vectors need the same length, got: {} and {}",
+            a.len(),
+            b.len()
+        ));
+    }
+    Ok(a.iter().zip(b).map(|(a, b)| a * b).sum())
+}
+
+pub fn cross(a: &[f64], b: &[f64]) -> Result<Vec<f64>, String> {
+    match (a, b) {
+        ([a0, a1, a2], [b0, b1, b2]) => Ok(vec![
+            a1 * b2 - a2 * b1,
+            a2 * b0 - a0 * b2,
+            a0 * b1 - a1 * b0,
+        ]),
+        _ => Err(format!(
+            "cross needs two vectors of 3 numbers, got: {} and {}",
+            a.len(),
+            b.len()
+        )),
+    }
+}
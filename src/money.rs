@@ -0,0 +1,368 @@
+use std::{collections::HashMap, fmt::Display, ops::Neg, str::FromStr};
+
+use crate::{context::Context, token::Token};
+
+/// An exact fraction, the amounts of money and the exchange rates are not floats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rational {
+    num: i128,
+    /// Always positive and without common factors with `num`
+    den: i128,
+}
+
+impl Rational {
+    pub fn new(num: i128, den: i128) -> Result<Self, String> {
+        if den == 0 {
+            return Err(format!("Invalid fraction: {num} / 0"));
+        }
+        let gcd = gcd(num, den).max(1);
+        let sign = den.signum();
+        Ok(Self {
+            num: num / gcd * sign,
+            den: den / gcd * sign,
+        })
+    }
+
+    pub fn integer(num: i128) -> Self {
+        Self { num, den: 1 }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.num == 0
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// A number token, a float is the shortest decimal that gives the same float
+    pub fn from_token(token: &Token) -> Result<Self, String> {
+        match token {
+            Token::I(num) => Ok(Self::integer(*num as i128)),
+            Token::F(num) if num.is_finite() => num.to_string().parse(),
+            _ => Err(format!("Expected a number, got: {token}")),
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, String> {
+        let num = overflow(self.num.checked_mul(other.den))?
+            .checked_add(overflow(other.num.checked_mul(self.den))?);
+        Self::new(overflow(num)?, overflow(self.den.checked_mul(other.den))?)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, String> {
+        self.checked_add(-other)
+    }
+
+    pub fn checked_mul(self, other: Self) -> Result<Self, String> {
+        // the factors are reduced first, so the products stay smaller
+        let a = gcd(self.num, other.den).max(1);
+        let b = gcd(other.num, self.den).max(1);
+        Self::new(
+            overflow((self.num / a).checked_mul(other.num / b))?,
+            overflow((self.den / b).checked_mul(other.den / a))?,
+        )
+    }
+
+    /// Like with the other numbers, the division by 0 is 0
+    pub fn checked_div(self, other: Self) -> Result<Self, String> {
+        if other.is_zero() {
+            return Ok(Self::integer(0));
+        }
+        self.checked_mul(Self::new(other.den, other.num)?)
+    }
+
+    /// Rounded to the decimals, half away from zero
+    pub fn round(self, decimals: u32) -> Result<Self, String> {
+        let scale = overflow(10i128.checked_pow(decimals))?;
+        let scaled = overflow(self.num.checked_mul(scale))?;
+        let (quotient, remainder) = (scaled / self.den, scaled % self.den);
+        let rounded = if remainder.abs() * 2 >= self.den {
+            quotient + scaled.signum()
+        } else {
+            quotient
+        };
+        Self::new(rounded, scale)
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        let a = self.num.checked_mul(other.den)?;
+        let b = other.num.checked_mul(self.den)?;
+        Some(a.cmp(&b))
+    }
+}
+
+/// A decimal like `12.50` or `-0.9123`
+impl FromStr for Rational {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("Invalid decimal: {s}");
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s),
+        };
+        let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if whole.is_empty() && fraction.is_empty()
+            || !whole
+                .bytes()
+                .chain(fraction.bytes())
+                .all(|b| b.is_ascii_digit())
+        {
+            return Err(error());
+        }
+        let num: i128 = format!("{whole}{fraction}").parse().map_err(|_| error())?;
+        let den = 10i128
+            .checked_pow(fraction.len() as u32)
+            .ok_or_else(error)?;
+        Self::new(if negative { -num } else { num }, den)
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+fn overflow<T>(value: Option<T>) -> Result<T, String> {
+    value.ok_or_else(|| "The amount is too big to be exact".to_string())
+}
+
+/// An ISO 4217 currency with the decimals of its minor unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+pub struct Currency {
+    code: &'static str,
+    decimals: u32,
+}
+
+/// The currencies that can be written after a number, like `12.50 EUR`
+const CURRENCIES: &[(&str, u32)] = &[
+    ("AED", 2),
+    ("ARS", 2),
+    ("AUD", 2),
+    ("BHD", 3),
+    ("BRL", 2),
+    ("CAD", 2),
+    ("CHF", 2),
+    ("CLP", 0),
+    ("CNY", 2),
+    ("COP", 2),
+    ("CZK", 2),
+    ("DKK", 2),
+    ("EGP", 2),
+    ("EUR", 2),
+    ("GBP", 2),
+    ("HKD", 2),
+    ("HUF", 2),
+    ("IDR", 2),
+    ("ILS", 2),
+    ("INR", 2),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("MXN", 2),
+    ("MYR", 2),
+    ("NOK", 2),
+    ("NZD", 2),
+    ("OMR", 3),
+    ("PHP", 2),
+    ("PLN", 2),
+    ("RON", 2),
+    ("RUB", 2),
+    ("SAR", 2),
+    ("SEK", 2),
+    ("SGD", 2),
+    ("THB", 2),
+    ("TND", 3),
+    ("TRY", 2),
+    ("TWD", 2),
+    ("UAH", 2),
+    ("USD", 2),
+    ("VND", 0),
+    ("ZAR", 2),
+];
+
+impl Currency {
+    pub fn from_code(code: &str) -> Option<Self> {
+        CURRENCIES
+            .iter()
+            .find(|(known, _)| *known == code)
+            .map(|(code, decimals)| Self {
+                code,
+                decimals: *decimals,
+            })
+    }
+
+    pub fn code(self) -> &'static str {
+        self.code
+    }
+
+    /// The amounts are rounded to these decimals when they are converted or printed
+    pub fn decimals(self) -> u32 {
+        self.decimals
+    }
+}
+
+/// The exchange rates are given by the application, the calculator never fetches them
+pub trait Rates: std::fmt::Debug + Send + Sync {
+    /// How much one `from` is in `to`
+    fn rate(&self, from: Currency, to: Currency) -> Option<Rational>;
+}
+
+/// Fixed exchange rates, a rate can be used in both directions and two rates with the same
+/// currency give a cross rate, so `EUR` to `CHF` and `USD` to `CHF` are enough for `EUR` to `USD`
+#[derive(Debug, Clone, Default)]
+pub struct RateTable {
+    rates: HashMap<(Currency, Currency), Rational>,
+}
+
+impl RateTable {
+    /// `insert("USD", "EUR", "0.92")` when one dollar is 0.92 euros
+    pub fn insert(&mut self, from: &str, to: &str, rate: &str) -> Result<(), String> {
+        let currency = |code| Currency::from_code(code).ok_or(format!("Unknown currency: {code}"));
+        let rate: Rational = rate.parse()?;
+        if rate <= Rational::integer(0) {
+            return Err(format!("The rate needs to be positive, got: {rate}"));
+        }
+        self.rates.insert((currency(from)?, currency(to)?), rate);
+        Ok(())
+    }
+}
+
+impl RateTable {
+    /// The rate that was inserted or its inverse
+    fn direct(&self, from: Currency, to: Currency) -> Option<Rational> {
+        match self.rates.get(&(from, to)) {
+            Some(rate) => Some(*rate),
+            None => Rational::integer(1)
+                .checked_div(*self.rates.get(&(to, from))?)
+                .ok(),
+        }
+    }
+}
+
+impl Rates for RateTable {
+    fn rate(&self, from: Currency, to: Currency) -> Option<Rational> {
+        if let Some(rate) = self.direct(from, to) {
+            return Some(rate);
+        }
+        // sorted, so the same table always gives the same cross rate
+        let mut currencies: Vec<Currency> = self.rates.keys().flat_map(|(a, b)| [*a, *b]).collect();
+        currencies.sort_by_key(|currency| currency.code);
+        currencies.into_iter().find_map(|via| {
+            self.direct(from, via)?
+                .checked_mul(self.direct(via, to)?)
+                .ok()
+        })
+    }
+}
+
+/// An exact amount of a currency, like `12.50 EUR`
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Money {
+    amount: Rational,
+    currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount: Rational, currency: Currency) -> Self {
+        Self { amount, currency }
+    }
+
+    pub fn amount(self) -> Rational {
+        self.amount
+    }
+
+    pub fn currency(self) -> Currency {
+        self.currency
+    }
+
+    /// Converted with the rates of the context and rounded to the minor unit
+    pub fn to(self, currency: Currency, context: &Context) -> Result<Self, String> {
+        if currency == self.currency {
+            return Ok(self);
+        }
+        let rate = context
+            .rates
+            .as_ref()
+            .and_then(|rates| rates.rate(self.currency, currency))
+            .ok_or_else(|| {
+                format!(
+                    "No exchange rate from {} to {}",
+                    self.currency.code, currency.code
+                )
+            })?;
+        let amount = self.amount.checked_mul(rate)?.round(currency.decimals)?;
+        Ok(Self::new(amount, currency))
+    }
+
+    pub fn add(self, other: Self, context: &Context) -> Result<Self, String> {
+        let other = other.to(self.currency, context)?;
+        Ok(Self::new(
+            self.amount.checked_add(other.amount)?,
+            self.currency,
+        ))
+    }
+
+    pub fn sub(self, other: Self, context: &Context) -> Result<Self, String> {
+        self.add(-other, context)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Self {
+            num: -self.num,
+            den: self.den,
+        }
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money::new(-self.amount, self.currency)
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+/// With the decimals of the currency, like `12.50 EUR`
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let decimals = self.currency.decimals;
+        let Ok(rounded) = self.amount.round(decimals) else {
+            return write!(f, "{} {}", self.amount.to_f64(), self.currency.code);
+        };
+        // the rounded amount has a power of ten as the denominator
+        let scaled = rounded.num * (10i128.pow(decimals) / rounded.den);
+        let sign = if scaled < 0 { "-" } else { "" };
+        let (whole, fraction) = (
+            scaled.abs() / 10i128.pow(decimals),
+            scaled.abs() % 10i128.pow(decimals),
+        );
+        write!(f, "{sign}{whole}")?;
+        if decimals > 0 {
+            write!(f, ".{fraction:0width$}", width = decimals as usize)?;
+        }
+        write!(f, " {}", self.currency.code)
+    }
+}
@@ -0,0 +1,202 @@
+//! Number theory on 64 bit integers
+
+pub fn gcd(a: i64, b: i64) -> i64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i64
+}
+
+pub fn lcm(a: i64, b: i64) -> Result<i64, String> {
+    if a == 0 || b == 0 {
+        return Ok(0);
+    }
+    (a / gcd(a, b))
+        .checked_mul(b)
+        .and_then(i64::checked_abs)
+        .ok_or_else(|| format!("lcm({a}, {b}) is too big"))
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    (a as u128 * b as u128 % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// `base ^ exp % m`, the result is between 0 and `m`
+pub fn modpow(base: i64, exp: i64, m: i64) -> Result<i64, String> {
+    if m <= 0 {
+        return Err(format!("modpow needs a positive modulus, got: {m}"));
+    }
+    let base = base.rem_euclid(m);
+    if exp < 0 {
+        let inverse = modinv(base, m)?;
+        return Ok(pow_mod(inverse as u64, exp.unsigned_abs(), m as u64) as i64);
+    }
+    Ok(pow_mod(base as u64, exp as u64, m as u64) as i64)
+}
+
+/// The `x` where `a * x % m` is 1
+pub fn modinv(a: i64, m: i64) -> Result<i64, String> {
+    if m <= 0 {
+        return Err(format!("modinv needs a positive modulus, got: {m}"));
+    }
+    // extended euclid, i128 so that the coefficients never overflow
+    let (mut r0, mut r1) = (m as i128, a.rem_euclid(m) as i128);
+    let (mut t0, mut t1) = (0i128, 1i128);
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+    if r0 != 1 {
+        return Err(format!("{a} has no inverse modulo {m}"));
+    }
+    Ok(t0.rem_euclid(m as i128) as i64)
+}
+
+/// Deterministic Miller-Rabin, these bases are enough for every 64 bit number
+pub fn is_prime(n: i64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    let n = n as u64;
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    for p in BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let (mut d, mut s) = (n - 1, 0);
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+    'bases: for a in BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// The smallest prime bigger than `n`
+pub fn next_prime(n: i64) -> Result<i64, String> {
+    let mut candidate = n.max(1);
+    loop {
+        candidate = candidate
+            .checked_add(1)
+            .ok_or_else(|| format!("There is no prime after {n} that fits in 64 bits"))?;
+        if is_prime(candidate) {
+            return Ok(candidate);
+        }
+    }
+}
+
+/// Pollard's rho with Floyd's cycle detection, `n` is odd and not a prime
+fn pollard_rho(n: u64) -> u64 {
+    let mut c = 1;
+    loop {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y) as i64, n as i64) as u64;
+        }
+        if d != n {
+            return d;
+        }
+        c += 1;
+    }
+}
+
+fn factor_into(n: u64, factors: &mut Vec<i64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n as i64) {
+        factors.push(n as i64);
+        return;
+    }
+    let d = pollard_rho(n);
+    factor_into(d, factors);
+    factor_into(n / d, factors);
+}
+
+/// The prime factors sorted, with repeats, a negative number starts with -1
+pub fn factor(n: i64) -> Result<Vec<i64>, String> {
+    if n == 0 {
+        return Err("0 cannot be factored".into());
+    }
+    let mut factors = Vec::new();
+    if n < 0 {
+        factors.push(-1);
+    }
+    let mut rest = n.unsigned_abs();
+    for p in [2, 3, 5, 7, 11, 13] {
+        while rest.is_multiple_of(p) {
+            factors.push(p as i64);
+            rest /= p;
+        }
+    }
+    let start = factors.len();
+    factor_into(rest, &mut factors);
+    factors[start..].sort();
+    Ok(factors)
+}
+
+/// Euler's totient, how many numbers up to `n` are coprime with `n`
+pub fn totient(n: i64) -> Result<i64, String> {
+    if n < 1 {
+        return Err(format!("totient needs a positive number, got: {n}"));
+    }
+    let mut factors = factor(n)?;
+    factors.dedup();
+    Ok(factors.iter().fold(n, |result, p| result / p * (p - 1)))
+}
+
+/// Every positive divisor, sorted
+pub fn divisors(n: i64) -> Result<Vec<i64>, String> {
+    if n == 0 {
+        return Err("0 has infinite divisors".into());
+    }
+    // 2^63 divides itself but does not fit
+    if n == i64::MIN {
+        return Err(format!("The divisors of {n} are too big"));
+    }
+    let mut divisors = vec![1];
+    let factors: Vec<i64> = factor(n)?.into_iter().filter(|p| *p > 0).collect();
+    for run in factors.chunk_by(|a, b| a == b) {
+        let current = divisors.len();
+        let mut power = 1;
+        for p in run {
+            power *= p;
+            for i in 0..current {
+                divisors.push(divisors[i] * power);
+            }
+        }
+    }
+    divisors.sort();
+    Ok(divisors)
+}
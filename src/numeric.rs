@@ -0,0 +1,173 @@
+use crate::{
+    context::Context,
+    executor::Binding,
+    function::{float, Function},
+    token::Token,
+};
+
+/// The wanted error of `integrate`, relative to the result
+const TOLERANCE: f64 = 1e-10;
+/// The most intervals `integrate` splits the range into
+const MAX_INTERVALS: usize = 1000;
+
+/// The nodes of the 15 point Kronrod rule from the middle outwards, the odd ones
+/// are the nodes of the 7 point Gauss rule
+const KRONROD_NODES: [f64; 8] = [
+    0.0,
+    0.20778495500789848,
+    0.4058451513773972,
+    0.5860872354676911,
+    0.7415311855993945,
+    0.8648644233597691,
+    0.9491079123427585,
+    0.9914553711208126,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.20948214108472782,
+    0.20443294007529889,
+    0.19035057806478542,
+    0.1690047266392679,
+    0.14065325971552592,
+    0.10479001032225019,
+    0.06309209262997856,
+    0.022935322010529224,
+];
+/// For the nodes 0, 2, 4 and 6
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.4179591836734694,
+    0.3818300505051189,
+    0.27970539148927664,
+    0.1294849661688697,
+];
+
+/// The integral over one interval and how far the Gauss rule is from it
+fn gauss_kronrod(f: &Binding, a: f64, b: f64) -> Result<(f64, f64), String> {
+    let middle = (a + b) / 2.0;
+    let half = (b - a) / 2.0;
+    let center = f.eval(middle)?;
+    let mut kronrod = center * KRONROD_WEIGHTS[0];
+    let mut gauss = center * GAUSS_WEIGHTS[0];
+    for i in 1..KRONROD_NODES.len() {
+        let dx = half * KRONROD_NODES[i];
+        let sum = f.eval(middle - dx)? + f.eval(middle + dx)?;
+        kronrod += sum * KRONROD_WEIGHTS[i];
+        if i % 2 == 0 {
+            gauss += sum * GAUSS_WEIGHTS[i / 2];
+        }
+    }
+    let (kronrod, gauss) = (kronrod * half, gauss * half);
+    if !kronrod.is_finite() {
+        return Err(format!(
+            "integrate got a value that is not finite between {a} and {b}"
+        ));
+    }
+    Ok((kronrod, (kronrod - gauss).abs()))
+}
+
+/// Adaptive Gauss–Kronrod, the interval with the biggest error is split until the
+/// error is small enough, returns the integral and the estimated error
+pub fn integrate(f: &Binding, a: f64, b: f64) -> Result<(f64, f64), String> {
+    if !a.is_finite() || !b.is_finite() {
+        return Err(format!("integrate needs finite limits, got: {a} and {b}"));
+    }
+    if a == b {
+        return Ok((0.0, 0.0));
+    }
+    let (value, error) = gauss_kronrod(f, a, b)?;
+    let mut intervals = vec![(a, b, value, error)];
+    loop {
+        let value: f64 = intervals.iter().map(|i| i.2).sum();
+        let error: f64 = intervals.iter().map(|i| i.3).sum();
+        if error <= TOLERANCE * value.abs().max(1.0) || intervals.len() >= MAX_INTERVALS {
+            return Ok((value, error));
+        }
+        let worst = (0..intervals.len())
+            .max_by(|x, y| intervals[*x].3.total_cmp(&intervals[*y].3))
+            .unwrap_or(0);
+        let (a, b, _, _) = intervals.swap_remove(worst);
+        let middle = (a + b) / 2.0;
+        if middle <= a.min(b) || middle >= a.max(b) {
+            // the interval cannot be split anymore
+            return Ok((value, error));
+        }
+        for (a, b) in [(a, middle), (middle, b)] {
+            let (value, error) = gauss_kronrod(f, a, b)?;
+            intervals.push((a, b, value, error))
+        }
+    }
+}
+
+/// Central differences with smaller and smaller steps and Richardson extrapolation
+/// (Ridders' method), returns the derivative and the estimated error
+pub fn nderiv(f: &Binding, x: f64) -> Result<(f64, f64), String> {
+    const STEPS: usize = 10;
+    const SHRINK: f64 = 1.4;
+    let mut h = 0.1 * x.abs().max(1.0);
+    let central = |h: f64| Ok::<_, String>((f.eval(x + h)? - f.eval(x - h)?) / (2.0 * h));
+
+    let mut table = [[0.0; STEPS]; STEPS];
+    table[0][0] = central(h)?;
+    let (mut best, mut error) = (table[0][0], f64::INFINITY);
+    for i in 1..STEPS {
+        h /= SHRINK;
+        table[0][i] = central(h)?;
+        let mut factor = SHRINK * SHRINK;
+        for j in 1..=i {
+            table[j][i] = (table[j - 1][i] * factor - table[j - 1][i - 1]) / (factor - 1.0);
+            factor *= SHRINK * SHRINK;
+            let change = (table[j][i] - table[j - 1][i])
+                .abs()
+                .max((table[j][i] - table[j - 1][i - 1]).abs());
+            if change <= error {
+                (best, error) = (table[j][i], change);
+            }
+        }
+        // stop when the higher order gets worse
+        if (table[i][i] - table[i - 1][i - 1]).abs() >= 2.0 * error {
+            break;
+        }
+    }
+    if !best.is_finite() {
+        return Err(format!("nderiv got a value that is not finite at {x}"));
+    }
+    Ok((best, error))
+}
+
+/// `integrate(expr, x, a, b)` and `nderiv(expr, x, at)`, with `true` as the last
+/// argument the result is `[value, error]`
+pub fn call(function: Function, args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    let usage = match function {
+        Function::Integrate => "integrate(pow(x, 2), x, 0, 1)",
+        _ => "nderiv(pow(x, 2), x, 3)",
+    };
+    let mut args = args.into_iter();
+    let (Some(expr), Some(Token::Var(var))) = (args.next(), args.next()) else {
+        return Err(format!(
+            "{function} needs an expression and a variable: {usage}"
+        ));
+    };
+    let mut rest: Vec<Token> = args.collect();
+    let with_error = match rest.last() {
+        Some(Token::Bool(with_error)) => {
+            let with_error = *with_error;
+            rest.pop();
+            with_error
+        }
+        _ => false,
+    };
+    let f = Binding {
+        expr,
+        var: &var,
+        context,
+    };
+    let (value, error) = match (function, rest.as_slice()) {
+        (Function::Integrate, [a, b]) => integrate(&f, float(function, a)?, float(function, b)?)?,
+        (Function::NDeriv, [at]) => nderiv(&f, float(function, at)?)?,
+        _ => return Err(format!("{function} needs the arguments like: {usage}")),
+    };
+    Ok(if with_error {
+        Token::List(vec![Token::F(value), Token::F(error)])
+    } else {
+        Token::F(value)
+    })
+}
@@ -0,0 +1,613 @@
+use std::fmt::Display;
+
+use crate::{
+    context::Context,
+    executor::Executor,
+    function::Function,
+    number::{divisors, gcd, lcm},
+    token::Token,
+};
+
+/// The biggest power that is expanded, like `pow(x + 1, 64)`
+const MAX_DEGREE: i64 = 64;
+
+/// The coefficients of the polynomial of the variable from the constant up,
+/// `None` when the token is not a polynomial, like `sin(x)`
+pub fn coefficients(
+    token: &Token,
+    var: &str,
+    context: &Context,
+) -> Result<Option<Vec<f64>>, String> {
+    if !token.contains_var(var) {
+        let value = Executor::evaluate(token.clone(), context)?;
+        return Ok(value.is_num().then(|| vec![value.get_f64()]));
+    }
+    let coefficients = |token: &Token| coefficients(token, var, context);
+    let result = match token {
+        Token::Var(_) => Some(vec![0.0, 1.0]),
+        Token::Group(tokens) if tokens.len() == 1 => coefficients(&tokens[0])?,
+        Token::Add(a, b) => both(coefficients(a)?, coefficients(b)?, add),
+        Token::Sub(a, b) | Token::Equation(a, b) => {
+            both(coefficients(a)?, coefficients(b)?, |a, b| {
+                add(a, scale(b, -1.0))
+            })
+        }
+        Token::Neg(a) => coefficients(a)?.map(|a| scale(a, -1.0)),
+        Token::Mul(a, b) => both(coefficients(a)?, coefficients(b)?, |a, b| mul(&a, &b)),
+        Token::Div(a, b) if !b.contains_var(var) => {
+            let divisor = Executor::evaluate(b.as_ref().clone(), context)?;
+            coefficients(a)?
+                .filter(|_| divisor.is_num() && !divisor.is_zero())
+                .map(|a| scale(a, 1.0 / divisor.get_f64()))
+        }
+        Token::Pow(a, n) if !n.contains_var(var) => {
+            let n = Executor::evaluate(n.as_ref().clone(), context)?;
+            match (coefficients(a)?, n) {
+                (Some(a), Token::I(n)) if (0..=MAX_DEGREE).contains(&n) => {
+                    Some((0..n).fold(vec![1.0], |result, _| mul(&result, &a)))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+    Ok(result.map(trim))
+}
+
+fn both(
+    a: Option<Vec<f64>>,
+    b: Option<Vec<f64>>,
+    f: impl Fn(Vec<f64>, Vec<f64>) -> Vec<f64>,
+) -> Option<Vec<f64>> {
+    Some(f(a?, b?))
+}
+
+/// Removes the zeros of the biggest powers
+pub fn trim(mut coefficients: Vec<f64>) -> Vec<f64> {
+    while coefficients.last() == Some(&0.0) {
+        coefficients.pop();
+    }
+    coefficients
+}
+
+pub fn add(mut a: Vec<f64>, b: Vec<f64>) -> Vec<f64> {
+    if a.len() < b.len() {
+        a.resize(b.len(), 0.0);
+    }
+    for (a, b) in a.iter_mut().zip(b) {
+        *a += b;
+    }
+    a
+}
+
+pub fn scale(a: Vec<f64>, factor: f64) -> Vec<f64> {
+    a.into_iter().map(|a| a * factor).collect()
+}
+
+pub fn mul(a: &[f64], b: &[f64]) -> Vec<f64> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut result = vec![0.0; a.len() + b.len() - 1];
+    for (i, a) in a.iter().enumerate() {
+        for (j, b) in b.iter().enumerate() {
+            result[i + j] += a * b;
+        }
+    }
+    result
+}
+
+/// Horner's method
+pub fn eval(coefficients: &[f64], x: f64) -> f64 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0.0, |result, c| result * x + c)
+}
+
+pub fn derivative(coefficients: &[f64]) -> Vec<f64> {
+    coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, c)| c * i as f64)
+        .collect()
+}
+
+/// Every real root sorted, the roots are between the roots of the derivative
+pub fn real_roots(coefficients: &[f64]) -> Result<Vec<f64>, String> {
+    let c = trim(coefficients.to_vec());
+    match c.len() {
+        0 => return Err("Every number is a solution".into()),
+        1 => return Ok(Vec::new()),
+        2 => return Ok(vec![-c[0] / c[1]]),
+        _ => {}
+    }
+    let lead = c[c.len() - 1];
+    // Cauchy's bound, every root is inside
+    let bound = 1.0 + c.iter().map(|c| (c / lead).abs()).fold(0.0, f64::max);
+    let critical = real_roots(&derivative(&c))?;
+    let p = |x: f64| eval(&c, x);
+    // how big the rounding errors of `p(x)` can be
+    let noise = |x: f64| {
+        1e-9 * c
+            .iter()
+            .enumerate()
+            .map(|(i, c)| c.abs() * x.abs().powi(i as i32))
+            .sum::<f64>()
+    };
+
+    let mut points = vec![-bound];
+    points.extend(critical.iter().copied().filter(|x| x.abs() < bound));
+    points.push(bound);
+    let mut roots = Vec::new();
+    for window in points.windows(2) {
+        let (a, b) = (window[0], window[1]);
+        if p(a).abs() <= noise(a) {
+            // a root of the derivative too, like the double root of `x * x`
+            roots.push(a);
+        } else if p(a).signum() != p(b).signum() && p(b).abs() > noise(b) {
+            let root = crate::roots::brent(|x| Ok(p(x)), a, b)?;
+            roots.push(root);
+        }
+    }
+    roots.dedup_by(|a, b| (*a - *b).abs() <= 1e-9 * b.abs().max(1.0));
+    Ok(roots)
+}
+
+/// A polynomial of one variable, like `pow(x, 2) - 1` of `x`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    var: String,
+    /// From the constant up, without zeros at the end
+    coefficients: Vec<f64>,
+}
+
+impl Polynomial {
+    /// The coefficients are from the constant up, `[-1, 0, 1]` is `pow(x, 2) - 1`
+    pub fn new(var: impl Into<String>, coefficients: Vec<f64>) -> Self {
+        Self {
+            var: var.into(),
+            coefficients: trim(coefficients),
+        }
+    }
+
+    /// Collects the coefficients of the token, the other parts are calculated
+    pub fn from_token(token: &Token, var: &str, context: &Context) -> Result<Self, String> {
+        match coefficients(token, var, context)? {
+            Some(coefficients) => Ok(Self::new(var, coefficients)),
+            None => Err(format!("Not a polynomial of {var}: {token}")),
+        }
+    }
+
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+
+    pub fn coefficients(&self) -> &[f64] {
+        &self.coefficients
+    }
+
+    /// `None` for the polynomial 0
+    pub fn degree(&self) -> Option<usize> {
+        self.coefficients.len().checked_sub(1)
+    }
+
+    /// The quotient and the remainder of the long division
+    pub fn div_rem(&self, divisor: &Polynomial) -> Result<(Self, Self), String> {
+        let Some(n) = divisor.degree() else {
+            return Err("Cannot divide by the polynomial 0".into());
+        };
+        let lead = divisor.coefficients[n];
+        let mut rest = self.coefficients.clone();
+        let len = rest.len().saturating_sub(n);
+        let mut quotient = vec![0.0; len];
+        for i in (0..len).rev() {
+            let q = rest[i + n] / lead;
+            quotient[i] = q;
+            for (j, d) in divisor.coefficients.iter().enumerate() {
+                rest[i + j] -= q * d;
+            }
+            rest[i + n] = 0.0;
+        }
+        Ok((
+            Self::new(self.var.clone(), quotient),
+            Self::new(self.var.clone(), rest),
+        ))
+    }
+
+    /// Every complex root as the real and the imaginary part, found with the
+    /// Durand–Kerner method and sorted
+    pub fn roots(&self) -> Result<Vec<(f64, f64)>, String> {
+        const MAX_ITERATIONS: usize = 1000;
+        let Some(n) = self.degree() else {
+            return Err("Every number is a root of the polynomial 0".into());
+        };
+        let lead = self.coefficients[n];
+        let monic: Vec<f64> = self.coefficients.iter().map(|c| c / lead).collect();
+        let p = |z: Complex| eval_c(&monic, z);
+        // the rounding errors of `p(z)` are about this big
+        let sizes: Vec<f64> = monic.iter().map(|c| c.abs()).collect();
+        let bound = |z: Complex| eval(&sizes, abs_c(z).max(1.0));
+        // the starting points cannot be on a line or a circle around 0
+        let mut z: Vec<Complex> = (0..n)
+            .scan((1.0, 0.0), |power, _| {
+                let current = *power;
+                *power = mul_c(*power, (0.4, 0.9));
+                Some(current)
+            })
+            .collect();
+        // a repeated root is found slowly and only to a root of the precision, the
+        // iteration also stops when the steps do not get smaller anymore
+        let (mut smallest, mut stalled) = (f64::INFINITY, 0);
+        for _ in 0..MAX_ITERATIONS {
+            let mut change: f64 = 0.0;
+            for i in 0..n {
+                let denominator = (0..n)
+                    .filter(|j| *j != i)
+                    .fold((1.0, 0.0), |result, j| mul_c(result, sub_c(z[i], z[j])));
+                let delta = div_c(p(z[i]), denominator);
+                z[i] = sub_c(z[i], delta);
+                change = change.max(abs_c(delta) / abs_c(z[i]).max(1.0));
+            }
+            if change < smallest {
+                (smallest, stalled) = (change, 0);
+            } else {
+                stalled += 1;
+            }
+            if change <= 1e-14 || stalled > 100 {
+                break;
+            }
+        }
+        let far =
+            |z: &Complex| !z.0.is_finite() || !z.1.is_finite() || abs_c(p(*z)) > 1e-10 * bound(*z);
+        if z.iter().any(far) {
+            return Err(format!("roots did not converge for: {self}"));
+        }
+        // the copies of a repeated root are scattered around it, the root `m` times is
+        // a simple root of the derivative `m - 1` times that is found exactly
+        let mut done = vec![false; n];
+        for i in 0..n {
+            if done[i] {
+                continue;
+            }
+            let size = abs_c(z[i]).max(1.0);
+            let mut close: Vec<usize> = (i..n)
+                .filter(|j| !done[*j] && abs_c(sub_c(z[*j], z[i])) <= 0.05 * size)
+                .collect();
+            close.sort_by(|a, b| {
+                let distance = |j: &usize| abs_c(sub_c(z[*j], z[i]));
+                distance(a).total_cmp(&distance(b))
+            });
+            // close roots that are not the same are kept
+            for m in (2..=close.len()).rev() {
+                let sum = close[..m]
+                    .iter()
+                    .fold((0.0, 0.0), |sum, j| add_c(sum, z[*j]));
+                let mean = (sum.0 / m as f64, sum.1 / m as f64);
+                let slope = (1..m).fold(monic.clone(), |c, _| derivative(&c));
+                let root = newton_c(&slope, mean);
+                if abs_c(p(root)) <= 1e-12 * bound(root) {
+                    for j in &close[..m] {
+                        (z[*j], done[*j]) = (root, true);
+                    }
+                    break;
+                }
+            }
+        }
+        let mut roots: Vec<Complex> = z
+            .into_iter()
+            .map(|(re, im)| {
+                let size = re.abs().max(im.abs()).max(1.0);
+                let clean = |x: f64| if x.abs() <= 1e-9 * size { 0.0 } else { x };
+                (clean(re), clean(im))
+            })
+            .collect();
+        roots.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        Ok(roots)
+    }
+
+    /// Factors over the rationals, like `(x - 1) * (x + 1)` for `pow(x, 2) - 1`,
+    /// the polynomial is returned as it is when it cannot be factored
+    pub fn factor(&self) -> Token {
+        self.factor_rational().unwrap_or_else(|| self.to_token())
+    }
+
+    fn factor_rational(&self) -> Option<Token> {
+        const MAX_COEFFICIENT: i64 = 1_000_000_000_000;
+        self.degree().filter(|n| *n > 0)?;
+        let rationals = self
+            .coefficients
+            .iter()
+            .map(|c| rational(*c))
+            .collect::<Option<Vec<(i64, i64)>>>()?;
+        let denominator = rationals
+            .iter()
+            .try_fold(1, |result, (_, d)| lcm(result, *d).ok())?;
+        let mut integers = rationals
+            .iter()
+            .map(|(n, d)| n.checked_mul(denominator / d))
+            .collect::<Option<Vec<i64>>>()?;
+        let mut content = integers.iter().fold(0, |g, c| gcd(g, *c));
+        if integers[integers.len() - 1] < 0 {
+            content = -content;
+        }
+        for c in &mut integers {
+            *c /= content;
+        }
+        if integers.iter().any(|c| c.abs() > MAX_COEFFICIENT) {
+            return None;
+        }
+
+        let mut factors = Vec::new();
+        let zeros = integers.iter().take_while(|c| **c == 0).count();
+        if zeros > 0 {
+            factors.push((linear(&self.var, 0, 1), zeros));
+            integers.drain(..zeros);
+        }
+        let lead = integers[integers.len() - 1];
+        let ps = divisors(integers[0]).ok()?;
+        let qs = divisors(lead).ok()?;
+        for q in &qs {
+            for p in ps.iter().flat_map(|p| [-p, *p]) {
+                if gcd(p, *q) != 1 {
+                    continue;
+                }
+                let mut multiplicity = 0;
+                while integers.len() > 1 && is_rational_root(&integers, p, *q) {
+                    integers = divide_linear(&integers, p, *q);
+                    multiplicity += 1;
+                }
+                if multiplicity > 0 {
+                    factors.push((linear(&self.var, p, *q), multiplicity));
+                }
+            }
+        }
+        let mut constant = content as f64 / denominator as f64;
+        match integers.as_slice() {
+            [c] => constant *= *c as f64,
+            _ => {
+                let rest = integers.into_iter().map(|c| c as f64).collect();
+                factors.push((Self::new(self.var.clone(), rest).to_token(), 1));
+            }
+        }
+
+        let factors = factors
+            .into_iter()
+            .map(|(token, multiplicity)| match multiplicity {
+                1 => token,
+                m => Token::pow(token, Token::I(m as i64)),
+            });
+        let token = match constant {
+            1.0 => factors.reduce(Token::m)?,
+            _ => factors.fold(number(constant), Token::m),
+        };
+        Some(token)
+    }
+
+    /// Like `pow(x, 2) + (3 * x) - 1`, from the biggest power down
+    pub fn to_token(&self) -> Token {
+        let var = Token::Var(self.var.clone());
+        let term = |power: usize, c: f64| {
+            let base = match power {
+                0 => return number(c),
+                1 => var.clone(),
+                _ => Token::pow(var.clone(), Token::I(power as i64)),
+            };
+            match c {
+                1.0 => base,
+                -1.0 => Token::Neg(Box::new(base)),
+                _ => Token::m(number(c), base),
+            }
+        };
+        let mut terms = self
+            .coefficients
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, c)| **c != 0.0);
+        let Some((power, c)) = terms.next() else {
+            return Token::I(0);
+        };
+        terms.fold(term(power, *c), |result, (power, c)| {
+            if *c < 0.0 {
+                Token::s(result, term(power, -c))
+            } else {
+                Token::a(result, term(power, *c))
+            }
+        })
+    }
+}
+
+impl Display for Polynomial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_token())
+    }
+}
+
+/// An integer when the float has no fraction
+fn number(num: f64) -> Token {
+    if num.fract() == 0.0 && num.abs() < 1e15 {
+        Token::I(num as i64)
+    } else {
+        Token::F(num)
+    }
+}
+
+type Complex = (f64, f64);
+
+/// Newton's method for a complex root that is not repeated
+fn newton_c(coefficients: &[f64], mut z: Complex) -> Complex {
+    let slope = derivative(coefficients);
+    for _ in 0..100 {
+        let delta = div_c(eval_c(coefficients, z), eval_c(&slope, z));
+        if !delta.0.is_finite() || !delta.1.is_finite() {
+            break;
+        }
+        z = sub_c(z, delta);
+        if abs_c(delta) <= 1e-15 * abs_c(z).max(1.0) {
+            break;
+        }
+    }
+    z
+}
+
+/// Horner's method for a complex number
+fn eval_c(coefficients: &[f64], z: Complex) -> Complex {
+    coefficients
+        .iter()
+        .rev()
+        .fold((0.0, 0.0), |result, c| add_c(mul_c(result, z), (*c, 0.0)))
+}
+
+fn add_c(a: Complex, b: Complex) -> Complex {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub_c(a: Complex, b: Complex) -> Complex {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul_c(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn div_c(a: Complex, b: Complex) -> Complex {
+    let size = b.0 * b.0 + b.1 * b.1;
+    (
+        (a.0 * b.0 + a.1 * b.1) / size,
+        (a.1 * b.0 - a.0 * b.1) / size,
+    )
+}
+
+fn abs_c(a: Complex) -> f64 {
+    a.0.hypot(a.1)
+}
+
+/// The closest fraction with a small denominator, found with continued fractions
+fn rational(x: f64) -> Option<(i64, i64)> {
+    const MAX_DENOMINATOR: i64 = 1_000_000;
+    if !x.is_finite() || x.abs() > 1e15 {
+        return None;
+    }
+    let (mut h, mut h1, mut k, mut k1) = (1i64, 0i64, 0i64, 1i64);
+    let mut rest = x;
+    loop {
+        let a = rest.floor();
+        (h, h1) = ((a as i64).checked_mul(h)?.checked_add(h1)?, h);
+        (k, k1) = ((a as i64).checked_mul(k)?.checked_add(k1)?, k);
+        if k > MAX_DENOMINATOR {
+            return None;
+        }
+        if (h as f64 / k as f64 - x).abs() <= 1e-12 * x.abs().max(1.0) {
+            return Some((h, k));
+        }
+        rest = 1.0 / (rest - a);
+    }
+}
+
+/// `q * x - p`, or `x - p` when `q` is 1
+fn linear(var: &str, p: i64, q: i64) -> Token {
+    let var = Token::Var(var.to_string());
+    let x = match q {
+        1 => var,
+        q => Token::m(Token::I(q), var),
+    };
+    match p {
+        0 if q == 1 => x,
+        p if p < 0 => Token::a(x, Token::I(-p)),
+        p => Token::s(x, Token::I(p)),
+    }
+}
+
+/// `p / q` is a root when `sum(a[k] * pow(p, k) * pow(q, n - k))` is 0
+fn is_rational_root(integers: &[i64], p: i64, q: i64) -> bool {
+    let n = integers.len() - 1;
+    let mut sum: i128 = 0;
+    let (mut p_power, mut q_power): (i128, i128) = (1, (q as i128).pow(n as u32));
+    for (k, a) in integers.iter().enumerate() {
+        let term = (*a as i128)
+            .checked_mul(p_power)
+            .and_then(|t| t.checked_mul(q_power));
+        match term.and_then(|t| sum.checked_add(t)) {
+            Some(next) => sum = next,
+            None => return false,
+        }
+        if k < n {
+            match p_power.checked_mul(p as i128) {
+                Some(next) => p_power = next,
+                None => return false,
+            }
+            q_power /= q as i128;
+        }
+    }
+    sum == 0
+}
+
+/// Divides by `q * x - p`, the division has no remainder
+fn divide_linear(integers: &[i64], p: i64, q: i64) -> Vec<i64> {
+    let n = integers.len() - 1;
+    let mut quotient = vec![0; n];
+    let mut carry = 0;
+    for i in (0..n).rev() {
+        // the coefficient of `x^(i + 1)` minus what was already taken
+        let c = integers[i + 1] + carry;
+        quotient[i] = c / q;
+        carry = quotient[i] * p;
+    }
+    quotient
+}
+
+/// `expand`, `coeffs`, `deg`, `polydiv`, `roots` and `factor` of a polynomial,
+/// the variable can be left out when the expression has only one
+pub fn call(function: Function, args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    let count = match function {
+        Function::PolyDiv => 2,
+        _ => 1,
+    };
+    if args.len() != count && args.len() != count + 1 {
+        return Err(format!(
+            "{function} needs {count} polynomials and an optional variable, got: {} arguments",
+            args.len()
+        ));
+    }
+    let var = match args.get(count) {
+        Some(Token::Var(var)) => var.clone(),
+        Some(token) => return Err(format!("{function} needs a variable, got: {token}")),
+        None => args
+            .iter()
+            .find_map(Token::find_var)
+            .unwrap_or("x")
+            .to_string(),
+    };
+    let polynomial = Polynomial::from_token(&args[0], &var, context)?;
+    // a lambda like the result of `diff`, the variable is not known
+    let lambda = |token: Token| Ok(Token::Lambda(var.clone(), Box::new(token)));
+    match function {
+        Function::Expand => lambda(polynomial.to_token()),
+        Function::Factor => lambda(polynomial.factor()),
+        // from the biggest power down like it is written
+        Function::Coeffs => Ok(Token::List(
+            polynomial
+                .coefficients()
+                .iter()
+                .rev()
+                .map(|c| number(*c))
+                .collect(),
+        )),
+        // the polynomial 0 has the degree -1
+        Function::Deg => Ok(Token::I(polynomial.degree().map_or(-1, |n| n as i64))),
+        Function::PolyDiv => {
+            let divisor = Polynomial::from_token(&args[1], &var, context)?;
+            let (quotient, remainder) = polynomial.div_rem(&divisor)?;
+            lambda(Token::List(vec![quotient.to_token(), remainder.to_token()]))
+        }
+        _ => Ok(Token::List(
+            polynomial
+                .roots()?
+                .into_iter()
+                .map(|(re, im)| Token::List(vec![number(re), number(im)]))
+                .collect(),
+        )),
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{context::Context, executor::Binding, polynomial, token::Token};
+
+const MAX_ITERATIONS: usize = 100;
+const TOLERANCE: f64 = 1e-12;
+
+fn not_converged() -> String {
+    format!(
+        "solve did not converge after {MAX_ITERATIONS} iterations, \
+         try another guess or a range like solve(f(x) = 0, x, lo, hi)"
+    )
+}
+
+/// Brent's method, the function needs different signs at `a` and `b`
+pub fn brent(mut f: impl FnMut(f64) -> Result<f64, String>, a: f64, b: f64) -> Result<f64, String> {
+    let (mut a, mut b) = (a, b);
+    let (mut fa, mut fb) = (f(a)?, f(b)?);
+    if fa == 0.0 {
+        return Ok(a);
+    }
+    if fb == 0.0 {
+        return Ok(b);
+    }
+    if fa.signum() == fb.signum() {
+        return Err(format!(
+            "solve needs a range where the sides have different signs, got: {a} and {b}"
+        ));
+    }
+    if fa.abs() < fb.abs() {
+        (a, b, fa, fb) = (b, a, fb, fa);
+    }
+    let (mut c, mut fc) = (a, fa);
+    let mut d = b - a;
+    let mut bisected = true;
+    for _ in 0..MAX_ITERATIONS {
+        if fb == 0.0 || (b - a).abs() <= TOLERANCE * b.abs().max(1.0) {
+            return Ok(b);
+        }
+        let mut s = if fa != fc && fb != fc {
+            // inverse quadratic interpolation
+            a * fb * fc / ((fa - fb) * (fa - fc))
+                + b * fa * fc / ((fb - fa) * (fb - fc))
+                + c * fa * fb / ((fc - fa) * (fc - fb))
+        } else {
+            // secant
+            b - fb * (b - a) / (fb - fa)
+        };
+        let between = (s - (3.0 * a + b) / 4.0) * (s - b) < 0.0;
+        let slow = if bisected {
+            (s - b).abs() >= (b - c).abs() / 2.0
+        } else {
+            (s - b).abs() >= (c - d).abs() / 2.0
+        };
+        bisected = !between || slow;
+        if bisected {
+            s = (a + b) / 2.0;
+        }
+        let fs = f(s)?;
+        d = c;
+        (c, fc) = (b, fb);
+        if fa.signum() == fs.signum() {
+            (a, fa) = (s, fs);
+        } else {
+            (b, fb) = (s, fs);
+        }
+        if fa.abs() < fb.abs() {
+            (a, b, fa, fb) = (b, a, fb, fa);
+        }
+    }
+    Err(not_converged())
+}
+
+/// Newton's method, with the symbolic derivative when there is one and it agrees
+/// with the numeric slope
+fn newton(equation: &Binding, guess: f64) -> Result<f64, String> {
+    let derivative = equation
+        .expr
+        .derivative(equation.var)
+        .ok()
+        .map(|expr| Binding {
+            expr,
+            var: equation.var,
+            context: equation.context,
+        });
+    let slope = |x: f64| -> Result<f64, String> {
+        let h = 1e-7 * x.abs().max(1.0);
+        let numeric = (equation.eval(x + h)? - equation.eval(x - h)?) / (2.0 * h);
+        Ok(match derivative.as_ref().and_then(|d| d.eval(x).ok()) {
+            Some(exact) if (exact - numeric).abs() <= 1e-3 * numeric.abs().max(1.0) => exact,
+            _ => numeric,
+        })
+    };
+    let mut x = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let y = equation.eval(x)?;
+        if y == 0.0 {
+            return Ok(x);
+        }
+        let slope = slope(x)?;
+        if slope == 0.0 || !slope.is_finite() {
+            break;
+        }
+        let next = x - y / slope;
+        if (next - x).abs() <= TOLERANCE * next.abs().max(1.0) {
+            return Ok(next);
+        }
+        x = next;
+    }
+    Err(not_converged())
+}
+
+/// Looks for a sign change close to 0, first with small steps and after that with big ones
+///
+/// A bracket that fails, like one where the function is not defined everywhere, is skipped
+fn scan(equation: &Binding) -> Option<f64> {
+    let mut xs: Vec<f64> = (-100..=100).map(|i| i as f64 / 10.0).collect();
+    let mut x = 10.0;
+    while x < 1e6 {
+        x *= 1.5;
+        xs.push(x);
+        xs.push(-x);
+    }
+    xs.sort_by(f64::total_cmp);
+    let ys: Vec<f64> = xs
+        .iter()
+        .map(|x| equation.eval(*x).unwrap_or(f64::NAN))
+        .collect();
+
+    let mut brackets: Vec<usize> = (0..xs.len() - 1)
+        .filter(|i| ys[*i].is_finite() && ys[i + 1].is_finite())
+        .filter(|i| ys[*i] == 0.0 || ys[*i].signum() != ys[i + 1].signum())
+        .collect();
+    brackets.sort_by(|a, b| xs[*a].abs().total_cmp(&xs[*b].abs()));
+    brackets.into_iter().find_map(|i| {
+        let root = brent(|x| equation.eval(x), xs[i], xs[i + 1]).ok()?;
+        // a sign change can also be a pole like in `1 / x`
+        let y = equation.eval(root).ok()?;
+        let close = y.abs() <= 1e-6 * (ys[i].abs() + ys[i + 1].abs()).max(1e-6);
+        close.then_some(root)
+    })
+}
+
+/// `solve(a = b, x)`, `solve(a = b, x, guess)` or `solve(a = b, x, lo, hi)`
+///
+/// Polynomials without a guess or range give a list of all the real roots
+pub fn solve(args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    let mut args = args.into_iter();
+    let (Some(expr), Some(Token::Var(var))) = (args.next(), args.next()) else {
+        return Err("solve needs an equation and a variable: solve(x * x = 2, x)".into());
+    };
+    let expr = match expr {
+        Token::Equation(a, b) => Token::Sub(a, b),
+        expr => expr,
+    };
+    let numbers = args
+        .map(|token| match token {
+            Token::I(_) | Token::F(_) => Ok(token.get_f64()),
+            _ => Err(format!(
+                "solve needs numbers for the guess or range, got: {token}"
+            )),
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    let equation = Binding {
+        expr,
+        var: &var,
+        context,
+    };
+
+    let root = match numbers.as_slice() {
+        [] => {
+            if let Some(coefficients) = polynomial::coefficients(&equation.expr, &var, context)? {
+                let roots = polynomial::real_roots(&coefficients)?;
+                return Ok(Token::List(roots.into_iter().map(Token::F).collect()));
+            }
+            match scan(&equation) {
+                Some(root) => root,
+                None => newton(&equation, 1.0)?,
+            }
+        }
+        [guess] => newton(&equation, *guess)?,
+        [lo, hi] => brent(|x| equation.eval(x), *lo, *hi)?,
+        _ => {
+            return Err("solve needs at most a guess or a range: solve(x * x = 2, x, 0, 2)".into())
+        }
+    };
+    Ok(Token::F(root))
+}
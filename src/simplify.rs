@@ -0,0 +1,398 @@
+use std::fmt::Display;
+
+use crate::{context::Context, token::Token};
+
+/// A rewrite done by the simplifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    FoldConstants,
+    Ungroup,
+    AddZero,
+    SubZero,
+    ZeroSub,
+    AddNeg,
+    SubNeg,
+    MulZero,
+    MulOne,
+    NumberFirst,
+    ZeroDiv,
+    DivOne,
+    PowOne,
+    PowZero,
+    DoubleNeg,
+    LikeTerms,
+    CollectPowers,
+    Pythagorean,
+    SinNeg,
+    CosNeg,
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Rule::FoldConstants => "1 + 2 = 3",
+            Rule::Ungroup => "(x) = x",
+            Rule::AddZero => "x + 0 = x",
+            Rule::SubZero => "x - 0 = x",
+            Rule::ZeroSub => "0 - x = -x",
+            Rule::AddNeg => "x + -y = x - y",
+            Rule::SubNeg => "x - -y = x + y",
+            Rule::MulZero => "x * 0 = 0",
+            Rule::MulOne => "x * 1 = x",
+            Rule::NumberFirst => "x * 2 = 2 * x",
+            Rule::ZeroDiv => "0 / x = 0",
+            Rule::DivOne => "x / 1 = x",
+            Rule::PowOne => "pow(x, 1) = x",
+            Rule::PowZero => "pow(x, 0) = 1",
+            Rule::DoubleNeg => "--x = x",
+            Rule::LikeTerms => "2 * x + 3 * x = 5 * x",
+            Rule::CollectPowers => "x * x = pow(x, 2)",
+            Rule::Pythagorean => "pow(sin(x), 2) + pow(cos(x), 2) = 1",
+            Rule::SinNeg => "sin(-x) = -sin(x)",
+            Rule::CosNeg => "cos(-x) = cos(x)",
+        })
+    }
+}
+
+impl Token {
+    /// A simpler tree that calculates the same, variables are kept
+    pub fn simplify(&self) -> Token {
+        self.simplify_with_rules().0
+    }
+
+    /// Like `simplify`, and the rules that were used in order
+    pub fn simplify_with_rules(&self) -> (Token, Vec<Rule>) {
+        let mut rules = Vec::new();
+        let token = simplify(self.clone(), &mut rules);
+        (token, rules)
+    }
+}
+
+fn simplify(mut token: Token, rules: &mut Vec<Rule>) -> Token {
+    for child in token.children_mut() {
+        *child = simplify(std::mem::replace(child, Token::Inf), rules);
+    }
+    match rewrite(&token) {
+        Some((token, rule)) => {
+            rules.push(rule);
+            simplify(token, rules)
+        }
+        None => token,
+    }
+}
+
+fn zero(token: &Token) -> bool {
+    token.is_num() && token.is_zero()
+}
+
+fn one(token: &Token) -> bool {
+    token.is_num() && token.get_f64() == 1.0
+}
+
+fn neg(token: Token) -> Token {
+    Token::Neg(Box::new(token))
+}
+
+/// One rewrite of the token, the children are already simplified
+fn rewrite(token: &Token) -> Option<(Token, Rule)> {
+    if let Some(folded) = fold(token) {
+        return Some((folded, Rule::FoldConstants));
+    }
+    let rewritten = match token {
+        Token::Group(tokens) if tokens.len() == 1 => (tokens[0].clone(), Rule::Ungroup),
+        Token::Add(a, b) if zero(a) => (b.as_ref().clone(), Rule::AddZero),
+        Token::Add(a, b) if zero(b) => (a.as_ref().clone(), Rule::AddZero),
+        Token::Sub(a, b) if zero(b) => (a.as_ref().clone(), Rule::SubZero),
+        Token::Sub(a, b) if zero(a) => (neg(b.as_ref().clone()), Rule::ZeroSub),
+        Token::Add(a, b) if matches!(**b, Token::Neg(_)) => {
+            let Token::Neg(b) = b.as_ref() else {
+                unreachable!()
+            };
+            (Token::Sub(a.clone(), b.clone()), Rule::AddNeg)
+        }
+        Token::Sub(a, b) if matches!(**b, Token::Neg(_)) => {
+            let Token::Neg(b) = b.as_ref() else {
+                unreachable!()
+            };
+            (Token::Add(a.clone(), b.clone()), Rule::SubNeg)
+        }
+        Token::Add(..) | Token::Sub(..) => return sum(token),
+        Token::Mul(..) => return product(token),
+        Token::Div(a, _) if zero(a) => (Token::I(0), Rule::ZeroDiv),
+        Token::Div(a, b) if one(b) => (a.as_ref().clone(), Rule::DivOne),
+        Token::Pow(a, b) if one(b) => (a.as_ref().clone(), Rule::PowOne),
+        // `pow` calculates a float, `pow(x, 0) / 3` is not the integer division `1 / 3`
+        Token::Pow(_, b) if zero(b) => (Token::F(1.0), Rule::PowZero),
+        Token::Neg(a) => match a.as_ref() {
+            Token::Neg(a) => (a.as_ref().clone(), Rule::DoubleNeg),
+            _ => return None,
+        },
+        Token::Sin(a) => match a.as_ref() {
+            Token::Neg(a) => (neg(Token::sin(a.as_ref().clone())), Rule::SinNeg),
+            _ => return None,
+        },
+        Token::Cos(a) => match a.as_ref() {
+            Token::Neg(a) => (Token::cos(a.as_ref().clone()), Rule::CosNeg),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(rewritten)
+}
+
+/// Calculates the token with the executor rules when all the children are numbers,
+/// only when the result is exact so `1 / 3` and `sqrt(2)` are kept
+fn fold(token: &Token) -> Option<Token> {
+    let foldable = match token {
+        Token::Add(..)
+        | Token::Sub(..)
+        | Token::Mul(..)
+        | Token::Pow(..)
+        | Token::Neg(_)
+        | Token::Sin(_)
+        | Token::Cos(_)
+        | Token::Sqrt(_) => true,
+        Token::Div(a, b) => match (a.as_ref(), b.as_ref()) {
+            (Token::I(a), Token::I(b)) => *b != 0 && a % b == 0,
+            _ => !b.is_zero(),
+        },
+        // random functions are never folded
+        Token::Call(function, args) => function.is_deterministic() && !function.is_symbolic(args),
+        _ => false,
+    };
+    let children = token.children();
+    if !foldable || children.is_empty() || !children.iter().all(|t| t.is_num()) {
+        return None;
+    }
+    let float_input = children.iter().any(|t| matches!(t, Token::F(_)));
+    let mut folded = token.clone();
+    folded.calculate(&Context::default()).ok()?;
+    // a float stays a float, `1 / pow(2, 2)` is not the integer division `1 / 4`
+    match folded {
+        Token::I(_) => Some(folded),
+        Token::F(num) if float_input || num.fract() == 0.0 => Some(folded),
+        _ => None,
+    }
+}
+
+/// Splits `2 * x * y` in the coefficient `2` and `x * y`
+fn term(token: &Token) -> (Token, Token) {
+    let mut coefficient = Token::I(1);
+    let mut all = Vec::new();
+    factors(token, &mut coefficient, &mut all);
+    let rest = all
+        .into_iter()
+        .map(|(base, exponent)| {
+            if one(&exponent) {
+                base
+            } else {
+                Token::pow(base, exponent)
+            }
+        })
+        .reduce(Token::m);
+    (coefficient, rest.unwrap_or(Token::I(1)))
+}
+
+fn negate(num: Token) -> Token {
+    match num {
+        Token::I(num) => Token::I(num.wrapping_neg()),
+        Token::F(num) => Token::F(-num),
+        num => neg(num),
+    }
+}
+
+/// `coefficient * rest` without the ones
+fn with_coefficient(c: Token, rest: Token) -> Token {
+    if zero(&c) {
+        Token::I(0)
+    } else if one(&c) {
+        rest
+    } else if c.is_num() && c.get_f64() == -1.0 {
+        neg(rest)
+    } else {
+        Token::m(c, rest)
+    }
+}
+
+/// The terms of a chain of `+` and `-`, numbers have `None` as the rest
+fn terms(token: &Token, negative: bool, terms: &mut Vec<(Token, Option<Token>)>) {
+    match token {
+        Token::Add(a, b) => {
+            self::terms(a, negative, terms);
+            self::terms(b, negative, terms);
+        }
+        Token::Sub(a, b) => {
+            self::terms(a, negative, terms);
+            self::terms(b, !negative, terms);
+        }
+        Token::Neg(a) => self::terms(a, !negative, terms),
+        num if num.is_num() => {
+            let num = if negative {
+                negate(num.clone())
+            } else {
+                num.clone()
+            };
+            terms.push((num, None))
+        }
+        token => {
+            let (c, rest) = term(token);
+            let c = if negative { negate(c) } else { c };
+            terms.push((c, Some(rest)))
+        }
+    }
+}
+
+fn add(a: Token, b: Token) -> Token {
+    fold(&Token::a(a.clone(), b.clone())).unwrap_or(Token::a(a, b))
+}
+
+fn mul(a: Token, b: Token) -> Token {
+    fold(&Token::m(a.clone(), b.clone())).unwrap_or(Token::m(a, b))
+}
+
+/// Combines the like terms of a sum, `2 * x + 1 + 3 * x` is `5 * x + 1`
+fn sum(token: &Token) -> Option<(Token, Rule)> {
+    let mut all = Vec::new();
+    terms(token, false, &mut all);
+    let len = all.len();
+    let mut rule = Rule::LikeTerms;
+
+    // pow(sin(x), 2) + pow(cos(x), 2) is 1
+    'pythagorean: for i in 0..all.len() {
+        for j in 0..all.len() {
+            if let (Some(Token::Pow(s, e1)), Some(Token::Pow(c, e2))) = (&all[i].1, &all[j].1) {
+                let same = match (s.as_ref(), c.as_ref()) {
+                    (Token::Sin(a), Token::Cos(b)) => a == b,
+                    _ => false,
+                };
+                if same && **e1 == Token::I(2) && **e2 == Token::I(2) && all[i].0 == all[j].0 {
+                    let c = all[i].0.clone();
+                    all[i] = (c, None);
+                    all.remove(j);
+                    rule = Rule::Pythagorean;
+                    break 'pythagorean;
+                }
+            }
+        }
+    }
+
+    let mut combined: Vec<(Token, Option<Token>)> = Vec::new();
+    for (c, rest) in all {
+        match combined.iter_mut().find(|(_, other)| *other == rest) {
+            Some((sum, _)) => *sum = add(sum.clone(), c),
+            None => combined.push((c, rest)),
+        }
+    }
+    let numbers = combined.iter().position(|(_, rest)| rest.is_none());
+    if let Some(i) = numbers {
+        // the number is written last
+        let number = combined.remove(i);
+        combined.push(number);
+    }
+    combined.retain(|(c, _)| !zero(c));
+    if combined.len() >= len {
+        return None;
+    }
+    if numbers.is_some() && combined.iter().all(|(_, rest)| rest.is_none()) {
+        rule = Rule::FoldConstants;
+    }
+
+    let mut result: Option<Token> = None;
+    for (c, rest) in combined {
+        let negative = c.is_num() && c.get_f64() < 0.0;
+        let c = if result.is_some() && negative {
+            negate(c)
+        } else {
+            c
+        };
+        let term = match rest {
+            Some(rest) => with_coefficient(c, rest),
+            None => c,
+        };
+        result = Some(match result {
+            None => term,
+            Some(result) if negative => Token::s(result, term),
+            Some(result) => Token::a(result, term),
+        });
+    }
+    Some((result.unwrap_or(Token::I(0)), rule))
+}
+
+/// The factors of a chain of `*`, the numbers are multiplied in the coefficient
+fn factors(token: &Token, coefficient: &mut Token, factors: &mut Vec<(Token, Token)>) -> usize {
+    match token {
+        Token::Mul(a, b) => {
+            self::factors(a, coefficient, factors) + self::factors(b, coefficient, factors)
+        }
+        Token::Neg(a) => {
+            *coefficient = negate(coefficient.clone());
+            self::factors(a, coefficient, factors)
+        }
+        num if num.is_num() => {
+            *coefficient = mul(coefficient.clone(), num.clone());
+            1
+        }
+        Token::Pow(base, exponent) => {
+            factors.push((base.as_ref().clone(), exponent.as_ref().clone()));
+            0
+        }
+        token => {
+            factors.push((token.clone(), Token::I(1)));
+            0
+        }
+    }
+}
+
+/// Collects the numbers in front and the powers, `x * 2 * x` is `2 * pow(x, 2)`
+fn product(token: &Token) -> Option<(Token, Rule)> {
+    let mut coefficient = Token::I(1);
+    let mut all = Vec::new();
+    let numbers = factors(token, &mut coefficient, &mut all);
+    if zero(&coefficient) {
+        return Some((Token::I(0), Rule::MulZero));
+    }
+
+    let mut combined: Vec<(Token, Token)> = Vec::new();
+    for (base, exponent) in all.iter().cloned() {
+        match combined.iter_mut().find(|(other, _)| *other == base) {
+            Some((_, sum)) => *sum = add(sum.clone(), exponent),
+            None => combined.push((base, exponent)),
+        }
+    }
+    let rule = if combined.len() < all.len() {
+        Rule::CollectPowers
+    } else if numbers > 1 {
+        Rule::FoldConstants
+    } else if one(&coefficient) && numbers == 1 {
+        Rule::MulOne
+    } else {
+        Rule::NumberFirst
+    };
+
+    // the coefficient is written first, `-1` is written as a negation
+    let negative = coefficient.is_num() && coefficient.get_f64() == -1.0;
+    let mut result = (!one(&coefficient) && !negative).then_some(coefficient.clone());
+    for (base, exponent) in combined {
+        let factor = if one(&exponent) {
+            base
+        } else if zero(&exponent) {
+            continue;
+        } else {
+            Token::pow(base, exponent)
+        };
+        result = Some(match result {
+            None => factor,
+            Some(result) => Token::m(result, factor),
+        });
+    }
+    let result = match result {
+        Some(result) if negative => neg(result),
+        Some(result) => result,
+        None if negative => coefficient,
+        None => Token::I(1),
+    };
+    if result.same(token) {
+        return None;
+    }
+    Some((result, rule))
+}
@@ -0,0 +1,190 @@
+use std::f64::consts::PI;
+
+pub fn mean(data: &[f64]) -> f64 {
+    data.iter().sum::<f64>() / data.len() as f64
+}
+
+/// Sample variance when `sample` is true, else population variance
+pub fn variance(data: &[f64], sample: bool) -> Result<f64, String> {
+    let n = data.len();
+    if n < 1 + sample as usize {
+        return Err(format!(
+            "Variance needs at least {} numbers",
+            1 + sample as usize
+        ));
+    }
+    let mean = mean(data);
+    let sum: f64 = data.iter().map(|x| (x - mean).powi(2)).sum();
+    Ok(sum / (n - sample as usize) as f64)
+}
+
+pub fn stdev(data: &[f64], sample: bool) -> Result<f64, String> {
+    variance(data, sample).map(f64::sqrt)
+}
+
+/// The most common number, the smallest one when more are as common
+pub fn mode(data: &[f64]) -> f64 {
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let (mut best, mut best_count) = (sorted[0], 0);
+    for run in sorted.chunk_by(|a, b| a == b) {
+        if run.len() > best_count {
+            best = run[0];
+            best_count = run.len();
+        }
+    }
+    best
+}
+
+/// Linear interpolation between the closest ranks, `q` is between 0 and 1
+pub fn quantile(data: &[f64], q: f64) -> Result<f64, String> {
+    if !(0.0..=1.0).contains(&q) {
+        return Err(format!("Quantile needs to be between 0 and 1, got: {q}"));
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let rank = q * (sorted.len() - 1) as f64;
+    let low = rank.floor() as usize;
+    let high = rank.ceil() as usize;
+    Ok(sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64))
+}
+
+fn same_len(xs: &[f64], ys: &[f64]) -> Result<(), String> {
+    if xs.len() != ys.len() {
+        Err(format!(
+            "Lists need the same length, got: {} and {}",
+            xs.len(),
+            ys.len()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Sample covariance
+pub fn covariance(xs: &[f64], ys: &[f64]) -> Result<f64, String> {
+    same_len(xs, ys)?;
+    if xs.len() < 2 {
+        return Err("Covariance needs at least 2 numbers".into());
+    }
+    let (mx, my) = (mean(xs), mean(ys));
+    let sum: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum();
+    Ok(sum / (xs.len() - 1) as f64)
+}
+
+/// Pearson correlation coefficient
+pub fn correlation(xs: &[f64], ys: &[f64]) -> Result<f64, String> {
+    let cov = covariance(xs, ys)?;
+    Ok(cov / (stdev(xs, true)? * stdev(ys, true)?))
+}
+
+/// Least squares line, returns the slope and the intercept
+pub fn linreg(xs: &[f64], ys: &[f64]) -> Result<(f64, f64), String> {
+    let slope = covariance(xs, ys)? / variance(xs, true)?;
+    Ok((slope, mean(ys) - slope * mean(xs)))
+}
+
+pub fn normal_pdf(x: f64, mu: f64, sigma: f64) -> f64 {
+    let z = (x - mu) / sigma;
+    (-z * z / 2.0).exp() / (sigma * (2.0 * PI).sqrt())
+}
+
+/// Probability of a standard normal value to be bigger than `x`, for `x >= 0`
+fn upper_tail(x: f64) -> f64 {
+    let pdf = normal_pdf(x, 0.0, 1.0);
+    if x < 3.0 {
+        // Marsaglia's series, is accurate for small numbers
+        let (mut sum, mut term) = (x, x);
+        let mut i = 1.0;
+        loop {
+            term *= x * x / (2.0 * i + 1.0);
+            i += 1.0;
+            let next = sum + term;
+            if next == sum {
+                break;
+            }
+            sum = next;
+        }
+        0.5 - sum * pdf
+    } else {
+        // continued fraction, is accurate for the far tail
+        let mut fraction = x;
+        for i in (1..=60).rev() {
+            fraction = x + i as f64 / fraction;
+        }
+        pdf / fraction
+    }
+}
+
+pub fn normal_cdf(x: f64, mu: f64, sigma: f64) -> f64 {
+    let z = (x - mu) / sigma;
+    if z >= 0.0 {
+        1.0 - upper_tail(z)
+    } else {
+        upper_tail(-z)
+    }
+}
+
+/// Inverse of the normal cdf, Acklam's approximation with a Halley step
+pub fn normal_inv(p: f64, mu: f64, sigma: f64) -> Result<f64, String> {
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("Probability needs to be between 0 and 1, got: {p}"));
+    }
+    if p == 0.0 {
+        return Ok(f64::NEG_INFINITY);
+    }
+    if p == 1.0 {
+        return Ok(f64::INFINITY);
+    }
+
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    let mut x = if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    };
+
+    let e = normal_cdf(x, 0.0, 1.0) - p;
+    let u = e * (2.0 * PI).sqrt() * (x * x / 2.0).exp();
+    x -= u / (1.0 + x * u / 2.0);
+    Ok(mu + sigma * x)
+}
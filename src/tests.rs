@@ -1,4 +1,8 @@
-use crate::{kalc_f64, kalc_i64};
+use crate::{
+    compile, derivative, kalc, kalc_bool, kalc_f64, kalc_i64, kalc_trace, kalc_with, parse,
+    simplify, BinaryOp, Column, Context, Date, Duration, ExprKind, Formatter, Interval, Matrix,
+    Notation, Polynomial, Radix, RateTable, Rule, Span, Token, Uncertain, Value, Width,
+};
 
 #[test]
 fn add() {
@@ -32,5 +36,1194 @@ fn order() {
 #[test]
 fn sin() {
     assert_eq!(kalc_f64("sin(1)").unwrap(), 0.8414709848078965);
-    assert_eq!(kalc_f64("sin(1) - cos(1)").unwrap(), 0.30116867893975674)
+    assert_eq!(kalc_f64("sin(1) - cos(1)").unwrap(), 0.30116867893975674);
+    assert_eq!(kalc_f64("cos(pi)").unwrap(), -1.0);
+    assert_eq!(kalc_f64("exp(1)").unwrap(), std::f64::consts::E)
+}
+
+#[test]
+fn bitwise() {
+    assert_eq!(kalc_i64("0xFF & (1 << 4)").unwrap(), 16);
+    assert_eq!(kalc_i64("0b1010 | 0o5").unwrap(), 15);
+    assert_eq!(kalc_i64("6 ^ 3").unwrap(), 5);
+    assert_eq!(kalc_i64("~0").unwrap(), -1);
+    assert_eq!(kalc_i64("256 >> 4").unwrap(), 16);
+    assert!(kalc_i64("1.5 & 1").is_err());
+}
+
+#[test]
+fn programmer() {
+    let u8 = || Context::programmer(Width::U8);
+    assert_eq!(kalc_with("~5", u8()).unwrap().get_i64(), 250);
+    assert_eq!(kalc_with("200 + 100", u8()).unwrap().get_i64(), 44);
+    assert_eq!(kalc_with("1 << 8", u8()).unwrap().get_i64(), 0);
+    assert_eq!(
+        kalc_with("127 + 1", Context::programmer(Width::I8))
+            .unwrap()
+            .get_i64(),
+        -128
+    );
+    let res = kalc_with("0xFFFFFFFFFFFFFFFF >> 60", Context::programmer(Width::U64)).unwrap();
+    assert_eq!(res.get_i64(), 15);
+}
+
+#[test]
+fn radix() {
+    let res = kalc_with("~0x0F", Context::programmer(Width::U8)).unwrap();
+    assert_eq!(res.get_radix(Radix::Hex), "0xF0");
+    assert_eq!(res.get_radix(Radix::Bin), "0b11110000");
+    assert_eq!(res.get_radix(Radix::Oct), "0o360");
+    assert_eq!(res.get_radix(Radix::Dec), "240");
+    let res = kalc_with("0xFF", Context::programmer(Width::I8)).unwrap();
+    assert_eq!(res.get_radix(Radix::Dec), "-1");
+    assert_eq!(res.get_radix(Radix::Hex), "0xFF");
+    assert_eq!(kalc("0 - 255").unwrap().get_radix(Radix::Hex), "-0xFF");
+
+    // the base after `to` or `in`
+    assert_eq!(kalc_string("255 to hex"), "0xFF");
+    assert_eq!(kalc_string("255 to bin"), "0b11111111");
+    assert_eq!(kalc_string("255 in oct"), "0o377");
+    assert_eq!(kalc_string("0xFF + 1 to dec"), "256");
+    assert_eq!(kalc_string("1295 to base(36)"), "36#ZZ");
+    assert_eq!(kalc_string("-7 to base(3)"), "-3#21");
+    assert_eq!(kalc_string("8 to base(2)"), "0b1000");
+    assert_eq!(kalc_string("0 to hex"), "0x0");
+    assert_eq!(kalc_string("0 to bin"), "0b0");
+    assert_eq!(kalc_string("0 to oct"), "0o0");
+    assert_eq!(kalc_string("0 to base(2)"), "0b0");
+    assert_eq!(kalc_string("[10, 255] to hex"), "[0xA, 0xFF]");
+    assert_eq!(kalc_i64("255 to hex").unwrap(), 255);
+    let res = kalc_with("-1 to hex", Context::programmer(Width::U8)).unwrap();
+    assert_eq!(res.get().to_string(), "0xFF");
+    assert!(kalc("1.5 to hex").is_err());
+    assert!(kalc("10 to base(37)").is_err());
+}
+
+#[test]
+fn formatter() {
+    let format = |formula: &str, formatter: Formatter| kalc(formula).unwrap().format(&formatter);
+    let notation = |notation| Formatter::default().with_notation(notation);
+    assert_eq!(
+        format("1.0 / 3", Formatter::default()),
+        "0.3333333333333333"
+    );
+    assert_eq!(format("1e21", Formatter::default()), "1e21");
+    assert_eq!(format("2.5e-8", Formatter::default()), "2.5e-8");
+    assert_eq!(
+        format("1.0 / 3", notation(Notation::General(12))),
+        "0.333333333333"
+    );
+    assert_eq!(format("0.1 + 0.2", notation(Notation::General(12))), "0.3");
+    assert_eq!(
+        format("1e15 * 1.0", notation(Notation::General(12))),
+        "1e15"
+    );
+    assert_eq!(
+        format("pow(2, 62)", notation(Notation::General(12))),
+        "4.61168601843e18"
+    );
+    assert_eq!(format("pi", notation(Notation::Fixed(2))), "3.14");
+    assert_eq!(format("2.5", notation(Notation::Significant(3))), "2.50");
+    assert_eq!(
+        format("123456", notation(Notation::Significant(2))),
+        "120000"
+    );
+    assert_eq!(
+        format("0.000123456", notation(Notation::Significant(2))),
+        "0.00012"
+    );
+    assert_eq!(
+        format("-123456", notation(Notation::Scientific(3))),
+        "-1.23e5"
+    );
+    assert_eq!(
+        format("12345", notation(Notation::Engineering(3))),
+        "12.3e3"
+    );
+    assert_eq!(
+        format("0.00012", notation(Notation::Engineering(2))),
+        "120e-6"
+    );
+    assert_eq!(format("10000", notation(Notation::Engineering(1))), "10e3");
+
+    // groups and the marks of the locale
+    let grouped = Formatter::default().with_grouping();
+    assert_eq!(format("1234567", grouped), "1,234,567");
+    assert_eq!(format("-1234.5", grouped), "-1,234.5");
+    let german = grouped.with_locale("de_DE").unwrap();
+    assert_eq!(format("1234567.891", german), "1.234.567,891");
+    assert_eq!(format("[1.5, 2]", german), "[1,5; 2]");
+    let swiss = grouped
+        .with_notation(Notation::Fixed(2))
+        .with_locale("de-CH")
+        .unwrap();
+    assert_eq!(format("1234.5", swiss), "1'234.50");
+    assert_eq!(format("255 to hex", german), "0xFF");
+    assert!(Formatter::default().with_locale("xx").is_err());
+}
+
+#[test]
+fn compare() {
+    assert!(kalc_bool("2 < 3").unwrap());
+    assert!(kalc_bool("2.5 >= 2").unwrap());
+    assert!(kalc_bool("1 + 1 == 2").unwrap());
+    assert!(!kalc_bool("4 != (2 * 2)").unwrap());
+    assert!(kalc_bool("1 < 2 and not (3 <= 2)").unwrap());
+    assert!(kalc_bool("false || !false && true").unwrap());
+    assert!(kalc_bool("1 < true").is_err());
+    assert!(kalc_bool("1 and true").is_err());
+    // `same` is used to find out if a step changed something, `NaN` does not change
+    let nan = Token::a(Token::F(f64::NAN), Token::I(1));
+    assert!(nan.same(&nan.clone()));
+    assert!(!nan.same(&Token::a(Token::F(f64::NAN), Token::I(2))));
+    assert!(!Token::I(1).same(&Token::F(1.0)));
+}
+
+#[test]
+fn conditional() {
+    assert_eq!(kalc_i64("if(2 > 1, 10, 20)").unwrap(), 10);
+    assert_eq!(kalc_f64("150 > 100 ? 20 * 0.9 : 20").unwrap(), 18.0);
+    assert_eq!(kalc_i64("(50 > 100 ? 10 : 20) + 1").unwrap(), 21);
+    // the branch that is not taken is never calculated
+    assert_eq!(kalc_i64("if(true, 1, 1 < false)").unwrap(), 1);
+    assert!(kalc_i64("if(1, 2, 3)").is_err());
+    assert!(kalc_i64("if(true, 2)").is_err());
+}
+
+fn kalc_string(formula: &str) -> String {
+    kalc(formula).unwrap().get().to_string()
+}
+
+#[test]
+fn list() {
+    assert_eq!(kalc_string("[1, 2, 3]"), "[1, 2, 3]");
+    assert_eq!(kalc_string("[1, -2, 3 * 2]"), "[1, -2, 6]");
+    assert_eq!(kalc_string("[1, 2, 3] * 2"), "[2, 4, 6]");
+    assert_eq!(kalc_string("10 - [1, 2]"), "[9, 8]");
+    assert_eq!(kalc_string("[1, 2] + [10, 20]"), "[11, 22]");
+    assert!(kalc("[1, 2] + [1, 2, 3]").is_err());
+    assert!(kalc("[1, 2)").is_err());
+}
+
+#[test]
+fn range() {
+    assert_eq!(kalc_string("1..5"), "[1, 2, 3, 4, 5]");
+    assert_eq!(kalc_string("1..10 step 3"), "[1, 4, 7, 10]");
+    assert_eq!(kalc_string("5..1 step -2"), "[5, 3, 1]");
+    assert_eq!(kalc_string("0..0.3 step 0.1").matches(',').count(), 3);
+    assert!(kalc("1..5 step 0").is_err());
+    // the integers near the limits are counted exactly and do not overflow
+    let big = "-9223372036854775807..9223372036854775807 step 1000000000000000";
+    assert_eq!(kalc_i64(&format!("count({big})")).unwrap(), 18447);
+    assert_eq!(
+        kalc_string("9223372036854775807..0 step -4611686018427387904"),
+        "[9223372036854775807, 4611686018427387903]"
+    );
+}
+
+#[test]
+fn aggregate() {
+    assert_eq!(kalc_i64("sum(1..100)").unwrap(), 5050);
+    assert_eq!(kalc_i64("sum(1, 2, 3)").unwrap(), 6);
+    assert_eq!(kalc_i64("product([1, 2, 3, 4])").unwrap(), 24);
+    assert_eq!(kalc_f64("mean([1, 2, 3, 4])").unwrap(), 2.5);
+    assert_eq!(kalc_f64("median([5, 1, 3])").unwrap(), 3.0);
+    assert_eq!(kalc_f64("median([4, 1, 3, 2])").unwrap(), 2.5);
+    assert_eq!(kalc_i64("min([4, -1, 3])").unwrap(), -1);
+    assert_eq!(kalc_f64("max(4, 1.5, 3)").unwrap(), 4.0);
+    assert_eq!(kalc_i64("count(1..10 step 2)").unwrap(), 5);
+    assert_eq!(kalc_i64("2 sum(1, 2)").unwrap(), 6);
+    assert_eq!(kalc_string("[1, sum(2, 3)]"), "[1, 5]");
+    assert_eq!(kalc_string("[1, 2 sum(2, 3)]"), "[1, 10]");
+    assert_eq!(kalc_string("[1, (2)]"), "[1, 2]");
+    assert_eq!(kalc_i64("sum(1, (2))").unwrap(), 3);
+    assert!(kalc("product(1..30)").is_err());
+    assert!(kalc("mean([])").is_err());
+}
+
+#[test]
+fn lambda() {
+    assert_eq!(kalc_string("map([1, 2, 3], x -> x * 2)"), "[2, 4, 6]");
+    assert_eq!(kalc_string("filter(1..10, n -> n > 7)"), "[8, 9, 10]");
+    assert_eq!(
+        kalc_i64("sum(map(filter(1..6, x -> x > 3), x -> x * x))").unwrap(),
+        77
+    );
+    assert!(kalc("map([1, 2], 2)").is_err());
+    assert_eq!(kalc("x + 1").err().unwrap(), "Unknown variable: x");
+}
+
+#[test]
+fn stats() {
+    let close = |formula: &str, expected: f64| {
+        let result = kalc_f64(formula).unwrap();
+        assert!((result - expected).abs() < 1e-9, "{formula} = {result}");
+    };
+    close("var([2, 4, 4, 4, 5, 5, 7, 9])", 32.0 / 7.0);
+    close("varp([2, 4, 4, 4, 5, 5, 7, 9])", 4.0);
+    close("stdevp([2, 4, 4, 4, 5, 5, 7, 9])", 2.0);
+    assert_eq!(kalc_i64("mode([1, 3, 3, 2, 2, 3])").unwrap(), 3);
+    assert!(kalc("mode([sqrt(0 - 1)])").is_err());
+    close("percentile([1, 2, 3, 4, 5], 25)", 2.0);
+    close("quantile([1, 2, 3, 4], 0.5)", 2.5);
+    close("correlation([1, 2, 3], [2, 4, 6])", 1.0);
+    assert_eq!(kalc_string("linreg([1, 2, 3], [3, 5, 7])"), "[2, 1]");
+    close("zscore(12, 10, 2)", 1.0);
+    close("normpdf(0)", 0.3989422804014327);
+    close("normcdf(1.96)", 0.9750021048517795);
+    close("normcdf(-1.96)", 1.0 - 0.9750021048517795);
+    close("norminv(0.975)", 1.959963984540054);
+    close("norminv(0.5, 10, 2)", 10.0);
+    assert!(kalc("var([1])").is_err());
+    assert!(kalc("normcdf(1, 0, 0)").is_err());
+    assert!(kalc("percentile([1, 2], 150)").is_err());
+}
+
+#[test]
+fn finance() {
+    let close = |formula: &str, expected: f64| {
+        let result = kalc_f64(formula).unwrap();
+        assert!((result - expected).abs() < 1e-6, "{formula} = {result}");
+    };
+    // the expected numbers are from a spreadsheet
+    close("pmt(0.05 / 12, 360, 200000)", -1073.6432460242797);
+    close("pmt(0.06 / 12, 60, 20000, 0, 1)", -384.7323687448412);
+    close("pv(0.08 / 12, 240, 500)", -59777.145851187815);
+    close("fv(0.06 / 12, 10, -200, -500, 1)", 2581.4033740601185);
+    close("nper(0.01, -100, -1000, 10000)", 60.08212285376166);
+    close("rate(48, -200, 8000)", 0.007701472488246008);
+    close("npv(0.1, -10000, 3000, 4200, 6800)", 1188.4434123352207);
+    close(
+        "irr([-70000, 12000, 15000, 18000, 21000, 26000])",
+        0.08663094803653162,
+    );
+    close("pmt(0, 10, 1000)", -100.0);
+    close("simple_interest(1000, 0.05, 3)", 150.0);
+    close("compound_interest(1000, 0.05, 2, 12)", 104.94133555744327);
+    assert_eq!(kalc_string("round_to(1.23, 0.05)"), "1.25");
+    assert_eq!(kalc_string("round_to(1.17, 0.05)"), "1.15");
+    assert!(kalc("irr([100, 200])").is_err());
+    assert!(kalc("pmt(0.1, 10)").is_err());
+}
+
+#[test]
+fn number_theory() {
+    assert_eq!(kalc_i64("gcd(12, 18)").unwrap(), 6);
+    assert_eq!(kalc_i64("gcd([12, -18, 27])").unwrap(), 3);
+    assert_eq!(kalc_i64("lcm(4, 6, 10)").unwrap(), 60);
+    assert!(kalc_bool("isprime(97)").unwrap());
+    assert!(!kalc_bool("isprime(1)").unwrap());
+    assert!(!kalc_bool("isprime(3215031751)").unwrap());
+    assert!(kalc_bool("isprime(9223372036854775783)").unwrap());
+    assert_eq!(kalc_i64("nextprime(100)").unwrap(), 101);
+    assert_eq!(kalc_string("factor(360)"), "[2, 2, 2, 3, 3, 5]");
+    assert_eq!(kalc_string("factor(-15)"), "[-1, 3, 5]");
+    assert_eq!(
+        kalc_string("factor(9223372036854775807)"),
+        "[7, 7, 73, 127, 337, 92737, 649657]"
+    );
+    assert_eq!(kalc_i64("modpow(4, 13, 497)").unwrap(), 445);
+    assert_eq!(kalc_i64("modinv(3, 11)").unwrap(), 4);
+    assert_eq!(kalc_i64("totient(36)").unwrap(), 12);
+    assert_eq!(kalc_string("divisors(12)"), "[1, 2, 3, 4, 6, 12]");
+    assert!(kalc("modinv(2, 4)").is_err());
+    assert!(kalc("gcd(1.5, 2)").is_err());
+    assert_eq!(kalc_i64("gcd(0x8000000000000000, 6)").unwrap(), 2);
+    assert!(kalc("gcd(0, 0x8000000000000000)").is_err());
+    assert!(kalc("lcm(0x8000000000000000, 3)").is_err());
+    assert!(kalc("divisors(0x8000000000000000)").is_err());
+}
+
+#[test]
+fn random() {
+    let seeded = |formula: &str| {
+        let context = Context::default().with_seed(42);
+        kalc_with(formula, context).unwrap().get().to_string()
+    };
+    assert_eq!(seeded("rand()"), seeded("rand()"));
+    assert_eq!(
+        seeded("[rand(), randn(), randint(1, 6)]"),
+        seeded("[rand(), randn(), randint(1, 6)]")
+    );
+    let twice = seeded("[rand(), rand()]");
+    let (first, second) = twice.split_once(", ").unwrap();
+    assert_ne!(first.trim_start_matches('['), second.trim_end_matches(']'));
+
+    let context = Context::default().with_seed(7);
+    for _ in 0..100 {
+        let x = context.rng.next_f64();
+        assert!((0.0..1.0).contains(&x));
+        assert!((1..=6).contains(&context.rng.range(1, 6)));
+    }
+    let result = kalc_with("choice([10, 20, 30])", Context::default().with_seed(1)).unwrap();
+    assert!([10, 20, 30].contains(&result.get_i64()));
+    let result = kalc_with("randn(100, 0)", Context::default()).unwrap();
+    assert_eq!(result.get_f64(), 100.0);
+
+    assert!(kalc("randint(6, 1)").is_err());
+    assert!(kalc("choice([])").is_err());
+    let mut lexer = crate::Lexer::default();
+    lexer.parse("1 + sum(1, rand())").unwrap();
+    assert!(!crate::Token::from(&lexer.expr()).is_deterministic());
+    lexer = crate::Lexer::default();
+    lexer.parse("1 + sum(1, 2)").unwrap();
+    assert!(crate::Token::from(&lexer.expr()).is_deterministic());
+}
+
+#[test]
+fn matrix() {
+    let close = |formula: &str, expected: f64| {
+        let result = kalc_f64(formula).unwrap();
+        assert!((result - expected).abs() < 1e-9, "{formula} = {result}");
+    };
+    assert_eq!(kalc_string("[[1, 2], [3, 4]] * [5, 6]"), "[17, 39]");
+    assert_eq!(kalc_string("[5, 6] * [[1, 2], [3, 4]]"), "[23, 34]");
+    assert_eq!(
+        kalc_string("[[1, 2], [3, 4]] * [[5, 6], [7, 8]]"),
+        "[[19, 22], [43, 50]]"
+    );
+    assert_eq!(
+        kalc_string("[[1, 2], [3, 4]] .* [[5, 6], [7, 8]]"),
+        "[[5, 12], [21, 32]]"
+    );
+    assert_eq!(kalc_string("[1, 2] .* [3, 4]"), "[3, 8]");
+    assert_eq!(kalc_string("2 * [[1, 2], [3, 4]] - 1"), "[[1, 3], [5, 7]]");
+    assert_eq!(kalc_string("-[[1, 2]]"), "[[-1, -2]]");
+    close("det([[1, 2], [3, 4]])", -2.0);
+    close("det([[2, 0, 1], [1, 3, 2], [1, 1, 2]])", 6.0);
+    close("det([[1, 2], [2, 4]])", 0.0);
+    // the small entries are not singular
+    assert_eq!(kalc_f64("det([[1e-13]])").unwrap(), 1e-13);
+    assert_eq!(kalc_string("inv([[1e-13]])"), "[[10000000000000]]");
+    close("det([[1, 2, 3], [4, 5, 6], [7, 8, 9]])", 0.0);
+    assert_eq!(
+        kalc_string("inv([[2, 0], [0, 4]])"),
+        "[[0.5, 0], [0, 0.25]]"
+    );
+    assert_eq!(
+        kalc_string("transpose([[1, 2, 3], [4, 5, 6]])"),
+        "[[1, 4], [2, 5], [3, 6]]"
+    );
+    assert_eq!(kalc_string("transpose([1, 2])"), "[[1], [2]]");
+    close("dot([1, 2, 3], [4, 5, 6])", 32.0);
+    assert_eq!(kalc_string("cross([1, 0, 0], [0, 1, 0])"), "[0, 0, 1]");
+    close("norm([3, 4])", 5.0);
+    close("norm([[1, 2], [2, 4]])", 5.0);
+    assert_eq!(kalc_string("identity(2)"), "[[1, 0], [0, 1]]");
+    close("sum(solve([[2, 1], [1, 3]], [3, 5]) * [1, 0])", 0.8);
+    close("sum(solve([[2, 1], [1, 3]], [3, 5]) * [0, 1])", 1.4);
+
+    assert!(kalc("[[1, 2], [3, 4]] * [1, 2, 3]").is_err());
+    assert!(kalc("[[1, 2]] .* [[1, 2], [3, 4]]").is_err());
+    assert!(kalc("det([[1, 2, 3], [4, 5, 6]])").is_err());
+    assert!(kalc("inv([[1, 2], [2, 4]])").is_err());
+    assert!(kalc("inv([[1, 2, 3], [4, 5, 6], [7, 8, 9]])").is_err());
+    assert!(kalc("cross([1, 2], [3, 4])").is_err());
+    assert!(kalc("[] * [[1]]").is_err());
+    assert!(Matrix::new(2, 2, vec![1.0, 2.0, 3.0]).is_err());
+    assert!(Matrix::new(0, 0, Vec::new()).is_err());
+    assert_eq!(
+        Matrix::new(1, 2, vec![1.0, 2.0]).unwrap().to_string(),
+        "[[1, 2]]"
+    );
+}
+
+#[test]
+fn diff() {
+    let d = |formula: &str| derivative(formula, "x").unwrap().to_string();
+    assert_eq!(d("sin(pow(x, 2))"), "2 * x * cos(pow(x, 2))");
+    assert_eq!(d("3 * x + 2"), "3");
+    assert_eq!(d("pow(x, 3)"), "3 * pow(x, 2)");
+    assert_eq!(d("cos(x)"), "-sin(x)");
+    assert_eq!(d("x * y"), "y");
+    assert_eq!(d("sqrt(x)"), "1 / (2 * sqrt(x))");
+    assert_eq!(d("ln(x)"), "1 / x");
+    assert_eq!(d("exp(2 * x)"), "2 * exp(2 * x)");
+    assert_eq!(d("1 / x"), "-1 / pow(x, 2)");
+    assert_eq!(d("pow(2, x)"), "pow(2, x) * ln(2)");
+    assert_eq!(d("x - (x + 1)"), "0");
+
+    assert_eq!(
+        kalc_string("diff(sin(pow(x, 2)), x)"),
+        "x -> 2 * x * cos(pow(x, 2))"
+    );
+    assert_eq!(kalc_f64("diff(pow(x, 3), x, 2)").unwrap(), 12.0);
+    assert_eq!(kalc_string("map([1, 2], diff(pow(x, 2), x))"), "[2, 4]");
+    assert_eq!(kalc_string("diff(diff(pow(x, 3), x), x)"), "x -> 6 * x");
+    assert!(derivative("x < 1", "x").is_err());
+    // the constants of the derivative are floats
+    assert_eq!(kalc_f64("diff(x / 2, x, 1)").unwrap(), 0.5);
+    assert_eq!(kalc_f64("diff(ln(x), x, 2)").unwrap(), 0.5);
+    for formula in [
+        "x / 2",
+        "ln(x)",
+        "1 / x",
+        "x / (x + 1)",
+        "sqrt(x) / 3",
+        "cos(x / 2)",
+    ] {
+        for at in ["0.5", "2.0", "5.5"] {
+            let exact = kalc_f64(&format!("diff({formula}, x, {at})")).unwrap();
+            let numeric = kalc_f64(&format!("nderiv({formula}, x, {at})")).unwrap();
+            assert!((exact - numeric).abs() < 1e-6, "{formula} at {at}: {exact}");
+        }
+    }
+
+    let expr = parse("pow(x, 3)").unwrap().derivative("x").unwrap();
+    assert_eq!(expr.to_string(), "3.0 * pow(x, 2)");
+    assert!(parse("x < 1").unwrap().derivative("x").is_err());
+}
+
+#[test]
+fn simplifier() {
+    let s = |formula: &str| simplify(formula).unwrap().to_string();
+    assert_eq!(s("x * 1"), "x");
+    assert_eq!(s("0 + x"), "x");
+    assert_eq!(s("x * 0"), "0");
+    assert_eq!(s("(2 * x) + (3 * x)"), "5 * x");
+    assert_eq!(s("(2 * x * y) - (5 * x * y)"), "-3 * x * y");
+    assert_eq!(s("x - x"), "0");
+    assert_eq!(s("1 + x + 2"), "x + 3");
+    assert_eq!(s("x * x"), "pow(x, 2)");
+    assert_eq!(s("x * 2 * pow(x, 3)"), "2 * pow(x, 4)");
+    assert_eq!(s("pow(sin(x), 2) + pow(cos(x), 2)"), "1");
+    assert_eq!(s("sin(-x) + cos(-x)"), "-sin(x) + cos(x)");
+    assert_eq!(s("1 / 3 + (x * sqrt(4))"), "1 / 3 + (2 * x)");
+    assert_eq!(s("1 / 3 + x * sqrt(4)"), "2 * (1 / 3 + x)");
+    assert_eq!(s("sum(1, 2) + rand()"), "3 + rand()");
+    // floats are not folded in integers that divide differently
+    assert_eq!(s("1 / pow(2, 2)"), "0.25");
+    assert_eq!(s("pow(x, 0) / 3"), kalc_string("1 / 3.0"));
+    // NaN is not equal to itself and the simplifier still stops
+    assert_eq!(s("sqrt(-1.0) * y"), "NaN * y");
+    assert_eq!(kalc_string("diff(sqrt(-1.0) * x * x, x)"), "x -> NaN * x");
+
+    let (token, rules) = crate::Token::Group(vec![crate::Token::m(
+        crate::Token::Var("x".into()),
+        crate::Token::I(1),
+    )])
+    .simplify_with_rules();
+    assert_eq!(token.to_string(), "x");
+    assert_eq!(rules, vec![Rule::MulOne, Rule::Ungroup]);
+}
+
+#[test]
+fn solve() {
+    let close = |formula: &str, expected: f64| {
+        let result = kalc_f64(formula).unwrap();
+        assert!((result - expected).abs() < 1e-9, "{formula} = {result}");
+    };
+    assert_eq!(kalc_string("solve(x * x = 4, x)"), "[-2, 2]");
+    assert_eq!(kalc_string("solve(pow(x, 2) - (2 * x) + 1, x)"), "[1]");
+    assert_eq!(kalc_string("solve(x * x = -1, x)"), "[]");
+    let result = kalc("solve(pow(x, 3) - (6 * pow(x, 2)) + (11 * x) = 6, x)").unwrap();
+    let Token::List(roots) = result.get() else {
+        panic!("{}", result.get())
+    };
+    assert_eq!(roots.len(), 3);
+    for (root, expected) in roots.iter().zip([1.0, 2.0, 3.0]) {
+        assert!((root.get_f64() - expected).abs() < 1e-9, "{root}");
+    }
+    // x^3 - 2x = 5 has one real root
+    let roots = kalc("solve(pow(x, 3) - (2 * x) = 5, x)")
+        .unwrap()
+        .get()
+        .to_string();
+    assert!(roots.starts_with("[2.0945514815423"), "{roots}");
+    close("solve(cos(x) = x, x)", 0.7390851332151607);
+    close("solve(cos(x) = x, x, 1)", 0.7390851332151607);
+    close("solve(sin(x), x, 3, 4)", std::f64::consts::PI);
+    close("solve(x * x = 2, x, 1)", std::f64::consts::SQRT_2);
+    // a guess on an equation with a division
+    close("solve(x / 2 = 1, x, 5)", 2.0);
+    close("solve(cos(x / 2) = 0, x, 3)", std::f64::consts::PI);
+    // the bracket from 0 to 0.1 cannot be calculated, the next one has the root
+    close(
+        "solve(if((x > 0) and (x < 0.1), 1 < true, (x - 0.05) * (x - 0.15)), x)",
+        0.15,
+    );
+    assert_eq!(kalc_string("solve([[2, 0], [0, 4]], [2, 2])"), "[1, 0.5]");
+
+    assert!(kalc("solve(sin(x), x, 1, 2)").is_err());
+    let error = kalc("solve(x * x + 1, x, 0)").err().unwrap();
+    assert!(error.starts_with("solve did not converge"), "{error}");
+    assert!(kalc("1 = 1").err().unwrap().starts_with("Use \"==\""));
+}
+
+#[test]
+fn numeric_calculus() {
+    let close = |formula: &str, expected: f64, tolerance: f64| {
+        let result = kalc_f64(formula).unwrap();
+        assert!(
+            (result - expected).abs() <= tolerance,
+            "{formula} = {result}"
+        );
+    };
+    close("integrate(x * x, x, 0, 1)", 1.0 / 3.0, 1e-12);
+    close("integrate(sin(x), x, 0, 3.141592653589793)", 2.0, 1e-12);
+    close("integrate(1 / x, x, 1, 2)", std::f64::consts::LN_2, 1e-12);
+    close("integrate(sqrt(x), x, 0, 1)", 2.0 / 3.0, 1e-9);
+    close("integrate(x, x, 1, 0)", -0.5, 1e-12);
+    close("integrate(x, x, 2, 2)", 0.0, 0.0);
+    close("nderiv(pow(x, 3), x, 2)", 12.0, 1e-9);
+    close("nderiv(ln(x), x, 0.5)", 2.0, 1e-9);
+    close("nderiv(cos(x), x, 0)", 0.0, 1e-9);
+
+    let result = kalc("integrate(sqrt(x), x, 0, 1, true)").unwrap();
+    let Token::List(list) = result.get() else {
+        panic!("{}", result.get())
+    };
+    let [value, error] = [list[0].get_f64(), list[1].get_f64()];
+    assert!(
+        (value - 2.0 / 3.0).abs() <= error.max(1e-12),
+        "{value} {error}"
+    );
+    let result = kalc("nderiv(sin(x), x, 0, true)").unwrap();
+    let Token::List(list) = result.get() else {
+        panic!("{}", result.get())
+    };
+    assert!((list[0].get_f64() - 1.0).abs() < 1e-9);
+    assert!(list[1].get_f64() < 1e-6);
+
+    assert!(kalc("integrate(x, x, 0)").is_err());
+    assert!(kalc("integrate(x, y, 0, 1)").is_err());
+    assert!(kalc("nderiv(x, x, [1, 2])").is_err());
+}
+
+#[test]
+fn series() {
+    assert_eq!(kalc_i64("sum(i, 1, 10, i * i)").unwrap(), 385);
+    assert_eq!(kalc_i64("prod(i, 1, 5, i)").unwrap(), 120);
+    assert_eq!(kalc_i64("product(i, 1, 5, i)").unwrap(), 120);
+    assert_eq!(kalc_i64("sum(i, 1, 3, sum(j, 1, i, j))").unwrap(), 10);
+    // an empty series
+    assert_eq!(kalc_i64("sum(i, 1, 0, i)").unwrap(), 0);
+    assert_eq!(kalc_i64("prod(i, 1, 0, i)").unwrap(), 1);
+    // the present value of an annuity of 100 for 10 years at 5%
+    let pv = kalc_f64("sum(t, 1, 10, 100 / pow(1.05, t))").unwrap();
+    assert!((pv - 772.1734929184811).abs() < 1e-9, "{pv}");
+    // the list form still works
+    assert_eq!(kalc_i64("sum(1, 2, 3, 4)").unwrap(), 10);
+
+    let error = kalc("sum(i, 1, 1000000, i)").err().unwrap();
+    assert_eq!(error, "sum can have at most 100000 terms, got: 1000000");
+    assert!(kalc("sum(i, 1, 2.5, i)").is_err());
+    assert!(kalc("sum(i, 1, 3, [i])").is_err());
+    let error = kalc("prod(i, 1, 25, i)").err().unwrap();
+    assert_eq!(error, "The product is too big for an integer");
+}
+
+#[test]
+fn polynomials() {
+    assert_eq!(
+        kalc_string("expand(pow(x + 1, 3))"),
+        "x -> pow(x, 3) + (3 * pow(x, 2)) + (3 * x) + 1"
+    );
+    assert_eq!(
+        kalc_string("expand((y + 1) * (y - 1), y)"),
+        "y -> pow(y, 2) - 1"
+    );
+    assert_eq!(kalc_string("coeffs(pow(x + 1, 3))"), "[1, 3, 3, 1]");
+    assert_eq!(kalc_i64("deg(pow(x, 5) - x)").unwrap(), 5);
+    assert_eq!(kalc_i64("deg(0, x)").unwrap(), -1);
+    assert_eq!(
+        kalc_string("polydiv(pow(x, 3) - 1, x - 1)"),
+        "x -> [pow(x, 2) + x + 1, 0]"
+    );
+    assert_eq!(
+        kalc_string("polydiv(pow(x, 3) + 2, x * x + 1, x)"),
+        "x -> [x, -x + 2]"
+    );
+
+    assert_eq!(kalc_string("roots(pow(x, 2) + 1)"), "[[0, -1], [0, 1]]");
+    assert_eq!(kalc_string("roots(x - 2)"), "[[2, 0]]");
+    // the repeated roots
+    assert_eq!(
+        kalc_string("roots(pow(x + 1, 3))"),
+        "[[-1, 0], [-1, 0], [-1, 0]]"
+    );
+    assert_eq!(
+        kalc_string("roots(pow(x - 1, 4))"),
+        "[[1, 0], [1, 0], [1, 0], [1, 0]]"
+    );
+    assert_eq!(
+        kalc_string("roots(pow(x, 2) * (x - 3))"),
+        "[[0, 0], [0, 0], [3, 0]]"
+    );
+    assert_eq!(
+        kalc_string("roots((x - 1) * (x - 1.00001))"),
+        "[[1, 0], [1.00001, 0]]"
+    );
+    let result = kalc("roots(pow(x, 3) - 1)").unwrap();
+    let Token::Matrix(roots) = result.get() else {
+        panic!("{}", result.get())
+    };
+    let expected = [(-0.5, -0.75f64.sqrt()), (-0.5, 0.75f64.sqrt()), (1.0, 0.0)];
+    for (i, (re, im)) in expected.into_iter().enumerate() {
+        assert!((roots.get(i, 0) - re).abs() < 1e-12);
+        assert!((roots.get(i, 1) - im).abs() < 1e-12);
+    }
+
+    assert_eq!(
+        kalc_string("factor(pow(x, 2) - 1)"),
+        "x -> (x + 1) * (x - 1)"
+    );
+    assert_eq!(
+        kalc_string("factor(2 * pow(x, 3) - (2 * x))"),
+        "x -> 2 * x * (x + 1) * (x - 1)"
+    );
+    assert_eq!(
+        kalc_string("factor(pow(x, 2) - (2 * x) + 1)"),
+        "x -> pow(x - 1, 2)"
+    );
+    assert_eq!(
+        kalc_string("factor(6 * pow(x, 2) - x - 2)"),
+        "x -> (2 * x + 1) * (3 * x - 2)"
+    );
+    assert_eq!(
+        kalc_string("factor(pow(x, 2) / 2 - 0.5)"),
+        "x -> 0.5 * (x + 1) * (x - 1)"
+    );
+    // no rational roots
+    assert_eq!(kalc_string("factor(pow(x, 2) + 1)"), "x -> pow(x, 2) + 1");
+    // the number theory factor still works
+    assert_eq!(kalc_string("factor(12)"), "[2, 2, 3]");
+
+    let p = Polynomial::new("x", vec![-1.0, 0.0, 1.0]);
+    assert_eq!(p.to_string(), "pow(x, 2) - 1");
+    assert_eq!(p.degree(), Some(2));
+    let (q, r) = p.div_rem(&Polynomial::new("x", vec![1.0, 1.0])).unwrap();
+    assert_eq!((q.to_string(), r.to_string()), ("x - 1".into(), "0".into()));
+
+    assert!(kalc("expand(sin(x))").is_err());
+    assert!(kalc("polydiv(x, 0, x)").is_err());
+    assert!(kalc("roots(0, x)").is_err());
+}
+
+#[test]
+fn intervals() {
+    let interval = |formula: &str| match kalc(formula).unwrap().get() {
+        Token::Interval(interval) => (interval.lo(), interval.hi()),
+        token => panic!("{formula} = {token}"),
+    };
+    assert_eq!(interval("interval(1, 2) + interval(3, 4)"), (4.0, 6.0));
+    assert_eq!(interval("interval(1, 2) - interval(3, 4)"), (-3.0, -1.0));
+    assert_eq!(interval("interval(-1, 2) * interval(3, 4)"), (-4.0, 8.0));
+    assert_eq!(interval("-interval(1, 2)"), (-2.0, -1.0));
+    assert_eq!(interval("pow(interval(-2, 3), 2)"), (0.0, 9.0));
+    assert_eq!(interval("pow(interval(-2, 3), 3)"), (-8.0, 27.0));
+    assert_eq!(interval("sqrt(interval(4, 9))"), (2.0, 3.0));
+    assert_eq!(interval("cos(interval(-1, 1))").1, 1.0);
+    assert_eq!(interval("sin(interval(0, 3.2))").1, 1.0);
+    assert_eq!(
+        interval("mean([interval(1, 2), interval(3, 4)])"),
+        (2.0, 3.0)
+    );
+
+    // the bounds are rounded outwards, 1.9 * 3 is not exact
+    let (lo, hi) = interval("interval(1.9, 2.1) * 3");
+    assert!(lo < 5.7 && lo > 5.699999999999997, "{lo}");
+    assert!(hi > 6.3 && hi < 6.300000000000003, "{hi}");
+    let (lo, hi) = interval("interval(0.1, 0.1) + interval(0.2, 0.2)");
+    assert!(lo < 0.30000000000000004 && hi >= 0.30000000000000004);
+    let (lo, hi) = interval("interval(1, 2) / 3");
+    // the float 1 / 3 is smaller than one third and 2 / 3 is smaller than two thirds
+    assert!(lo == 1.0 / 3.0 && hi > 2.0 / 3.0);
+
+    // division by intervals with 0 inside
+    assert_eq!(interval("1 / interval(0, 2)"), (0.5, f64::INFINITY));
+    assert_eq!(
+        interval("interval(1, 2) / interval(-2, 0)"),
+        (f64::NEG_INFINITY, -0.5)
+    );
+    assert_eq!(
+        interval("1 / interval(-1, 2)"),
+        (f64::NEG_INFINITY, f64::INFINITY)
+    );
+    assert!(kalc("1 / interval(0, 0)").is_err());
+
+    assert_eq!(interval("exp(interval(0, 0))"), (1.0, 1.0));
+    let (lo, hi) = interval("exp(interval(-1, 1))");
+    assert!(lo <= (-1f64).exp() && hi >= 1f64.exp() && hi - lo < 2.4);
+    // order statistics go from the lower bounds to the upper bounds
+    let list = "[interval(1, 2), interval(5, 6), interval(3, 4)]";
+    assert_eq!(interval(&format!("median({list})")), (3.0, 4.0));
+    assert_eq!(interval(&format!("percentile({list}, 100)")), (5.0, 6.0));
+    let (lo, hi) = interval("median([interval(1, 2), 3])");
+    assert!(lo <= 2.0 && hi >= 2.5 && hi - lo < 0.6);
+    assert!(kalc("var([interval(1, 2), 3])").is_err());
+
+    // interval mode, a list of two numbers is an interval
+    let context = || Context::default().with_intervals();
+    match kalc_with("[1, 2] * 3", context()).unwrap().get() {
+        Token::Interval(interval) => assert_eq!((interval.lo(), interval.hi()), (3.0, 6.0)),
+        token => panic!("{token}"),
+    }
+    assert_eq!(
+        kalc_with("[[1, 2], [3, 4]] * 2", context())
+            .unwrap()
+            .get()
+            .to_string(),
+        "[interval(2, 4), interval(6, 8)]"
+    );
+    assert_eq!(kalc_string("[1, 2] * 3"), "[3, 6]");
+
+    assert_eq!(kalc_f64("lower(interval(1, 2))").unwrap(), 1.0);
+    assert_eq!(kalc_f64("upper(interval(1, 2))").unwrap(), 2.0);
+    assert_eq!(kalc_f64("mid(interval(1, 3))").unwrap(), 2.0);
+    assert!(kalc_bool("interval(1, 2) < 3").unwrap());
+    assert!(kalc("interval(1, 2) < interval(1.5, 3)").is_err());
+    assert!(kalc("interval(3, 2)").is_err());
+    assert!(kalc("sqrt(interval(-2, -1))").is_err());
+
+    let a = Interval::new(1.0, 2.0).unwrap();
+    assert_eq!(a * Interval::point(-1.0), -a);
+    assert_eq!(a.to_string(), "interval(1, 2)");
+}
+
+#[test]
+fn uncertainty() {
+    let uncertain = |formula: &str| match kalc(formula).unwrap().get() {
+        Token::Uncertain(uncertain) => (uncertain.value(), uncertain.uncertainty()),
+        token => panic!("{formula} = {token}"),
+    };
+    assert_eq!(kalc_string("9.81 ± 0.02"), "9.810 ± 0.020");
+    assert_eq!(kalc_string("9.81+-0.02"), "9.810 ± 0.020");
+    assert_eq!(kalc_string("1234.5 ± 56"), "1230 ± 60");
+    assert_eq!(kalc_i64("2 + -3").unwrap(), -1);
+
+    // the same measurement is correlated with itself
+    assert_eq!(kalc_string("map([9.81 ± 0.02], x -> x - x)"), "[0 ± 0]");
+    assert_eq!(kalc_string("map([2 ± 0.1], x -> x + x)"), "[4.00 ± 0.20]");
+    assert_eq!(kalc_string("(2 ± 0.1) * (3 ± 0.2)"), "6.0 ± 0.5");
+    let (value, error) = uncertain("(2 ± 0.1) * (3 ± 0.2)");
+    assert_eq!(value, 6.0);
+    assert!((error - 0.5).abs() < 1e-12, "{error}");
+    let (value, error) = uncertain("sqrt(4 ± 0.4)");
+    assert_eq!((value, error), (2.0, 0.1));
+
+    // functions without a derivative rule use a central difference
+    let (value, error) = uncertain("ln(2 ± 0.1)");
+    assert_eq!(value, 2f64.ln());
+    assert!((error - 0.05).abs() < 1e-9, "{error}");
+    assert_eq!(kalc_i64("count([1 ± 0.1, 2 ± 0.1])").unwrap(), 2);
+    assert!(kalc_bool("(2 ± 0.1) < 3").unwrap());
+
+    assert!(kalc("9.81 ± -0.02").is_err());
+    assert!(kalc("rand(1 ± 0.1)").is_err());
+    // outside of the domain
+    assert_eq!(
+        kalc("sqrt(-1 ± 0.1)").err().unwrap(),
+        "sqrt needs numbers that are not negative, got: -1.00 ± 0.10"
+    );
+    assert_eq!(
+        kalc("ln(0 ± 1)").err().unwrap(),
+        "ln needs positive numbers, got: 0.0 ± 1.0"
+    );
+    assert!(kalc("pow(-8 ± 0.1, 0.5)").is_err());
+    assert!(kalc("(1 ± 0.1) / 0").is_err());
+
+    let a = Uncertain::new(1.0, 0.1).unwrap();
+    assert_eq!(a.sub(&a).uncertainty(), 0.0);
+    assert_eq!(a.add(&Uncertain::exact(1.0)).to_string(), "2.00 ± 0.10");
+}
+
+#[test]
+fn dates() {
+    assert_eq!(kalc_string("2026-10-18 + 45 days"), "2026-12-02");
+    assert_eq!(kalc_string("2026-12-25 - 2026-10-18"), "68 days");
+    assert_eq!(kalc_f64("(2026-12-25 - 2026-10-18) in days").unwrap(), 68.0);
+    assert_eq!(kalc_string("3h 20min * 4"), "13h 20min");
+    assert_eq!(kalc_f64("(3h 20min) in min").unwrap(), 200.0);
+    assert_eq!(kalc_string("90s + 1 day"), "1 day 1min 30s");
+    assert_eq!(kalc_string("-(1h 30min) / 2"), "-(45min)");
+    assert_eq!(kalc_string("[1 day, 2 days]"), "[1 day, 2 days]");
+    assert_eq!(kalc_i64("weekday(2026-10-18)").unwrap(), 7);
+    assert_eq!(kalc_i64("weekday(2024-02-29)").unwrap(), 4);
+    assert!(kalc_bool("2026-10-18 < 2026-10-18T00:00:01").unwrap());
+
+    // a time of the day gives a date and time
+    assert_eq!(kalc_string("2026-10-18T22:30 + 2h"), "2026-10-19T00:30:00");
+    assert_eq!(kalc_string("2026-10-18 - 1h"), "2026-10-17T23:00:00");
+    assert_eq!(
+        kalc_string("2024-03-01T12:00:30 - 2024-02-28"),
+        "2 days 12h 30s"
+    );
+    // dates before 1970
+    assert_eq!(kalc_string("1900-03-01 - 1 day"), "1900-02-28");
+    // still subtraction
+    assert_eq!(kalc_i64("2026-10").unwrap(), 2016);
+    assert_eq!(kalc_i64("2026 - 10 - 18").unwrap(), 1998);
+
+    assert!(kalc("2026-02-29").is_err());
+    assert!(kalc("2026-10-18T25:00").is_err());
+    assert!(kalc("2026-10-18 * 2").is_err());
+    assert!(kalc("5 in days").is_err());
+
+    // the clock with a fixed offset
+    let utc = kalc_with("now", Context::default()).unwrap().get().clone();
+    let tokyo = kalc_with("now", Context::default().with_utc_offset(9 * 60))
+        .unwrap()
+        .get()
+        .clone();
+    let (Token::DateTime(utc), Token::DateTime(tokyo)) = (utc, tokyo) else {
+        panic!("now is not a date and time")
+    };
+    let offset = (tokyo - utc).seconds();
+    assert!((offset - 9.0 * 3600.0).abs() < 5.0, "{offset}");
+    assert!(kalc_bool("today <= now").unwrap());
+
+    let date = Date::new(2026, 10, 18).unwrap();
+    assert_eq!(date.weekday(), 7);
+    assert_eq!(Duration::from_seconds(3600.0).to_string(), "1h");
+    // long durations stay exact, durations that are too long are printed in seconds
+    assert_eq!(kalc_string("1e14 s"), "1157407407 days 9h 46min 40s");
+    assert_eq!(kalc_string("1e16 s"), "10000000000000000s");
+    assert!(kalc("2026-10-18 + 1e20 s").is_err());
+    assert!(kalc("2026-10-18T14:30 - 1e300 days").is_err());
+}
+
+#[test]
+fn ast() {
+    let expr = parse("2 * x + 10").unwrap();
+    assert_eq!(expr.span, Span::new(0, 10));
+    let ExprKind::Group(exprs) = &expr.kind else {
+        panic!("{expr:?}")
+    };
+    let ExprKind::Binary(BinaryOp::Add, a, b) = &exprs[0].kind else {
+        panic!("{expr:?}")
+    };
+    assert_eq!((a.span, b.span), (Span::new(0, 5), Span::new(8, 10)));
+    assert_eq!(b.kind, ExprKind::Value(Value::Int(10)));
+    let mut spans = Vec::new();
+    expr.walk(&mut |node| spans.push(node.span));
+    assert_eq!(spans.len(), 6);
+    assert_eq!(spans[3], Span::new(0, 1));
+
+    let expr = parse("sqrt(16) <= 45 days").unwrap();
+    let mut nodes = Vec::new();
+    expr.walk(&mut |node| nodes.push((node.span, node.kind.clone())));
+    assert!(matches!(nodes[2], (span, ExprKind::Unary(..)) if span == Span::new(0, 8)));
+    assert_eq!(
+        nodes[5],
+        (
+            Span::new(12, 19),
+            ExprKind::Value(Value::Duration(Duration::from_seconds(45.0 * 86400.0)))
+        )
+    );
+    assert!(parse("2 +").is_err());
+
+    // the variable is replaced by a value before the calculation
+    let mut expr = parse("2 * x + 1").unwrap();
+    expr.transform(&mut |node| {
+        if node.kind == ExprKind::Var("x".to_string()) {
+            node.kind = ExprKind::Value(Value::Float(1.5));
+        }
+    });
+    assert_eq!(expr.to_string(), "2 * 1.5 + 1");
+    assert_eq!(expr.eval(&Context::default()), Ok(Value::Float(4.0)));
+    // the formula is the same when it is parsed again
+    for formula in [
+        "2.0 / 4",
+        "1e21 + 1",
+        "2.5e-7 * x",
+        "-2.5 * 2",
+        "2 * (x + 1)",
+        "sqrt(16) <= 45 days",
+        "[1.5, 2] .* 3",
+        "x -> x / 2.0",
+    ] {
+        let expr = parse(formula).unwrap();
+        let again = parse(&expr.to_string()).unwrap();
+        assert_eq!(again.to_string(), expr.to_string(), "{formula}");
+        assert_eq!(Token::from(&again), Token::from(&expr), "{formula}");
+    }
+    assert_eq!(parse("2.0 / 4").unwrap().to_string(), "2.0 / 4");
+    assert_eq!(parse("1e21").unwrap().to_string(), "1e21");
+    let value = parse("[1, 2] .* 3").unwrap().eval(&Context::default());
+    assert_eq!(value, Ok(Value::List(vec![Value::Int(3), Value::Int(6)])));
+    assert_eq!(Token::from(Value::Bool(true)), Token::Bool(true));
+    assert!(Value::try_from(Token::Var("x".to_string())).is_err());
+}
+
+#[test]
+fn compiled() {
+    let compiled = compile("price * (1 + (rate / 100.0)) - discount").unwrap();
+    assert_eq!(compiled.vars(), ["price", "rate", "discount"]);
+    assert_eq!(compiled.var("discount"), Some(2));
+    let row = [Value::Float(80.0), Value::Int(25), Value::Int(5)];
+    assert_eq!(compiled.eval(&row), Ok(Value::Float(95.0)));
+    // the same as `kalc`, integers stay integers and a division by zero is 0
+    let compiled = compile("a / b + sqrt(16) * pi").unwrap();
+    for (a, b) in [(7, 2), (-9, 4), (3, 0)] {
+        let expected = kalc(&format!("{a} / {b} + sqrt(16) * pi")).unwrap();
+        let value = compiled.eval(&[Value::Int(a), Value::Int(b)]).unwrap();
+        assert_eq!(Token::from(value), *expected.get());
+    }
+    let compiled = compile("x > 2 && y ? max(x, 3) : ln(exp(x))").unwrap();
+    let eval = |x, y| compiled.eval(&[Value::Int(x), Value::Bool(y)]);
+    assert_eq!(eval(5, true), Ok(Value::Int(5)));
+    assert_eq!(eval(1, true), Ok(Value::Float(1.0)));
+    assert_eq!(eval(5, false), Ok(Value::Float(5.0)));
+    let error = compile("max(x, 3)").unwrap().eval(&[Value::Bool(false)]);
+    assert_eq!(error.err().unwrap(), "max needs numbers, got: false");
+    // the parts without variables are calculated once
+    assert_eq!(
+        compile("sum(1..4) * x").unwrap().eval(&[Value::Int(2)]),
+        Ok(Value::Int(20))
+    );
+    assert_eq!(compile("2 + 3").unwrap().eval(&[]), Ok(Value::Int(5)));
+    assert!(compile("2 +").is_err());
+    assert!(compile("[1, x]").is_err());
+    assert!(compile("rand() * x").is_err());
+    assert!(compile("sum(1..x)").is_err());
+    // integers that overflow are an error like with `kalc`
+    let compiled = compile("x * 2 - -x").unwrap();
+    assert_eq!(compiled.eval(&[Value::Int(3)]), Ok(Value::Int(9)));
+    let error = "Cannot calculate: 9223372036854775807 * 2, the integer overflows, use a float";
+    assert_eq!(
+        compiled.eval(&[Value::Int(i64::MAX)]),
+        Err(error.to_string())
+    );
+    assert_eq!(kalc("9223372036854775807 * 2").err().unwrap(), error);
+    assert!(compiled.eval(&[Value::Int(i64::MIN)]).is_err());
+    assert_eq!(
+        compile("-x").unwrap().eval(&[Value::Int(i64::MIN)]).err(),
+        kalc("-(0 - 9223372036854775807 - 1)").err()
+    );
+    assert!(kalc("(0 - 9223372036854775807 - 1) / -1").is_err());
+    assert!(compile("x").unwrap().eval(&[]).is_err());
+    assert!(compile("x")
+        .unwrap()
+        .eval(&[Value::List(Vec::new())])
+        .is_err());
+}
+
+#[test]
+fn batch() {
+    let compiled = compile("price * qty / (qty - 2)").unwrap();
+    let price = [2.5, 4.0, 1.0];
+    let qty: [i64; 3] = [4, 2, 6];
+    let results = compiled
+        .eval_batch(&[Column::from(&price[..]), Column::from(&qty[..])])
+        .unwrap();
+    assert_eq!(
+        results,
+        [
+            Ok(Value::Float(5.0)),
+            Ok(Value::Int(0)),
+            Ok(Value::Float(1.5))
+        ]
+    );
+    // a row that fails does not stop the others
+    let compiled = compile("if(ok, x + 1, x && ok)").unwrap();
+    let results = compiled
+        .eval_batch(&[Column::Bool(&[true, false]), Column::Int(&[1, 2])])
+        .unwrap();
+    assert_eq!(results[0], Ok(Value::Int(2)));
+    assert_eq!(
+        results[1].clone().err().unwrap(),
+        "Expected a boolean, got: 2"
+    );
+    // a row that overflows is an error, the other rows are calculated
+    let results = compile("x * 2")
+        .unwrap()
+        .eval_batch(&[Column::Int(&[1, i64::MAX, 3])])
+        .unwrap();
+    assert_eq!(results[0], Ok(Value::Int(2)));
+    assert!(results[1].is_err());
+    assert_eq!(results[2], Ok(Value::Int(6)));
+    assert_eq!(
+        compile("1 + 2").unwrap().eval_batch(&[]),
+        Ok(vec![Ok(Value::Int(3))])
+    );
+    let compiled = compile("x + y").unwrap();
+    assert!(compiled.eval_batch(&[Column::Int(&[1])]).is_err());
+    assert!(compiled
+        .eval_batch(&[Column::Int(&[1]), Column::Int(&[1, 2])])
+        .is_err());
+}
+
+#[test]
+fn steps() {
+    // the direct calculation and the calculation by steps give the same results
+    for formula in [
+        "2 * (2 - 1) + 2 (3)",
+        "150 > 100 ? 20 * 0.9 : 20",
+        "sum(map(1..3, x -> x * x))",
+        "sum(t, 1, 10, 100 / pow(1.05, t))",
+        "[[1, 2], [3, 4]] * [5, 6]",
+        "diff(sin(pow(x, 2)), x)",
+        "2026-10-18 + 45 days",
+        "if(2 > 1, 3, 4 / 0)",
+    ] {
+        let mut lexer = crate::Lexer::default();
+        lexer.parse(formula).unwrap();
+        let mut executor = crate::Executor::new(lexer);
+        let steps = executor.steps().unwrap();
+        assert_eq!(
+            steps.last(),
+            Some(kalc(formula).unwrap().get()),
+            "{formula}"
+        );
+        assert!(steps.len() > 1);
+    }
+    assert_eq!(kalc("1 + x").err().unwrap(), "Unknown variable: x");
+}
+
+#[test]
+fn trace() {
+    let trace = kalc_trace("(2 + 3) * 4 - sqrt(16)").unwrap();
+    let steps: Vec<(&str, String, String)> = trace
+        .steps
+        .iter()
+        .map(|step| (step.rule, step.before.to_string(), step.after.to_string()))
+        .collect();
+    assert_eq!(
+        steps,
+        [
+            ("+", "2 + 3".to_string(), "5".to_string()),
+            ("sqrt", "sqrt(16)".to_string(), "4".to_string()),
+            ("*", "5 * 4".to_string(), "20".to_string()),
+            ("-", "20 - 4".to_string(), "16".to_string()),
+        ]
+    );
+    assert_eq!(trace.steps[2].expression.to_string(), "20 - 4");
+    assert_eq!(trace.result().to_string(), "16");
+    assert_eq!(
+        kalc_trace("2 * 3 + 1").unwrap().to_text(),
+        "2 * 3 + 1\n1. *: 2 * 3 = 6\n   6 + 1\n2. +: 6 + 1 = 7\n   7"
+    );
+    assert_eq!(
+        kalc_trace("sum(1, 2)").unwrap().to_json(),
+        r#"{"expression": "sum(1, 2)", "steps": [{"rule": "sum", "before": "sum(1, 2)", "after": "3", "expression": "3"}], "result": "3"}"#
+    );
+    // only the branch that is taken
+    let trace = kalc_trace("if(2 > 1, 3, 4 / 0)").unwrap();
+    let rules: Vec<&str> = trace.steps.iter().map(|step| step.rule).collect();
+    assert_eq!(rules, [">", "if"]);
+    assert!(kalc_trace("1 + x").is_err());
+}
+
+#[test]
+fn money() {
+    let mut rates = RateTable::default();
+    rates.insert("EUR", "CHF", "0.94").unwrap();
+    rates.insert("USD", "CHF", "0.80").unwrap();
+    rates.insert("USD", "JPY", "151.37").unwrap();
+    let context = Context::default().with_rates(rates);
+    let money = |formula: &str| {
+        kalc_with(formula, context.clone())
+            .unwrap()
+            .get()
+            .to_string()
+    };
+    assert_eq!(money("12.50 EUR + 3 USD to CHF"), "14.15 CHF");
+    // the cross rate of the dollar and the euro
+    assert_eq!(money("3 USD + 12.50 EUR"), "17.69 USD");
+    assert_eq!(money("10 CHF in USD"), "12.50 USD");
+    // the yen has no minor unit
+    assert_eq!(money("9.99 USD in JPY"), "1512 JPY");
+    assert_eq!(money("12.50 EUR * 3"), "37.50 EUR");
+    assert_eq!(money("2 * -(1.25 EUR)"), "-2.50 EUR");
+    assert_eq!(money("10 EUR / 4"), "2.50 EUR");
+    assert_eq!(money("10 EUR / 0"), "0.00 EUR");
+    assert_eq!(money("5 CHF / 2.5 CHF"), "2");
+    assert!(kalc_with("1 EUR < 1 CHF", context.clone())
+        .unwrap()
+        .get_bool());
+
+    // exact, not floats
+    assert!(kalc_bool("0.1 EUR + 0.2 EUR == 0.3 EUR").unwrap());
+    assert_eq!(kalc_string("0.10 EUR / 3 * 3"), "0.10 EUR");
+
+    // no rate between the currencies
+    assert_eq!(
+        kalc("1 EUR + 1 USD").err().unwrap(),
+        "No exchange rate from USD to EUR"
+    );
+    assert!(kalc_with("1 EUR in GBP", context.clone()).is_err());
+    assert!(kalc("2 EUR * 3 EUR").is_err());
+    assert!(kalc("2 EUR + 3").is_err());
+    assert!(RateTable::default().insert("EUR", "XYZ", "1").is_err());
+}
+
+#[cfg(feature = "bigfloat")]
+#[test]
+fn bigfloat() {
+    let mut context = Context::default();
+    context.set_precision(50);
+    let big = |formula: &str| {
+        kalc_with(formula, context.clone())
+            .unwrap()
+            .get()
+            .to_string()
+    };
+    // the reference values have 50 significant digits
+    assert_eq!(
+        big("sqrt(2)"),
+        "1.4142135623730950488016887242096980785696718753769"
+    );
+    assert_eq!(
+        big("pi"),
+        "3.1415926535897932384626433832795028841971693993751"
+    );
+    assert_eq!(
+        big("exp(1)"),
+        "2.7182818284590452353602874713526624977572470937"
+    );
+    assert_eq!(
+        big("ln(2)"),
+        "0.69314718055994530941723212145817656807550013436026"
+    );
+    assert_eq!(
+        big("sin(1)"),
+        "0.84147098480789650665250232163029899962256306079837"
+    );
+    assert_eq!(
+        big("cos(1)"),
+        "0.54030230586813971740093660744297660373231042061792"
+    );
+    assert_eq!(
+        big("sin(100)"),
+        "-0.50636564110975879365655761045978543206503272129066"
+    );
+    assert_eq!(
+        big("pow(2, 0.5)"),
+        "1.4142135623730950488016887242096980785696718753769"
+    );
+    assert_eq!(
+        big("pow(1.1, 100)"),
+        "13780.612339822270184118337172089636776264331200038"
+    );
+    assert_eq!(
+        big("ln(0.1)"),
+        "-2.3025850929940456840179914546843642076011014886288"
+    );
+    assert_eq!(
+        big("exp(-10)"),
+        "0.000045399929762484851535591515560550610237918088866565"
+    );
+    assert_eq!(
+        big("pi * 2"),
+        "6.2831853071795864769252867665590057683943387987502"
+    );
+    // integers stay integers and other functions get floats
+    assert_eq!(big("2 + 3"), "5");
+    assert_eq!(big("0.1 + 0.2"), "0.3");
+    assert_eq!(big("max(pi, 3)"), std::f64::consts::PI.to_string());
+    assert!(kalc_with("ln(-1)", context.clone()).is_err());
+    // results that are too far from 1 are errors, the base is reduced before `ln`
+    assert!(kalc_with("pow(2, 1e300)", context.clone()).is_err());
+    assert!(kalc_with("pow(2, 1000000000000000)", context.clone()).is_err());
+    assert!(kalc_with("exp(1e300)", context.clone()).is_err());
+    assert_eq!(
+        big("ln(pow(10, 3000))"),
+        "6907.7552789821370520539743640530926228033044658863"
+    );
+
+    let context = Context::default().with_precision(100);
+    let pi = kalc_with("pi", context).unwrap().get().to_string();
+    assert_eq!(pi.len(), 101);
+    assert!(pi.starts_with("3.14159265358979323846264338327950288419716939937510"));
 }
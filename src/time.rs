@@ -0,0 +1,353 @@
+use std::{
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    context::Context,
+    function::{arguments, Function},
+    token::Token,
+    value::Value,
+};
+
+const MINUTE: i64 = 60;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+/// Longer durations are printed in seconds, the days of them are not exact
+const MAX_SPLIT: f64 = 1e15;
+
+/// The units of the durations like `45 days` or `3h 20min`
+const UNITS: &[(&str, f64)] = &[
+    ("ms", 0.001),
+    ("s", 1.0),
+    ("sec", 1.0),
+    ("second", 1.0),
+    ("seconds", 1.0),
+    ("min", MINUTE as f64),
+    ("minute", MINUTE as f64),
+    ("minutes", MINUTE as f64),
+    ("h", HOUR as f64),
+    ("hour", HOUR as f64),
+    ("hours", HOUR as f64),
+    ("day", DAY as f64),
+    ("days", DAY as f64),
+    ("week", 7.0 * DAY as f64),
+    ("weeks", 7.0 * DAY as f64),
+];
+
+/// A day of the proleptic Gregorian calendar, like `2026-10-18`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    /// Days after 1970-01-01
+    days: i64,
+}
+
+/// A date with the time of the day, like `2026-10-18T14:30`, in the time zone of the context
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime {
+    /// Seconds after 1970-01-01T00:00:00
+    seconds: i64,
+}
+
+/// A length of time, like `3h 20min`
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Duration {
+    seconds: f64,
+}
+
+impl Date {
+    /// The date when it exists, the month is from 1 to 12
+    pub fn new(year: i64, month: i64, day: i64) -> Result<Self, String> {
+        let date = Self {
+            days: days_from_civil(year, month, day),
+        };
+        if !(1..=12).contains(&month) || date.civil() != (year, month, day) {
+            return Err(format!("Invalid date: {year:04}-{month:02}-{day:02}"));
+        }
+        Ok(date)
+    }
+
+    /// The year, the month and the day
+    pub fn civil(self) -> (i64, i64, i64) {
+        civil_from_days(self.days)
+    }
+
+    /// From 1 for Monday to 7 for Sunday, like in ISO 8601
+    pub fn weekday(self) -> i64 {
+        // 1970-01-01 was a Thursday
+        (self.days + 3).rem_euclid(7) + 1
+    }
+
+    /// The start of the day
+    pub fn midnight(self) -> DateTime {
+        DateTime {
+            seconds: self.days * DAY,
+        }
+    }
+}
+
+impl DateTime {
+    pub fn new(date: Date, hour: i64, minute: i64, second: i64) -> Result<Self, String> {
+        if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..60).contains(&second) {
+            return Err(format!("Invalid time: {hour:02}:{minute:02}:{second:02}"));
+        }
+        Ok(Self {
+            seconds: date.midnight().seconds + hour * HOUR + minute * MINUTE + second,
+        })
+    }
+
+    /// The system clock with the offset of the context
+    pub fn now(context: &Context) -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs() as i64)
+            .unwrap_or_default();
+        Self {
+            seconds: seconds + context.utc_offset as i64 * MINUTE,
+        }
+    }
+
+    pub fn date(self) -> Date {
+        Date {
+            days: self.seconds.div_euclid(DAY),
+        }
+    }
+
+    /// Seconds after the start of the day
+    fn time(self) -> i64 {
+        self.seconds.rem_euclid(DAY)
+    }
+}
+
+impl Duration {
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self { seconds }
+    }
+
+    pub fn seconds(self) -> f64 {
+        self.seconds
+    }
+
+    /// One of the unit, like `min` or `days`
+    pub fn unit(name: &str) -> Option<Self> {
+        UNITS
+            .iter()
+            .find(|(unit, _)| *unit == name)
+            .map(|(_, seconds)| Self::from_seconds(*seconds))
+    }
+
+    /// A number of whole days, a date with it is still a date
+    pub fn is_days(self) -> bool {
+        self.seconds % DAY as f64 == 0.0
+    }
+}
+
+impl DateTime {
+    /// Rounded to the second, an error when the date is out of range
+    pub fn checked_add(self, rhs: Duration) -> Result<DateTime, String> {
+        let seconds = rhs.seconds.round();
+        // `as` saturates, so the duration is checked first
+        (seconds.abs() < i64::MAX as f64)
+            .then(|| self.seconds.checked_add(seconds as i64))
+            .flatten()
+            .map(|seconds| DateTime { seconds })
+            .ok_or_else(|| format!("The date is out of range: {self} + {rhs}"))
+    }
+}
+
+impl Sub for DateTime {
+    type Output = Duration;
+
+    fn sub(self, rhs: DateTime) -> Duration {
+        Duration::from_seconds(self.seconds as f64 - rhs.seconds as f64)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, rhs: Duration) -> Duration {
+        Duration::from_seconds(self.seconds + rhs.seconds)
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, rhs: Duration) -> Duration {
+        Duration::from_seconds(self.seconds - rhs.seconds)
+    }
+}
+
+impl Neg for Duration {
+    type Output = Duration;
+
+    fn neg(self) -> Duration {
+        Duration::from_seconds(-self.seconds)
+    }
+}
+
+impl Mul<f64> for Duration {
+    type Output = Duration;
+
+    fn mul(self, rhs: f64) -> Duration {
+        Duration::from_seconds(self.seconds * rhs)
+    }
+}
+
+impl Div<f64> for Duration {
+    type Output = Duration;
+
+    fn div(self, rhs: f64) -> Duration {
+        Duration::from_seconds(self.seconds / rhs)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = self.civil();
+        write!(f, "{year:04}-{month:02}-{day:02}")
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let time = self.time();
+        write!(
+            f,
+            "{}T{:02}:{:02}:{:02}",
+            self.date(),
+            time / HOUR,
+            time % HOUR / MINUTE,
+            time % MINUTE
+        )
+    }
+}
+
+/// Like `1 day 3h 20min 5s`, it can be written back
+impl Display for Duration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.seconds < 0.0 {
+            return write!(f, "-({})", -*self);
+        }
+        if !self.seconds.is_finite() || self.seconds >= MAX_SPLIT {
+            return write!(f, "{}s", self.seconds);
+        }
+        // rounding errors like 0.1 * 3 should not be printed, only the fraction is rounded
+        // so that long durations stay exact
+        let mut whole = self.seconds.trunc();
+        let mut fraction = ((self.seconds - whole) * 1e9).round() / 1e9;
+        if fraction >= 1.0 {
+            (whole, fraction) = (whole + 1.0, 0.0);
+        }
+        let whole = whole as i64;
+        let parts = [
+            (whole / DAY, if whole / DAY == 1 { " day" } else { " days" }),
+            (whole % DAY / HOUR, "h"),
+            (whole % HOUR / MINUTE, "min"),
+        ];
+        let mut first = true;
+        for (value, unit) in parts {
+            if value != 0 {
+                if !first {
+                    write!(f, " ")?
+                }
+                write!(f, "{value}{unit}")?;
+                first = false;
+            }
+        }
+        let seconds = (whole % MINUTE) as f64 + fraction;
+        if seconds != 0.0 || first {
+            if !first {
+                write!(f, " ")?
+            }
+            write!(f, "{seconds}s")?
+        }
+        Ok(())
+    }
+}
+
+/// Howard Hinnant's algorithm, the days after 1970-01-01
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    // the year starts in March, so the leap day is at the end
+    let month = (month + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+/// An ISO 8601 date `2026-10-18` or date and time `2026-10-18T14:30:00` after the year,
+/// returns the token and how many characters were used
+pub fn parse(year: &str, rest: &str) -> Option<Result<(Value, usize), String>> {
+    let bytes = rest.as_bytes();
+    let digits = |from: usize| -> Option<i64> {
+        let pair = rest.get(from..from + 2)?;
+        pair.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| pair.parse().ok())?
+    };
+    if year.len() != 4 || bytes.first() != Some(&b'-') || bytes.get(3) != Some(&b'-') {
+        return None;
+    }
+    // `2026-10-180` is not a date
+    let end = |len: usize| !bytes.get(len).is_some_and(u8::is_ascii_alphanumeric);
+    let year: i64 = year.parse().ok()?;
+    let (month, day) = (digits(1)?, digits(4)?);
+    let date = match Date::new(year, month, day) {
+        Ok(date) => date,
+        Err(error) => return Some(Err(error)),
+    };
+    if bytes.get(6) != Some(&b'T') {
+        return end(6).then_some(Ok((Value::Date(date), 6)));
+    }
+    let (hour, minute) = (
+        digits(7)?,
+        digits(10).filter(|_| bytes.get(9) == Some(&b':'))?,
+    );
+    let (second, len) = match bytes.get(12) {
+        Some(b':') => (digits(13)?, 15),
+        _ => (0, 12),
+    };
+    if !end(len) {
+        return None;
+    }
+    Some(DateTime::new(date, hour, minute, second).map(|time| (Value::DateTime(time), len)))
+}
+
+/// `today`, `now` and `weekday(date)`
+pub fn call(function: Function, args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    match function {
+        Function::Today => {
+            arguments::<0>(function, args)?;
+            Ok(Token::Date(DateTime::now(context).date()))
+        }
+        Function::Now => {
+            arguments::<0>(function, args)?;
+            Ok(Token::DateTime(DateTime::now(context)))
+        }
+        _ => match arguments::<1>(function, args)? {
+            [Token::Date(date)] => Ok(Token::I(date.weekday())),
+            [Token::DateTime(time)] => Ok(Token::I(time.date().weekday())),
+            [token] => Err(format!("{function} needs a date, got: {token}")),
+        },
+    }
+}
@@ -1,37 +1,101 @@
 use std::{
+    cmp::Ordering,
     fmt::Display,
     ops::{Add, Div, Mul, Sub},
 };
 
+#[cfg(feature = "bigfloat")]
+use dashu_float::DBig;
+
+#[cfg(feature = "bigfloat")]
+use crate::bigfloat;
+use crate::{
+    context::{Context, Radix, Width},
+    function::Function,
+    interval::{self, Interval},
+    matrix::Matrix,
+    money::{Money, Rational},
+    time::{Date, DateTime, Duration},
+    uncertain::{self, Uncertain},
+};
+
+/// Constructor of a token with two arguments, like `Token::Add`
+pub type Binary = fn(Box<Token>, Box<Token>) -> Token;
+
+type IntervalFn = fn(Interval) -> Result<Interval, String>;
+type IntervalOp = fn(Interval, Interval) -> Result<Interval, String>;
+/// The value and the derivative at a value
+type Derivative = fn(f64) -> (f64, f64);
+
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum Token {
     I(i64),
     F(f64),
+    Bool(bool),
 
     Add(Box<Token>, Box<Token>),
     Div(Box<Token>, Box<Token>),
     Sub(Box<Token>, Box<Token>),
     Mul(Box<Token>, Box<Token>),
+    /// `.*` element by element, `*` with matrices is the matrix product
+    ElemMul(Box<Token>, Box<Token>),
+    /// `a ± b`, is calculated to an uncertain value
+    PlusMinus(Box<Token>, Box<Token>),
+    /// `(3h 20min) in min`, a duration as a number of the unit
+    In(Box<Token>, Box<Token>),
 
     Sin(Box<Token>),
     Cos(Box<Token>),
     Pow(Box<Token>, Box<Token>),
     Sqrt(Box<Token>),
 
-    Group(Vec<Token>),
+    BitAnd(Box<Token>, Box<Token>),
+    BitOr(Box<Token>, Box<Token>),
+    BitXor(Box<Token>, Box<Token>),
+    Shl(Box<Token>, Box<Token>),
+    Shr(Box<Token>, Box<Token>),
+    BitNot(Box<Token>),
+    Neg(Box<Token>),
 
-    SAdd,
-    SDiv,
-    SSub,
-    SMul,
+    Eq(Box<Token>, Box<Token>),
+    Ne(Box<Token>, Box<Token>),
+    Lt(Box<Token>, Box<Token>),
+    Le(Box<Token>, Box<Token>),
+    Gt(Box<Token>, Box<Token>),
+    Ge(Box<Token>, Box<Token>),
+    And(Box<Token>, Box<Token>),
+    Or(Box<Token>, Box<Token>),
+    Not(Box<Token>),
 
-    SSin,
-    SCos,
-    SPow,
-    SSqrt,
+    /// Only the condition is calculated, after that the token is replaced by one of the branches
+    If(Box<Token>, Box<Token>, Box<Token>),
 
-    SGroupBeagin,
-    SGroupEnd,
+    List(Vec<Token>),
+    Matrix(Matrix),
+    Interval(Interval),
+    Uncertain(Uncertain),
+    Date(Date),
+    DateTime(DateTime),
+    Duration(Duration),
+    Money(Money),
+    /// An integer printed in a base, like `255 to hex` = `0xFF`, with the width of the
+    /// programmer mode
+    Radix(i64, Radix, Option<Width>),
+    /// The base after `to`, like `hex` or `base(36)`
+    Base(Radix),
+    /// A float with the digits of `Context::precision`
+    #[cfg(feature = "bigfloat")]
+    Big(DBig),
+    /// Inclusive range from, to, step
+    Range(Box<Token>, Box<Token>, Box<Token>),
+    Var(String),
+    /// Is never calculated, the body is used when an argument is given
+    Lambda(String, Box<Token>),
+    /// `a = b`, only used by functions like `solve(x * x = 2, x)`
+    Equation(Box<Token>, Box<Token>),
+    Call(Function, Vec<Token>),
+
+    Group(Vec<Token>),
 
     Inf,
 }
@@ -41,24 +105,76 @@ impl Display for Token {
         match self {
             Token::I(t0) => write!(f, "{t0}"),
             Token::F(t0) => write!(f, "{t0}"),
-            Token::Add(t0, t1) => write!(f, "{t0} + {t1}"),
-            Token::Div(t0, t1) => write!(f, "{t0} / {t1}"),
-            Token::Sub(t0, t1) => write!(f, "{t0} - {t1}"),
-            Token::Mul(t0, t1) => write!(f, "{t0} * {t1}"),
+            Token::Bool(t0) => write!(f, "{t0}"),
+            Token::Add(t0, t1) => write!(f, "{t0} + {}", Operand(t1)),
+            Token::Div(t0, t1) => write!(f, "{} / {}", Factor(t0), Operand(t1)),
+            Token::Sub(t0, t1) => write!(f, "{t0} - {}", Operand(t1)),
+            Token::Mul(t0, t1) => write!(f, "{} * {}", Factor(t0), Operand(t1)),
+            Token::ElemMul(t0, t1) => write!(f, "{t0} .* {}", Operand(t1)),
+            Token::PlusMinus(t0, t1) => write!(f, "{t0} ± {}", Operand(t1)),
+            Token::In(t0, t1) => write!(f, "{t0} in {}", Operand(t1)),
             Token::Sin(t0) => write!(f, "sin({t0})"),
             Token::Cos(t0) => write!(f, "cos({t0})"),
             Token::Pow(t0, t1) => write!(f, "pow({t0}, {t1})"),
             Token::Sqrt(t0) => write!(f, "sqrt({t0})"),
-            Token::SAdd => f.write_str("+"),
-            Token::SDiv => f.write_str("/"),
-            Token::SSub => f.write_str("-"),
-            Token::SMul => f.write_str("*"),
-            Token::SSin => f.write_str("sin"),
-            Token::SCos => f.write_str("cos"),
-            Token::SPow => f.write_str("pow"),
-            Token::SSqrt => f.write_str("sqrt"),
-            Token::SGroupBeagin => f.write_str("("),
-            Token::SGroupEnd => f.write_str(")"),
+            Token::BitAnd(t0, t1) => write!(f, "{t0} & {}", Operand(t1)),
+            Token::BitOr(t0, t1) => write!(f, "{t0} | {}", Operand(t1)),
+            Token::BitXor(t0, t1) => write!(f, "{t0} ^ {}", Operand(t1)),
+            Token::Shl(t0, t1) => write!(f, "{t0} << {}", Operand(t1)),
+            Token::Shr(t0, t1) => write!(f, "{t0} >> {}", Operand(t1)),
+            Token::BitNot(t0) => write!(f, "~{}", Operand(t0)),
+            Token::Eq(t0, t1) => write!(f, "{t0} == {}", Operand(t1)),
+            Token::Ne(t0, t1) => write!(f, "{t0} != {}", Operand(t1)),
+            Token::Lt(t0, t1) => write!(f, "{t0} < {}", Operand(t1)),
+            Token::Le(t0, t1) => write!(f, "{t0} <= {}", Operand(t1)),
+            Token::Gt(t0, t1) => write!(f, "{t0} > {}", Operand(t1)),
+            Token::Ge(t0, t1) => write!(f, "{t0} >= {}", Operand(t1)),
+            Token::And(t0, t1) => write!(f, "{t0} and {}", Operand(t1)),
+            Token::Or(t0, t1) => write!(f, "{t0} or {}", Operand(t1)),
+            Token::Not(t0) => write!(f, "not {}", Operand(t0)),
+            Token::If(t0, t1, t2) => write!(f, "if({t0}, {t1}, {t2})"),
+            Token::Neg(t0) => write!(f, "-{}", Operand(t0)),
+            Token::List(tokens) => {
+                write!(f, "[")?;
+                for (i, token) in tokens.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+                    write!(f, "{token}")?
+                }
+                write!(f, "]")
+            }
+            Token::Matrix(matrix) => write!(f, "{matrix}"),
+            Token::Interval(interval) => write!(f, "{interval}"),
+            Token::Uncertain(uncertain) => write!(f, "{uncertain}"),
+            Token::Date(date) => write!(f, "{date}"),
+            Token::DateTime(time) => write!(f, "{time}"),
+            Token::Duration(duration) => write!(f, "{duration}"),
+            Token::Money(money) => write!(f, "{money}"),
+            Token::Radix(num, radix, width) => f.write_str(&radix.format(*num, *width)),
+            Token::Base(radix) => write!(f, "{radix}"),
+            #[cfg(feature = "bigfloat")]
+            Token::Big(num) => write!(f, "{num}"),
+            Token::Range(t0, t1, t2) => {
+                write!(f, "{t0}..{t1}")?;
+                if **t2 != Token::I(1) {
+                    write!(f, " step {t2}")?
+                }
+                Ok(())
+            }
+            Token::Var(name) => f.write_str(name),
+            Token::Lambda(name, t0) => write!(f, "{name} -> {t0}"),
+            Token::Equation(t0, t1) => write!(f, "{t0} = {t1}"),
+            Token::Call(function, tokens) => {
+                write!(f, "{function}(")?;
+                for (i, token) in tokens.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+                    write!(f, "{token}")?
+                }
+                write!(f, ")")
+            }
             Token::Inf => f.write_str("inf"),
             Token::Group(tokens) => {
                 write!(f, "( ")?;
@@ -72,6 +188,51 @@ impl Display for Token {
     }
 }
 
+/// A sum on the left side of `*` and `/` is calculated first too, the parentheses are
+/// only for the reader, `(x + 1) * (x - 1)`
+struct Factor<'a>(&'a Token);
+
+impl Display for Factor<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Token::Add(..) | Token::Sub(..) => write!(f, "({})", self.0),
+            token => write!(f, "{token}"),
+        }
+    }
+}
+
+/// Left to right evaluation needs parentheses only around operators on the right side
+struct Operand<'a>(&'a Token);
+
+impl Display for Operand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Token::Add(..)
+            | Token::Sub(..)
+            | Token::Mul(..)
+            | Token::ElemMul(..)
+            | Token::PlusMinus(..)
+            | Token::In(..)
+            | Token::Uncertain(_)
+            | Token::Div(..)
+            | Token::BitAnd(..)
+            | Token::BitOr(..)
+            | Token::BitXor(..)
+            | Token::Shl(..)
+            | Token::Shr(..)
+            | Token::Eq(..)
+            | Token::Ne(..)
+            | Token::Lt(..)
+            | Token::Le(..)
+            | Token::Gt(..)
+            | Token::Ge(..)
+            | Token::And(..)
+            | Token::Or(..) => write!(f, "({})", self.0),
+            token => write!(f, "{token}"),
+        }
+    }
+}
+
 impl Token {
     pub fn a(a: Token, b: Token) -> Token {
         Token::Add(Box::new(a), Box::new(b))
@@ -99,6 +260,10 @@ impl Token {
     pub fn sqrt(token: Token) -> Token {
         Token::Sqrt(Box::new(token))
     }
+
+    pub fn if_(condition: Token, a: Token, b: Token) -> Token {
+        Token::If(Box::new(condition), Box::new(a), Box::new(b))
+    }
 }
 
 impl Token {
@@ -115,6 +280,23 @@ impl Token {
             Token::Pow(t0, t1) => t0.is_num() && t1.is_num(),
             Token::Sqrt(t0) => t0.is_num(),
 
+            Token::BitAnd(t0, t1)
+            | Token::BitOr(t0, t1)
+            | Token::BitXor(t0, t1)
+            | Token::Shl(t0, t1)
+            | Token::Shr(t0, t1) => t0.is_num() && t1.is_num(),
+            Token::BitNot(t0) => t0.is_num(),
+
+            Token::Eq(t0, t1)
+            | Token::Ne(t0, t1)
+            | Token::Lt(t0, t1)
+            | Token::Le(t0, t1)
+            | Token::Gt(t0, t1)
+            | Token::Ge(t0, t1)
+            | Token::And(t0, t1)
+            | Token::Or(t0, t1) => t0.is_value() && t1.is_value(),
+            Token::Not(t0) | Token::If(t0, _, _) => t0.is_value(),
+
             Token::Group(tokens) => {
                 let a: usize = tokens.iter().map(|token| token.is_num() as usize).sum();
                 a == tokens.len()
@@ -127,21 +309,217 @@ impl Token {
         matches!(self, Token::I(_) | Token::F(_))
     }
 
-    pub fn is_calculabile(&self) -> bool {
-        matches!(
-            self,
+    /// Like `==` but `NaN` is the same as `NaN`, used to find out if a step changed something
+    pub fn same(&self, other: &Token) -> bool {
+        let bits = |a: f64, b: f64| a == b || a.to_bits() == b.to_bits();
+        match (self, other) {
+            (Token::F(a), Token::F(b)) => bits(*a, *b),
+            (Token::Matrix(a), Token::Matrix(b)) => {
+                let (rows, cols) = a.shape();
+                a.shape() == b.shape()
+                    && (0..rows)
+                        .all(|row| (0..cols).all(|col| bits(a.get(row, col), b.get(row, col))))
+            }
+            (Token::Uncertain(a), Token::Uncertain(b)) => {
+                a == b || bits(a.value(), b.value()) && bits(a.uncertainty(), b.uncertainty())
+            }
+            (Token::Call(f0, _), Token::Call(f1, _)) if f0 != f1 => false,
+            (Token::Lambda(v0, _), Token::Lambda(v1, _)) if v0 != v1 => false,
+            _ if std::mem::discriminant(self) != std::mem::discriminant(other) => false,
+            _ => {
+                let (a, b) = (self.children(), other.children());
+                match a.is_empty() && b.is_empty() {
+                    true => self == other,
+                    false => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same(b)),
+                }
+            }
+        }
+    }
+
+    /// The error for integers that overflow, a float has no limit
+    pub fn overflow(&self) -> String {
+        format!("Cannot calculate: {self}, the integer overflows, use a float")
+    }
+
+    /// `+` of two numbers, `None` when integers overflow
+    pub fn checked_add(self, rhs: Token) -> Option<Token> {
+        match (&self, &rhs) {
+            (Token::I(a), Token::I(b)) => a.checked_add(*b).map(Token::I),
+            _ => Some(self + rhs),
+        }
+    }
+
+    /// `-` of two numbers, `None` when integers overflow
+    pub fn checked_sub(self, rhs: Token) -> Option<Token> {
+        match (&self, &rhs) {
+            (Token::I(a), Token::I(b)) => a.checked_sub(*b).map(Token::I),
+            _ => Some(self - rhs),
+        }
+    }
+
+    /// `*` of two numbers, `None` when integers overflow
+    pub fn checked_mul(self, rhs: Token) -> Option<Token> {
+        match (&self, &rhs) {
+            (Token::I(a), Token::I(b)) => a.checked_mul(*b).map(Token::I),
+            _ => Some(self * rhs),
+        }
+    }
+
+    /// `/` of two numbers, the division by 0 is 0, `None` for `-2^63 / -1`
+    pub fn checked_div(self, rhs: Token) -> Option<Token> {
+        match (&self, &rhs) {
+            _ if rhs.is_zero() => Some(Token::I(0)),
+            (Token::I(a), Token::I(b)) => a.checked_div(*b).map(Token::I),
+            _ => Some(self / rhs),
+        }
+    }
+
+    /// A token that is fully calculated
+    pub fn is_value(&self) -> bool {
+        match self {
             Token::I(_)
-                | Token::F(_)
-                | Token::Group(_)
-                | Token::Add(_, _)
-                | Token::Sub(_, _)
-                | Token::Mul(_, _)
-                | Token::Div(_, _)
-                | Token::Sin(_)
-                | Token::Cos(_)
-                | Token::Pow(_, _)
-                | Token::Sqrt(_)
-        )
+            | Token::F(_)
+            | Token::Bool(_)
+            | Token::Matrix(_)
+            | Token::Interval(_)
+            | Token::Uncertain(_)
+            | Token::Date(_)
+            | Token::DateTime(_)
+            | Token::Duration(_)
+            | Token::Money(_)
+            | Token::Radix(..) => true,
+            #[cfg(feature = "bigfloat")]
+            Token::Big(_) => true,
+            Token::List(tokens) => tokens.iter().all(Token::is_value),
+            _ => false,
+        }
+    }
+
+    /// False when a random function is used, then the result cannot be cached or folded
+    pub fn is_deterministic(&self) -> bool {
+        match self {
+            Token::Call(function, _) if !function.is_deterministic() => false,
+            _ => self.children().into_iter().all(Token::is_deterministic),
+        }
+    }
+
+    /// The tokens inside of this token
+    pub fn children(&self) -> Vec<&Token> {
+        match self {
+            Token::Add(t0, t1)
+            | Token::Div(t0, t1)
+            | Token::Sub(t0, t1)
+            | Token::Mul(t0, t1)
+            | Token::ElemMul(t0, t1)
+            | Token::PlusMinus(t0, t1)
+            | Token::In(t0, t1)
+            | Token::Pow(t0, t1)
+            | Token::BitAnd(t0, t1)
+            | Token::BitOr(t0, t1)
+            | Token::BitXor(t0, t1)
+            | Token::Shl(t0, t1)
+            | Token::Shr(t0, t1)
+            | Token::Eq(t0, t1)
+            | Token::Ne(t0, t1)
+            | Token::Lt(t0, t1)
+            | Token::Le(t0, t1)
+            | Token::Gt(t0, t1)
+            | Token::Ge(t0, t1)
+            | Token::And(t0, t1)
+            | Token::Or(t0, t1)
+            | Token::Equation(t0, t1) => vec![t0, t1],
+            Token::Sin(t0)
+            | Token::Cos(t0)
+            | Token::Sqrt(t0)
+            | Token::BitNot(t0)
+            | Token::Neg(t0)
+            | Token::Not(t0)
+            | Token::Lambda(_, t0) => vec![t0],
+            Token::If(t0, t1, t2) | Token::Range(t0, t1, t2) => vec![t0, t1, t2],
+            Token::List(tokens) | Token::Call(_, tokens) | Token::Group(tokens) => {
+                tokens.iter().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Token> {
+        match self {
+            Token::Add(t0, t1)
+            | Token::Div(t0, t1)
+            | Token::Sub(t0, t1)
+            | Token::Mul(t0, t1)
+            | Token::ElemMul(t0, t1)
+            | Token::PlusMinus(t0, t1)
+            | Token::In(t0, t1)
+            | Token::Pow(t0, t1)
+            | Token::BitAnd(t0, t1)
+            | Token::BitOr(t0, t1)
+            | Token::BitXor(t0, t1)
+            | Token::Shl(t0, t1)
+            | Token::Shr(t0, t1)
+            | Token::Eq(t0, t1)
+            | Token::Ne(t0, t1)
+            | Token::Lt(t0, t1)
+            | Token::Le(t0, t1)
+            | Token::Gt(t0, t1)
+            | Token::Ge(t0, t1)
+            | Token::And(t0, t1)
+            | Token::Or(t0, t1)
+            | Token::Equation(t0, t1) => vec![t0, t1],
+            Token::Sin(t0)
+            | Token::Cos(t0)
+            | Token::Sqrt(t0)
+            | Token::BitNot(t0)
+            | Token::Neg(t0)
+            | Token::Not(t0)
+            | Token::Lambda(_, t0) => vec![t0],
+            Token::If(t0, t1, t2) | Token::Range(t0, t1, t2) => vec![t0, t1, t2],
+            Token::List(tokens) | Token::Call(_, tokens) | Token::Group(tokens) => {
+                tokens.iter_mut().collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// The first variable that is not inside of a lambda that defines it
+    pub fn find_var(&self) -> Option<&str> {
+        match self {
+            Token::Var(name) => Some(name),
+            Token::Lambda(name, body) => body.find_var().filter(|var| var != name),
+            _ => self.children().into_iter().find_map(Token::find_var),
+        }
+    }
+
+    /// Replaces every `Var` with the name, lambdas with the same name are not changed
+    pub fn substitute(&self, name: &str, value: &Token) -> Token {
+        match self {
+            Token::Var(var) if var == name => value.clone(),
+            Token::Lambda(var, _) if var == name => self.clone(),
+            _ => {
+                let mut token = self.clone();
+                for child in token.children_mut() {
+                    *child = child.substitute(name, value)
+                }
+                token
+            }
+        }
+    }
+
+    /// Gives the argument to a lambda
+    pub fn apply(&self, arg: Token) -> Result<Token, String> {
+        match self {
+            Token::Lambda(name, body) => Ok(body.substitute(name, &arg)),
+            _ => Err(format!("Expected a lambda like: x -> x * 2, got: {self}")),
+        }
+    }
+
+    pub fn into_list(self, function: Function) -> Result<Vec<Token>, String> {
+        match self {
+            Token::List(tokens) => Ok(tokens),
+            Token::Matrix(matrix) => Ok(matrix.into_rows()),
+            _ => Err(format!("{function} needs a list, got: {self}")),
+        }
     }
 
     pub fn is_zero(&self) -> bool {
@@ -152,30 +530,600 @@ impl Token {
         }
     }
 
-    pub fn calculate(&mut self) {
+    /// Returns the integer of a number, floats are not accepted
+    pub fn get_int(&self) -> Result<i64, String> {
+        match self {
+            Token::I(num) => Ok(*num),
+            _ => Err(format!(
+                "Bitwise operators only work on integers, got: {self}"
+            )),
+        }
+    }
+
+    pub fn get_bool(&self) -> Result<bool, String> {
         match self {
-            Token::Add(t1, t2) => {
-                if t1.is_num() && t2.is_num() {
-                    *self = t1.as_ref().clone() + t2.as_ref().clone()
+            Token::Bool(b) => Ok(*b),
+            _ => Err(format!("Expected a boolean, got: {self}")),
+        }
+    }
+
+    /// Compares two numbers, in the `U64` programmer mode the integers are compared unsigned
+    pub fn compare(&self, other: &Token, context: &Context) -> Result<Ordering, String> {
+        match (self, other) {
+            (Token::I(a), Token::I(b)) => match context.width {
+                Some(Width::U64) => Ok((*a as u64).cmp(&(*b as u64))),
+                _ => Ok(a.cmp(b)),
+            },
+            (Token::I(_) | Token::F(_), Token::I(_) | Token::F(_)) => {
+                let (a, b) = (self.get_f64(), other.get_f64());
+                a.partial_cmp(&b)
+                    .ok_or_else(|| format!("Cannot compare: {a} with {b}"))
+            }
+            (Token::Bool(a), Token::Bool(b)) => Ok(a.cmp(b)),
+            #[cfg(feature = "bigfloat")]
+            (Token::Big(_), _) | (_, Token::Big(_)) => {
+                match (bigfloat::to_big(self), bigfloat::to_big(other)) {
+                    (Some(a), Some(b)) => Ok(a.cmp(&b)),
+                    _ => Err(format!("Cannot compare: {self} with {other}")),
                 }
             }
-            Token::Div(t1, t2) => {
-                if t1.is_num() && t2.is_num() {
-                    if t2.is_zero() {
-                        *self = Token::I(0)
-                    } else {
-                        *self = t1.as_ref().clone() / t2.as_ref().clone()
-                    }
+            (Token::Money(a), Token::Money(b)) => {
+                let b = b.to(a.currency(), context)?;
+                a.amount()
+                    .partial_cmp(&b.amount())
+                    .ok_or_else(|| format!("Cannot compare: {self} with {other}"))
+            }
+            (Token::Duration(a), Token::Duration(b)) => a
+                .partial_cmp(b)
+                .ok_or_else(|| format!("Cannot compare: {self} with {other}")),
+            (Token::Date(_) | Token::DateTime(_), Token::Date(_) | Token::DateTime(_)) => {
+                Ok(self.to_datetime().cmp(&other.to_datetime()))
+            }
+            // measurements are compared by their values
+            (Token::Uncertain(_), _) | (_, Token::Uncertain(_)) => {
+                match (
+                    uncertain::to_uncertain(self),
+                    uncertain::to_uncertain(other),
+                ) {
+                    (Some(a), Some(b)) => a
+                        .value()
+                        .partial_cmp(&b.value())
+                        .ok_or_else(|| format!("Cannot compare: {self} with {other}")),
+                    _ => Err(format!("Cannot compare: {self} with {other}")),
                 }
             }
-            Token::Sub(t1, t2) => {
-                if t1.is_num() && t2.is_num() {
-                    *self = t1.as_ref().clone() - t2.as_ref().clone()
+            // only intervals that do not overlap or the same numbers are sure
+            (Token::Interval(_), _) | (_, Token::Interval(_)) => {
+                match (self.to_interval(), other.to_interval()) {
+                    (Some(a), Some(b)) if a.hi() < b.lo() => Ok(Ordering::Less),
+                    (Some(a), Some(b)) if a.lo() > b.hi() => Ok(Ordering::Greater),
+                    (Some(a), Some(b)) if a.lo() == a.hi() && a == b => Ok(Ordering::Equal),
+                    _ => Err(format!(
+                        "Cannot compare: {self} with {other}, the intervals overlap"
+                    )),
                 }
             }
-            Token::Mul(t1, t2) => {
-                if t1.is_num() && t2.is_num() {
-                    *self = t1.as_ref().clone() * t2.as_ref().clone()
+            _ => Err(format!("Cannot compare: {self} with {other}")),
+        }
+    }
+
+    /// The number as a float, `NaN` when is not a number
+    pub fn get_f64(&self) -> f64 {
+        match self {
+            Token::I(num) => *num as f64,
+            Token::F(num) => *num,
+            #[cfg(feature = "bigfloat")]
+            Token::Big(num) => num.to_f64().value(),
+            _ => f64::NAN,
+        }
+    }
+
+    /// Operators with a list are done for every element
+    fn broadcast(&self) -> Result<Option<Token>, String> {
+        let (t1, t2, token): (_, _, Binary) = match self {
+            Token::Add(t1, t2) => (t1, t2, Token::Add),
+            Token::Sub(t1, t2) => (t1, t2, Token::Sub),
+            Token::Mul(t1, t2) => (t1, t2, Token::Mul),
+            Token::ElemMul(t1, t2) => (t1, t2, Token::ElemMul),
+            Token::Div(t1, t2) => (t1, t2, Token::Div),
+            Token::Pow(t1, t2) => (t1, t2, Token::Pow),
+            Token::Sin(t1) | Token::Cos(t1) | Token::Sqrt(t1) | Token::Neg(t1) => {
+                let Token::List(list) = t1.as_ref() else {
+                    return Ok(None);
+                };
+                let tokens = list
+                    .iter()
+                    .map(|t| {
+                        let mut token = self.clone();
+                        *token.children_mut()[0] = t.clone();
+                        token
+                    })
+                    .collect();
+                return Ok(Some(Token::List(tokens)));
+            }
+            _ => return Ok(None),
+        };
+        if !t1.is_value() || !t2.is_value() {
+            return Ok(None);
+        }
+        let tokens = match (t1.as_ref(), t2.as_ref()) {
+            (Token::List(a), Token::List(b)) => {
+                if a.len() != b.len() {
+                    return Err(format!(
+                        "Lists need the same length, got: {} and {}",
+                        a.len(),
+                        b.len()
+                    ));
+                }
+                a.iter()
+                    .zip(b)
+                    .map(|(a, b)| token(Box::new(a.clone()), Box::new(b.clone())))
+                    .collect()
+            }
+            (Token::List(a), b) => a
+                .iter()
+                .map(|a| token(Box::new(a.clone()), Box::new(b.clone())))
+                .collect(),
+            (a, Token::List(b)) => b
+                .iter()
+                .map(|b| token(Box::new(a.clone()), Box::new(b.clone())))
+                .collect(),
+            _ => return Ok(None),
+        };
+        Ok(Some(Token::List(tokens)))
+    }
+
+    /// An interval or a number as an interval with one number
+    pub fn to_interval(&self) -> Option<Interval> {
+        match self {
+            Token::Interval(interval) => Some(*interval),
+            Token::F(num) => Some(Interval::point(*num)),
+            // big integers are not exact as floats
+            Token::I(num) if num.unsigned_abs() > 1 << f64::MANTISSA_DIGITS => {
+                let num = *num as f64;
+                Interval::new(num.next_down(), num.next_up()).ok()
+            }
+            Token::I(num) => Some(Interval::point(*num as f64)),
+            _ => None,
+        }
+    }
+
+    /// Operators with an interval, a number is an interval with one number
+    fn interval(&self) -> Result<Option<Token>, String> {
+        let unary: Option<(&Token, IntervalFn)> = match self {
+            Token::Neg(t1) => Some((t1, |a| Ok(-a))),
+            Token::Sin(t1) => Some((t1, |a| Ok(a.sin()))),
+            Token::Cos(t1) => Some((t1, |a| Ok(a.cos()))),
+            Token::Sqrt(t1) => Some((t1, Interval::sqrt)),
+            _ => None,
+        };
+        if let Some((t1, op)) = unary {
+            return match t1 {
+                Token::Interval(a) => Ok(Some(Token::Interval(op(*a)?))),
+                _ => Ok(None),
+            };
+        }
+        let (t1, t2, op): (_, _, IntervalOp) = match self {
+            Token::Add(t1, t2) => (t1, t2, |a, b| Ok(a + b)),
+            Token::Sub(t1, t2) => (t1, t2, |a, b| Ok(a - b)),
+            Token::Mul(t1, t2) | Token::ElemMul(t1, t2) => (t1, t2, |a, b| Ok(a * b)),
+            Token::Div(t1, t2) => (t1, t2, Interval::divide),
+            Token::Pow(t1, t2) => (t1, t2, Interval::pow),
+            _ => return Ok(None),
+        };
+        if !matches!(**t1, Token::Interval(_)) && !matches!(**t2, Token::Interval(_)) {
+            return Ok(None);
+        }
+        match (t1.to_interval(), t2.to_interval()) {
+            (Some(a), Some(b)) => Ok(Some(Token::Interval(op(a, b)?))),
+            _ if t1.is_value() && t2.is_value() => Err(format!(
+                "Cannot calculate: {self}, intervals only work with numbers and intervals"
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// Operators with a float or a big float, with the digits of the precision
+    #[cfg(feature = "bigfloat")]
+    fn big(&self, digits: usize) -> Result<Option<Token>, String> {
+        type Unary = fn(&DBig, usize) -> Result<DBig, String>;
+        type Binary = fn(&DBig, &DBig, usize) -> Result<DBig, String>;
+        let unary: Option<(&Token, Unary)> = match self {
+            Token::Neg(t1) if matches!(**t1, Token::Big(_)) => Some((t1, |x, _| Ok(-x.clone()))),
+            // like with floats, these give floats for integers too
+            Token::Sin(t1) => Some((t1, bigfloat::sin)),
+            Token::Cos(t1) => Some((t1, bigfloat::cos)),
+            Token::Sqrt(t1) => Some((t1, bigfloat::sqrt)),
+            _ => None,
+        };
+        if let Some((t1, op)) = unary {
+            return match bigfloat::to_big(t1) {
+                Some(x) => Ok(Some(Token::Big(op(&x, digits)?))),
+                None => Ok(None),
+            };
+        }
+        let (t1, t2, op): (_, _, Binary) = match self {
+            Token::Add(t1, t2) => (t1, t2, bigfloat::add),
+            Token::Sub(t1, t2) => (t1, t2, bigfloat::sub),
+            Token::Mul(t1, t2) | Token::ElemMul(t1, t2) => (t1, t2, bigfloat::mul),
+            Token::Div(t1, t2) => (t1, t2, bigfloat::div),
+            Token::Pow(t1, t2) => (t1, t2, bigfloat::pow),
+            _ => return Ok(None),
+        };
+        // integers stay integers, but `pow` always gives a float
+        if !bigfloat::is_big(t1) && !bigfloat::is_big(t2) && !matches!(self, Token::Pow(..)) {
+            return Ok(None);
+        }
+        match (bigfloat::to_big(t1), bigfloat::to_big(t2)) {
+            (Some(a), Some(b)) => Ok(Some(Token::Big(op(&a, &b, digits)?))),
+            _ => Ok(None),
+        }
+    }
+
+    /// `255 to hex` or `255 in base(36)`, a list is converted element by element
+    fn radix(&self, context: &Context) -> Result<Option<Token>, String> {
+        let Token::In(t1, t2) = self else {
+            return Ok(None);
+        };
+        let (Token::Base(radix), true) = (t2.as_ref(), t1.is_value()) else {
+            return Ok(None);
+        };
+        let num = match t1.as_ref() {
+            Token::I(num) | Token::Radix(num, ..) => *num,
+            Token::F(num) if num.fract() == 0.0 && num.abs() < i64::MAX as f64 => *num as i64,
+            Token::List(list) => {
+                let list = list
+                    .iter()
+                    .map(|token| Token::In(Box::new(token.clone()), t2.clone()))
+                    .collect();
+                return Ok(Some(Token::List(list)));
+            }
+            token => {
+                return Err(format!(
+                    "Cannot calculate: {self}, {radix} needs an integer, got: {token}"
+                ))
+            }
+        };
+        Ok(Some(Token::Radix(num, *radix, context.width)))
+    }
+
+    /// Operators with money, another currency is converted with the rates of the context
+    fn money(&self, context: &Context) -> Result<Option<Token>, String> {
+        if let Token::Neg(t1) = self {
+            return Ok(match t1.as_ref() {
+                Token::Money(a) => Some(Token::Money(-*a)),
+                _ => None,
+            });
+        }
+        let (t1, t2) = match self {
+            Token::Add(t1, t2)
+            | Token::Sub(t1, t2)
+            | Token::Mul(t1, t2)
+            | Token::ElemMul(t1, t2)
+            | Token::Div(t1, t2)
+            | Token::In(t1, t2) => (t1, t2),
+            _ => return Ok(None),
+        };
+        let is_money = matches!(**t1, Token::Money(_)) || matches!(**t2, Token::Money(_));
+        if !is_money || !t1.is_value() || !t2.is_value() {
+            return Ok(None);
+        }
+        let scale = |money: &Money, factor: Rational| -> Result<Token, String> {
+            Ok(Token::Money(Money::new(
+                money.amount().checked_mul(factor)?,
+                money.currency(),
+            )))
+        };
+        let token = match (self, t1.as_ref(), t2.as_ref()) {
+            (Token::Add(..), Token::Money(a), Token::Money(b)) => Token::Money(a.add(*b, context)?),
+            (Token::Sub(..), Token::Money(a), Token::Money(b)) => Token::Money(a.sub(*b, context)?),
+            (Token::Mul(..) | Token::ElemMul(..), Token::Money(a), b) if b.is_num() => {
+                scale(a, Rational::from_token(b)?)?
+            }
+            (Token::Mul(..) | Token::ElemMul(..), a, Token::Money(b)) if a.is_num() => {
+                scale(b, Rational::from_token(a)?)?
+            }
+            (Token::Div(..), Token::Money(a), b) if b.is_num() => scale(
+                a,
+                Rational::integer(1).checked_div(Rational::from_token(b)?)?,
+            )?,
+            (Token::Div(..), Token::Money(a), Token::Money(b)) => {
+                let b = b.to(a.currency(), context)?;
+                Token::F(a.amount().checked_div(b.amount())?.to_f64())
+            }
+            (Token::In(..), Token::Money(a), Token::Money(b)) => {
+                Token::Money(a.to(b.currency(), context)?)
+            }
+            _ => {
+                return Err(format!(
+                    "Cannot calculate: {self}, money works with money and numbers"
+                ))
+            }
+        };
+        Ok(Some(token))
+    }
+
+    /// A date is the start of the day
+    fn to_datetime(&self) -> Option<DateTime> {
+        match self {
+            Token::Date(date) => Some(date.midnight()),
+            Token::DateTime(time) => Some(*time),
+            _ => None,
+        }
+    }
+
+    /// Operators with dates and durations, a date with a number of whole days is a date
+    fn time(&self) -> Result<Option<Token>, String> {
+        if let Token::Neg(t1) = self {
+            return Ok(match t1.as_ref() {
+                Token::Duration(a) => Some(Token::Duration(-*a)),
+                _ => None,
+            });
+        }
+        let (t1, t2) = match self {
+            Token::Add(t1, t2)
+            | Token::Sub(t1, t2)
+            | Token::Mul(t1, t2)
+            | Token::ElemMul(t1, t2)
+            | Token::Div(t1, t2)
+            | Token::In(t1, t2) => (t1, t2),
+            _ => return Ok(None),
+        };
+        let is_time = |token: &Token| {
+            matches!(
+                token,
+                Token::Date(_) | Token::DateTime(_) | Token::Duration(_)
+            )
+        };
+        if !t1.is_value()
+            || !t2.is_value()
+            || !is_time(t1) && !is_time(t2) && !matches!(self, Token::In(..))
+        {
+            return Ok(None);
+        }
+        let add = |date: &Token, duration: Duration| -> Result<Token, String> {
+            let time = date
+                .to_datetime()
+                .unwrap_or_default()
+                .checked_add(duration)?;
+            Ok(match date {
+                Token::Date(_) if duration.is_days() => Token::Date(time.date()),
+                _ => Token::DateTime(time),
+            })
+        };
+        let token = match (self, t1.as_ref(), t2.as_ref()) {
+            (Token::Add(..), a @ (Token::Date(_) | Token::DateTime(_)), Token::Duration(b))
+            | (Token::Add(..), Token::Duration(b), a @ (Token::Date(_) | Token::DateTime(_))) => {
+                add(a, *b)?
+            }
+            (Token::Sub(..), a @ (Token::Date(_) | Token::DateTime(_)), Token::Duration(b)) => {
+                add(a, -*b)?
+            }
+            (Token::Sub(..), a, b) if a.to_datetime().is_some() && b.to_datetime().is_some() => {
+                Token::Duration(
+                    a.to_datetime().unwrap_or_default() - b.to_datetime().unwrap_or_default(),
+                )
+            }
+            (Token::Add(..), Token::Duration(a), Token::Duration(b)) => Token::Duration(*a + *b),
+            (Token::Sub(..), Token::Duration(a), Token::Duration(b)) => Token::Duration(*a - *b),
+            (Token::Mul(..) | Token::ElemMul(..), Token::Duration(a), b) if b.is_num() => {
+                Token::Duration(*a * b.get_f64())
+            }
+            (Token::Mul(..) | Token::ElemMul(..), a, Token::Duration(b)) if a.is_num() => {
+                Token::Duration(*b * a.get_f64())
+            }
+            // like with numbers, the division by 0 is 0
+            (Token::Div(..), Token::Duration(_), b) if b.is_zero() => {
+                Token::Duration(Duration::default())
+            }
+            (Token::Div(..), Token::Duration(a), b) if b.is_num() => {
+                Token::Duration(*a / b.get_f64())
+            }
+            (Token::Div(..) | Token::In(..), Token::Duration(a), Token::Duration(b)) => {
+                if b.seconds() == 0.0 {
+                    Token::I(0)
+                } else {
+                    Token::F(a.seconds() / b.seconds())
+                }
+            }
+            (Token::In(..), _, _) => {
+                return Err(format!(
+                    "Cannot calculate: {self}, in converts a duration like (3h 20min) in min"
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "Cannot calculate: {self}, dates work with durations and durations with numbers"
+                ))
+            }
+        };
+        Ok(Some(token))
+    }
+
+    /// `a ± b` and operators with an uncertain value, a number is an exact value
+    fn uncertain(&self) -> Result<Option<Token>, String> {
+        if let Token::PlusMinus(t1, t2) = self {
+            if !t1.is_value() || !t2.is_value() {
+                return Ok(None);
+            }
+            let uncertain = match (t1.as_ref(), t2.as_ref()) {
+                (a, b) if a.is_num() && b.is_num() => Uncertain::new(a.get_f64(), b.get_f64())?,
+                // another independent uncertainty, like a second error of the measurement
+                (Token::Uncertain(a), b) if b.is_num() => a.add(&Uncertain::new(0.0, b.get_f64())?),
+                _ => {
+                    return Err(format!(
+                        "Cannot calculate: {self}, ± needs numbers like 9.81 ± 0.02"
+                    ))
+                }
+            };
+            return Ok(Some(Token::Uncertain(uncertain)));
+        }
+        let unary: Option<(&Token, Derivative)> = match self {
+            Token::Neg(t1) => Some((t1, |x| (-x, -1.0))),
+            Token::Sin(t1) => Some((t1, |x| (x.sin(), x.cos()))),
+            Token::Cos(t1) => Some((t1, |x| (x.cos(), -x.sin()))),
+            Token::Sqrt(t1) => Some((t1, |x| (x.sqrt(), 0.5 / x.sqrt()))),
+            _ => None,
+        };
+        let result = match unary {
+            Some((Token::Uncertain(a), _)) if matches!(self, Token::Sqrt(_)) && a.value() < 0.0 => {
+                return Err(format!(
+                    "sqrt needs numbers that are not negative, got: {a}"
+                ))
+            }
+            Some((Token::Uncertain(a), f)) => {
+                let (value, derivative) = f(a.value());
+                a.map(value, derivative)
+            }
+            Some(_) => return Ok(None),
+            None => match self.uncertain_binary()? {
+                Some(result) => result,
+                None => return Ok(None),
+            },
+        };
+        if !result.is_finite() {
+            return Err(format!(
+                "Cannot calculate: {self}, the result is not finite"
+            ));
+        }
+        Ok(Some(Token::Uncertain(result)))
+    }
+
+    /// An operator with two values and at least one of them uncertain
+    fn uncertain_binary(&self) -> Result<Option<Uncertain>, String> {
+        let (t1, t2, op): (_, _, fn(&Uncertain, &Uncertain) -> Uncertain) = match self {
+            Token::Add(t1, t2) => (t1, t2, Uncertain::add),
+            Token::Sub(t1, t2) => (t1, t2, Uncertain::sub),
+            Token::Mul(t1, t2) | Token::ElemMul(t1, t2) => (t1, t2, Uncertain::mul),
+            Token::Div(t1, t2) => (t1, t2, Uncertain::div),
+            Token::Pow(t1, t2) => (t1, t2, Uncertain::pow),
+            _ => return Ok(None),
+        };
+        if !matches!(**t1, Token::Uncertain(_)) && !matches!(**t2, Token::Uncertain(_)) {
+            return Ok(None);
+        }
+        match (uncertain::to_uncertain(t1), uncertain::to_uncertain(t2)) {
+            (Some(a), Some(b)) => Ok(Some(op(&a, &b))),
+            _ if t1.is_value() && t2.is_value() => Err(format!(
+                "Cannot calculate: {self}, uncertain values only work with numbers"
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    /// Operators with a matrix, `*` is the matrix product and `.*` is element by element
+    fn matrix(&self) -> Result<Option<Token>, String> {
+        let (t1, t2, op): (_, _, fn(f64, f64) -> f64) = match self {
+            Token::Add(t1, t2) => (t1, t2, |a, b| a + b),
+            Token::Sub(t1, t2) => (t1, t2, |a, b| a - b),
+            Token::Mul(t1, t2) | Token::ElemMul(t1, t2) => (t1, t2, |a, b| a * b),
+            Token::Div(t1, t2) => (t1, t2, |a, b| a / b),
+            Token::Neg(t1) => {
+                return Ok(match t1.as_ref() {
+                    Token::Matrix(matrix) => Some(Token::Matrix(matrix.map(|x| -x))),
+                    _ => None,
+                })
+            }
+            _ => return Ok(None),
+        };
+        let has_matrix = matches!(**t1, Token::Matrix(_)) || matches!(**t2, Token::Matrix(_));
+        if !has_matrix || !t1.is_value() || !t2.is_value() {
+            return Ok(None);
+        }
+        let product = matches!(self, Token::Mul(..));
+        let token = match (t1.as_ref(), t2.as_ref()) {
+            (Token::Matrix(a), Token::Matrix(b)) if product => Token::Matrix(a.mul(b)?),
+            (Token::Matrix(a), Token::List(b)) if product => Token::List(
+                a.mul_vector(&vector(b)?)?
+                    .into_iter()
+                    .map(Token::F)
+                    .collect(),
+            ),
+            (Token::List(a), Token::Matrix(b)) if product => {
+                let row = Matrix::new(1, a.len(), vector(a)?)?;
+                Token::List(row.mul(b)?.into_data().into_iter().map(Token::F).collect())
+            }
+            (Token::Matrix(a), Token::Matrix(b)) => Token::Matrix(a.zip(b, op)?),
+            (Token::Matrix(a), b) if b.is_num() => {
+                let b = b.get_f64();
+                Token::Matrix(a.map(|a| op(a, b)))
+            }
+            (a, Token::Matrix(b)) if a.is_num() => {
+                let a = a.get_f64();
+                Token::Matrix(b.map(|b| op(a, b)))
+            }
+            _ => {
+                return Err(format!(
+                "Cannot calculate: {self}, matrices only work with numbers, vectors and matrices"
+            ))
+            }
+        };
+        Ok(Some(token))
+    }
+
+    pub fn calculate(&mut self, context: &Context) -> Result<(), String> {
+        if let Some(token) = self.matrix()? {
+            *self = token;
+            return Ok(());
+        }
+        if let Some(token) = self.broadcast()? {
+            *self = token;
+            return Ok(());
+        }
+        if let Some(token) = self.interval()? {
+            *self = token;
+            return Ok(());
+        }
+        if let Some(token) = self.uncertain()? {
+            *self = token;
+            return Ok(());
+        }
+        if let Some(token) = self.radix(context)? {
+            *self = token;
+            return Ok(());
+        }
+        if let Some(token) = self.money(context)? {
+            *self = token;
+            return Ok(());
+        }
+        if let Some(token) = self.time()? {
+            *self = token;
+            return Ok(());
+        }
+        #[cfg(feature = "bigfloat")]
+        if let Some(digits) = context.precision {
+            if let Some(token) = self.big(digits)? {
+                *self = token;
+                return Ok(());
+            }
+        }
+        match self {
+            Token::Add(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = match (context.width, t1.as_ref(), t2.as_ref()) {
+                    (Some(width), Token::I(a), Token::I(b)) => Token::I(width.add(*a, *b)),
+                    _ => (t1.as_ref().clone().checked_add(t2.as_ref().clone()))
+                        .ok_or_else(|| self.overflow())?,
+                }
+            }
+            Token::Div(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = match (context.width, t1.as_ref(), t2.as_ref()) {
+                    (Some(width), Token::I(a), Token::I(b)) => Token::I(width.div(*a, *b)),
+                    _ => (t1.as_ref().clone().checked_div(t2.as_ref().clone()))
+                        .ok_or_else(|| self.overflow())?,
+                }
+            }
+            Token::Sub(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = match (context.width, t1.as_ref(), t2.as_ref()) {
+                    (Some(width), Token::I(a), Token::I(b)) => Token::I(width.sub(*a, *b)),
+                    _ => (t1.as_ref().clone().checked_sub(t2.as_ref().clone()))
+                        .ok_or_else(|| self.overflow())?,
+                }
+            }
+            Token::Mul(t1, t2) | Token::ElemMul(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = match (context.width, t1.as_ref(), t2.as_ref()) {
+                    (Some(width), Token::I(a), Token::I(b)) => Token::I(width.mul(*a, *b)),
+                    _ => (t1.as_ref().clone().checked_mul(t2.as_ref().clone()))
+                        .ok_or_else(|| self.overflow())?,
                 }
             }
 
@@ -209,18 +1157,164 @@ impl Token {
                 _ => {}
             },
 
-            Token::Group(tokens) => {
-                if tokens.len() == 1 {
-                    if let Some(token) = tokens.pop() {
-                        *self = token;
-                    }
+            Token::BitAnd(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = Token::I(t1.get_int()? & t2.get_int()?)
+            }
+            Token::BitOr(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = Token::I(t1.get_int()? | t2.get_int()?)
+            }
+            Token::BitXor(t1, t2) if t1.is_num() && t2.is_num() => {
+                *self = Token::I(t1.get_int()? ^ t2.get_int()?)
+            }
+            Token::Shl(t1, t2) if t1.is_num() && t2.is_num() => {
+                let (a, b) = (t1.get_int()?, shift(t2)?);
+                *self = Token::I(context.width.unwrap_or(Width::I64).shl(a, b))
+            }
+            Token::Shr(t1, t2) if t1.is_num() && t2.is_num() => {
+                let (a, b) = (t1.get_int()?, shift(t2)?);
+                *self = Token::I(context.width.unwrap_or(Width::I64).shr(a, b))
+            }
+            Token::BitNot(t1) if t1.is_num() => *self = Token::I(!t1.get_int()?),
+            Token::Neg(t1) => match t1.as_ref() {
+                // the programmer mode wraps around
+                Token::I(t1) if context.width.is_some() => *self = Token::I(t1.wrapping_neg()),
+                Token::I(t1) => *self = Token::I(t1.checked_neg().ok_or_else(|| self.overflow())?),
+                Token::F(t1) => *self = Token::F(-t1),
+                _ => {}
+            },
+
+            Token::Eq(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.compare(t2, context)?.is_eq())
+            }
+            Token::Ne(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.compare(t2, context)?.is_ne())
+            }
+            Token::Lt(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.compare(t2, context)?.is_lt())
+            }
+            Token::Le(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.compare(t2, context)?.is_le())
+            }
+            Token::Gt(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.compare(t2, context)?.is_gt())
+            }
+            Token::Ge(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.compare(t2, context)?.is_ge())
+            }
+            Token::And(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.get_bool()? && t2.get_bool()?)
+            }
+            Token::Or(t1, t2) if t1.is_value() && t2.is_value() => {
+                *self = Token::Bool(t1.get_bool()? || t2.get_bool()?)
+            }
+            Token::Not(t1) if t1.is_value() => *self = Token::Bool(!t1.get_bool()?),
+            Token::If(condition, a, b) if condition.is_value() => {
+                *self = if condition.get_bool()? {
+                    std::mem::replace(a.as_mut(), Token::Inf)
+                } else {
+                    std::mem::replace(b.as_mut(), Token::Inf)
+                }
+            }
+
+            Token::Range(from, to, step) if from.is_num() && to.is_num() && step.is_num() => {
+                *self = Token::List(range(from, to, step)?)
+            }
+            Token::Call(function, args) if function.is_ready(args) => {
+                *self = function.call(std::mem::take(args), context)?
+            }
+            Token::Equation(t1, t2) if t1.is_value() && t2.is_value() => {
+                return Err(format!(
+                    "Use \"==\" to compare: {self}, \"=\" is for equations like solve(x * x = 2, x)"
+                ))
+            }
+
+            Token::List(bounds)
+                if context.intervals && bounds.len() == 2 && bounds.iter().all(Token::is_num) =>
+            {
+                if let Some(interval) = interval::from_bounds(&bounds[0], &bounds[1]) {
+                    *self = Token::Interval(interval?)
+                }
+            }
+            Token::List(rows) => {
+                if let Some(matrix) = Matrix::from_rows(rows) {
+                    *self = Token::Matrix(matrix)
+                }
+            }
+
+            Token::Group(tokens) if tokens.len() == 1 => {
+                if let Some(token) = tokens.pop() {
+                    *self = token;
                 }
             }
             _ => {}
         }
+
+        if let (Some(width), Token::I(num)) = (context.width, &self) {
+            *self = Token::I(width.wrap(*num))
+        }
+        Ok(())
     }
 }
 
+/// The numbers of a vector
+fn vector(list: &[Token]) -> Result<Vec<f64>, String> {
+    list.iter()
+        .map(|token| {
+            if token.is_num() {
+                Ok(token.get_f64())
+            } else {
+                Err(format!("A vector can only have numbers, got: {token}"))
+            }
+        })
+        .collect()
+}
+
+/// Ranges can not be longer than this
+const MAX_RANGE: usize = 10_000_000;
+
+fn range(from: &Token, to: &Token, step: &Token) -> Result<Vec<Token>, String> {
+    let invalid = || format!("Invalid range: {from}..{to} step {step}");
+    let len = match (from, to, step) {
+        // the integers are counted exactly, a f64 rounds `9223372036854775807`
+        (Token::I(from), Token::I(to), Token::I(step)) if *step != 0 => {
+            let (distance, step) = (*to as i128 - *from as i128, *step as i128);
+            if distance != 0 && (distance < 0) != (step < 0) {
+                0
+            } else {
+                distance / step + 1
+            }
+        }
+        _ => {
+            let len = (to.get_f64() - from.get_f64()) / step.get_f64();
+            if step.is_zero() || len.is_nan() {
+                return Err(invalid());
+            }
+            // rounding errors like 0.1 * 3 should not remove the last element
+            ((len + 1e-9).floor().max(-1.0) + 1.0) as i128
+        }
+    };
+    if len > MAX_RANGE as i128 {
+        return Err(format!("Range is too long: {from}..{to} step {step}"));
+    }
+    match (from, step) {
+        (Token::I(from), Token::I(step)) => (0..len)
+            .map(|i| {
+                let num = (*from as i128).checked_add(i.checked_mul(*step as i128)?)?;
+                i64::try_from(num).ok().map(Token::I)
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(invalid),
+        _ => {
+            let (from, step) = (from.get_f64(), step.get_f64());
+            Ok((0..len).map(|i| Token::F(from + i as f64 * step)).collect())
+        }
+    }
+}
+
+fn shift(token: &Token) -> Result<u32, String> {
+    u32::try_from(token.get_int()?).map_err(|_| format!("Cannot shift by: {token}"))
+}
+
 impl Add for Token {
     type Output = Token;
 
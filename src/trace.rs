@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+use crate::token::Token;
+
+/// One calculation of a part of the expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// The operator or the function that was used, like `+` or `sum`
+    pub rule: &'static str,
+    /// The part of the expression that was calculated, like `2 + 3`
+    pub before: Token,
+    /// What the part was calculated to, like `5`
+    pub after: Token,
+    /// The whole expression after the step
+    pub expression: Token,
+}
+
+/// How a result was calculated, made by `Executor::trace` or `kalc_trace`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// The expression before the first step
+    pub expression: Token,
+    pub steps: Vec<Step>,
+}
+
+impl Step {
+    /// `None` when the step only removes parentheses, like `(5)` to `5`
+    pub fn new(before: Token, after: Token, expression: &Token) -> Option<Self> {
+        Some(Self {
+            rule: rule(&before)?,
+            before,
+            after,
+            expression: outer(expression).clone(),
+        })
+    }
+}
+
+impl Trace {
+    pub fn new(expression: &Token, steps: Vec<Step>) -> Self {
+        Self {
+            expression: outer(expression).clone(),
+            steps,
+        }
+    }
+
+    /// The expression after the last step
+    pub fn result(&self) -> &Token {
+        self.steps
+            .last()
+            .map_or(&self.expression, |step| &step.expression)
+    }
+
+    /// The expression and every step with the expression after it, the same as `Display`
+    pub fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// `{"expression": "2 + 3", "steps": [{"rule": "+", "before": "2 + 3", "after": "5",
+    /// "expression": "5"}], "result": "5"}`, the tokens are written like their `Display`
+    pub fn to_json(&self) -> String {
+        let steps: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| {
+                format!(
+                    "{{\"rule\": {}, \"before\": {}, \"after\": {}, \"expression\": {}}}",
+                    json(step.rule),
+                    json(&step.before.to_string()),
+                    json(&step.after.to_string()),
+                    json(&step.expression.to_string())
+                )
+            })
+            .collect();
+        format!(
+            "{{\"expression\": {}, \"steps\": [{}], \"result\": {}}}",
+            json(&self.expression.to_string()),
+            steps.join(", "),
+            json(&self.result().to_string())
+        )
+    }
+}
+
+/// ```text
+/// 2 * 3 + 1
+/// 1. *: 2 * 3 = 6
+///    6 + 1
+/// 2. +: 6 + 1 = 7
+///    7
+/// ```
+impl Display for Trace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.expression)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            let number = format!("{}. ", i + 1);
+            write!(
+                f,
+                "\n{number}{}: {} = {}\n{:indent$}{}",
+                step.rule,
+                step.before,
+                step.after,
+                "",
+                step.expression,
+                indent = number.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The expression without the parentheses of the whole formula
+fn outer(token: &Token) -> &Token {
+    match token {
+        Token::Group(tokens) if tokens.len() == 1 => &tokens[0],
+        _ => token,
+    }
+}
+
+/// The name of the operator or the function of the token
+fn rule(token: &Token) -> Option<&'static str> {
+    Some(match token {
+        Token::Group(_) => return None,
+        Token::Add(..) => "+",
+        Token::Sub(..) => "-",
+        Token::Mul(..) => "*",
+        Token::Div(..) => "/",
+        Token::ElemMul(..) => ".*",
+        Token::PlusMinus(..) => "±",
+        Token::In(..) => "in",
+        Token::Sin(_) => "sin",
+        Token::Cos(_) => "cos",
+        Token::Pow(..) => "pow",
+        Token::Sqrt(_) => "sqrt",
+        Token::BitAnd(..) => "&",
+        Token::BitOr(..) => "|",
+        Token::BitXor(..) => "^",
+        Token::Shl(..) => "<<",
+        Token::Shr(..) => ">>",
+        Token::BitNot(_) => "~",
+        Token::Neg(_) => "negation",
+        Token::Eq(..) => "==",
+        Token::Ne(..) => "!=",
+        Token::Lt(..) => "<",
+        Token::Le(..) => "<=",
+        Token::Gt(..) => ">",
+        Token::Ge(..) => ">=",
+        Token::And(..) => "and",
+        Token::Or(..) => "or",
+        Token::Not(_) => "not",
+        Token::If(..) => "if",
+        Token::Range(..) => "range",
+        Token::List(_) => "list",
+        Token::Call(function, _) => function.name(),
+        _ => "value",
+    })
+}
+
+/// A JSON string with the escapes
+fn json(text: &str) -> String {
+    let mut result = String::from("\"");
+    for char in text.chars() {
+        match char {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            char if char.is_control() => result.push_str(&format!("\\u{:04x}", char as u32)),
+            char => result.push(char),
+        }
+    }
+    result.push('"');
+    result
+}
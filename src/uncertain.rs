@@ -0,0 +1,257 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{context::Context, function::Function, token::Token};
+
+/// Every `a ± b` is a new source, the same source in two values is correlated
+static NEXT_SOURCE: AtomicU64 = AtomicU64::new(0);
+
+/// A value with a standard uncertainty, propagated to first order
+///
+/// The value knows how much it changes for one standard uncertainty of every source
+/// it comes from, so `x - x` has no uncertainty
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Uncertain {
+    value: f64,
+    /// The source and the change, sorted by the source
+    parts: Vec<(u64, f64)>,
+}
+
+impl Uncertain {
+    /// A new independent value, like `9.81 ± 0.02`
+    pub fn new(value: f64, uncertainty: f64) -> Result<Self, String> {
+        if !(uncertainty >= 0.0 && uncertainty.is_finite()) {
+            return Err(format!(
+                "The uncertainty needs to be a positive number, got: {uncertainty}"
+            ));
+        }
+        let source = NEXT_SOURCE.fetch_add(1, Ordering::Relaxed);
+        Ok(Self {
+            value,
+            parts: vec![(source, uncertainty)],
+        })
+    }
+
+    /// A number without uncertainty
+    pub fn exact(value: f64) -> Self {
+        Self {
+            value,
+            parts: Vec::new(),
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// The standard uncertainty, the changes of all the sources added in quadrature
+    pub fn uncertainty(&self) -> f64 {
+        self.parts
+            .iter()
+            .map(|(_, change)| change * change)
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// The value and the changes are numbers, `sqrt(-1 ± 0.1)` is not
+    pub fn is_finite(&self) -> bool {
+        self.value.is_finite() && self.parts.iter().all(|(_, change)| change.is_finite())
+    }
+
+    /// `f(x)` with the derivative of `f` at the value
+    pub fn map(&self, value: f64, derivative: f64) -> Self {
+        Self {
+            value,
+            parts: self
+                .parts
+                .iter()
+                .map(|(source, change)| (*source, change * derivative))
+                .collect(),
+        }
+    }
+
+    /// `f(a, b)` with the partial derivatives of `f`, a derivative is only used when
+    /// its value has uncertainty, so it can be `NaN` for an exact value
+    pub fn combine(a: &Self, b: &Self, value: f64, da: f64, db: f64) -> Self {
+        let mut parts: Vec<(u64, f64)> = Vec::with_capacity(a.parts.len() + b.parts.len());
+        let (mut i, mut j) = (0, 0);
+        while i < a.parts.len() || j < b.parts.len() {
+            match (a.parts.get(i), b.parts.get(j)) {
+                (Some(x), Some(y)) if x.0 == y.0 => {
+                    parts.push((x.0, x.1 * da + y.1 * db));
+                    (i, j) = (i + 1, j + 1);
+                }
+                (Some(x), y) if y.is_none_or(|y| x.0 < y.0) => {
+                    parts.push((x.0, x.1 * da));
+                    i += 1;
+                }
+                (_, Some(y)) => {
+                    parts.push((y.0, y.1 * db));
+                    j += 1;
+                }
+                _ => break,
+            }
+        }
+        Self { value, parts }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        Self::combine(self, other, self.value + other.value, 1.0, 1.0)
+    }
+
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::combine(self, other, self.value - other.value, 1.0, -1.0)
+    }
+
+    pub fn mul(&self, other: &Self) -> Self {
+        Self::combine(
+            self,
+            other,
+            self.value * other.value,
+            other.value,
+            self.value,
+        )
+    }
+
+    pub fn div(&self, other: &Self) -> Self {
+        let (a, b) = (self.value, other.value);
+        Self::combine(self, other, a / b, 1.0 / b, -a / (b * b))
+    }
+
+    pub fn pow(&self, other: &Self) -> Self {
+        let (a, b) = (self.value, other.value);
+        let value = a.powf(b);
+        // `ln(a)` is only needed when the power is uncertain
+        let db = if other.parts.is_empty() {
+            0.0
+        } else {
+            value * a.ln()
+        };
+        Self::combine(self, other, value, b * a.powf(b - 1.0), db)
+    }
+}
+
+/// The value and the uncertainty rounded to the same digit, the uncertainty has
+/// two significant figures when it starts with 1 or 2 and one after that
+impl Display for Uncertain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let uncertainty = self.uncertainty();
+        if uncertainty == 0.0 || !uncertainty.is_finite() || !self.value.is_finite() {
+            return write!(f, "{} ± {uncertainty}", self.value);
+        }
+        let exponent = uncertainty.log10().floor() as i32;
+        let leading = uncertainty / 10f64.powi(exponent);
+        let figures = if leading < 3.0 { 2 } else { 1 };
+        let decimals = figures - 1 - exponent;
+        if decimals >= 0 {
+            let decimals = decimals as usize;
+            write!(f, "{:.decimals$} ± {uncertainty:.decimals$}", self.value)
+        } else {
+            let scale = 10f64.powi(-decimals);
+            let round = |x: f64| (x / scale).round() * scale;
+            write!(f, "{} ± {}", round(self.value), round(uncertainty))
+        }
+    }
+}
+
+/// An uncertain value or a number as an exact value
+pub fn to_uncertain(token: &Token) -> Option<Uncertain> {
+    match token {
+        Token::Uncertain(uncertain) => Some(uncertain.clone()),
+        Token::I(_) | Token::F(_) => Some(Uncertain::exact(token.get_f64())),
+        _ => None,
+    }
+}
+
+/// An uncertain value is in the arguments or in the lists of the arguments
+pub fn has_uncertain(args: &[Token]) -> bool {
+    args.iter().any(|arg| match arg {
+        Token::Uncertain(_) => true,
+        Token::List(list) => has_uncertain(list),
+        _ => false,
+    })
+}
+
+/// Any function of numbers, the derivative by every uncertain argument is found
+/// with a central difference
+pub fn call(function: Function, args: Vec<Token>, context: &Context) -> Result<Token, String> {
+    if !function.is_deterministic() {
+        return Err(format!("{function} does not work with uncertain values"));
+    }
+    let mut uncertain = Vec::new();
+    collect(&args, &mut uncertain);
+    if let (Function::Ln, Some(x)) = (function, uncertain.iter().find(|x| x.value <= 0.0)) {
+        return Err(format!("ln needs positive numbers, got: {x}"));
+    }
+    // the arguments with the values, one uncertain value can be shifted
+    let number = |shifted: Option<(usize, f64)>| -> Result<Token, String> {
+        let mut index = 0;
+        let args = args
+            .iter()
+            .map(|arg| values(arg, &mut index, shifted))
+            .collect();
+        match function.call(args, context)? {
+            token if token.is_num() => Ok(token),
+            token => Err(format!(
+                "{function} needs to give a number for uncertain values, got: {token}"
+            )),
+        }
+    };
+    let exact = number(None)?;
+    let mut result = Uncertain::exact(exact.get_f64());
+    for (i, x) in uncertain.iter().enumerate() {
+        let h = 1e-6 * x.value.abs().max(1.0);
+        let (above, below) = (
+            number(Some((i, x.value + h)))?,
+            number(Some((i, x.value - h)))?,
+        );
+        let derivative = (above.get_f64() - below.get_f64()) / (2.0 * h);
+        if derivative != 0.0 {
+            result = Uncertain::combine(&result, x, result.value, 1.0, derivative);
+        }
+    }
+    if !result.is_finite() {
+        let token = Token::Call(function, args);
+        return Err(format!(
+            "Cannot calculate: {token}, the result is not finite"
+        ));
+    }
+    // the result does not change with the values, like `count`
+    if result.parts.is_empty() {
+        return Ok(exact);
+    }
+    Ok(Token::Uncertain(result))
+}
+
+/// The uncertain values in the arguments and in their lists, in order
+fn collect(tokens: &[Token], uncertain: &mut Vec<Uncertain>) {
+    for token in tokens {
+        match token {
+            Token::Uncertain(x) => uncertain.push(x.clone()),
+            Token::List(list) => collect(list, uncertain),
+            _ => {}
+        }
+    }
+}
+
+/// The values in the place of the uncertain values, in the same order as `collect`
+fn values(token: &Token, index: &mut usize, shifted: Option<(usize, f64)>) -> Token {
+    match token {
+        Token::Uncertain(x) => {
+            let value = match shifted {
+                Some((i, value)) if i == *index => value,
+                _ => x.value,
+            };
+            *index += 1;
+            Token::F(value)
+        }
+        Token::List(list) => Token::List(
+            list.iter()
+                .map(|token| values(token, index, shifted))
+                .collect(),
+        ),
+        _ => token.clone(),
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Display;
+
+#[cfg(feature = "bigfloat")]
+use dashu_float::DBig;
+
+use crate::{
+    ast::Expr,
+    context::{Radix, Width},
+    interval::Interval,
+    matrix::Matrix,
+    money::Money,
+    time::{Date, DateTime, Duration},
+    token::Token,
+    uncertain::Uncertain,
+};
+
+/// A calculated result or a value written in the formula
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    List(Vec<Value>),
+    Matrix(Matrix),
+    Interval(Interval),
+    Uncertain(Uncertain),
+    Date(Date),
+    DateTime(DateTime),
+    Duration(Duration),
+    Money(Money),
+    /// An integer printed in a base, like `255 to hex` = `0xFF`
+    Radix(i64, Radix, Option<Width>),
+    /// The base after `to`, like `hex`
+    Base(Radix),
+    #[cfg(feature = "bigfloat")]
+    Big(DBig),
+    /// A function that is not called, like the result of `diff(pow(x, 2), x)`
+    Lambda(String, Box<Expr>),
+}
+
+impl Value {
+    /// The number as a float, `None` for values that are not numbers
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Int(num) | Value::Radix(num, ..) => Some(*num as f64),
+            Value::Float(num) => Some(*num),
+            #[cfg(feature = "bigfloat")]
+            Value::Big(num) => Some(num.to_f64().value()),
+            _ => None,
+        }
+    }
+}
+
+impl From<Value> for Token {
+    fn from(value: Value) -> Token {
+        match value {
+            Value::Int(num) => Token::I(num),
+            Value::Float(num) => Token::F(num),
+            Value::Bool(b) => Token::Bool(b),
+            Value::List(list) => Token::List(list.into_iter().map(Token::from).collect()),
+            Value::Matrix(matrix) => Token::Matrix(matrix),
+            Value::Interval(interval) => Token::Interval(interval),
+            Value::Uncertain(uncertain) => Token::Uncertain(uncertain),
+            Value::Date(date) => Token::Date(date),
+            Value::DateTime(time) => Token::DateTime(time),
+            Value::Duration(duration) => Token::Duration(duration),
+            Value::Money(money) => Token::Money(money),
+            Value::Radix(num, radix, width) => Token::Radix(num, radix, width),
+            Value::Base(radix) => Token::Base(radix),
+            #[cfg(feature = "bigfloat")]
+            Value::Big(num) => Token::Big(num),
+            Value::Lambda(name, body) => Token::Lambda(name, Box::new(Token::from(body.as_ref()))),
+        }
+    }
+}
+
+/// Fails for a token that still needs to be calculated
+impl TryFrom<Token> for Value {
+    type Error = String;
+
+    fn try_from(token: Token) -> Result<Value, String> {
+        Ok(match token {
+            Token::I(num) => Value::Int(num),
+            Token::F(num) => Value::Float(num),
+            Token::Inf => Value::Float(f64::INFINITY),
+            Token::Bool(b) => Value::Bool(b),
+            Token::List(list) => Value::List(
+                list.into_iter()
+                    .map(Value::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+            Token::Matrix(matrix) => Value::Matrix(matrix),
+            Token::Interval(interval) => Value::Interval(interval),
+            Token::Uncertain(uncertain) => Value::Uncertain(uncertain),
+            Token::Date(date) => Value::Date(date),
+            Token::DateTime(time) => Value::DateTime(time),
+            Token::Duration(duration) => Value::Duration(duration),
+            Token::Money(money) => Value::Money(money),
+            Token::Radix(num, radix, width) => Value::Radix(num, radix, width),
+            Token::Base(radix) => Value::Base(radix),
+            #[cfg(feature = "bigfloat")]
+            Token::Big(num) => Value::Big(num),
+            Token::Lambda(name, body) => Value::Lambda(name, Box::new(Expr::from(body.as_ref()))),
+            token => return Err(format!("Cannot calculate: {token}")),
+        })
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(num) => write!(f, "{num}"),
+            Value::Float(num) => write!(f, "{num}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::List(list) => {
+                write!(f, "[")?;
+                for (i, value) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?
+                    }
+                    write!(f, "{value}")?
+                }
+                write!(f, "]")
+            }
+            Value::Matrix(matrix) => write!(f, "{matrix}"),
+            Value::Interval(interval) => write!(f, "{interval}"),
+            Value::Uncertain(uncertain) => write!(f, "{uncertain}"),
+            Value::Date(date) => write!(f, "{date}"),
+            Value::DateTime(time) => write!(f, "{time}"),
+            Value::Duration(duration) => write!(f, "{duration}"),
+            Value::Money(money) => write!(f, "{money}"),
+            Value::Radix(num, radix, width) => f.write_str(&radix.format(*num, *width)),
+            Value::Base(radix) => write!(f, "{radix}"),
+            #[cfg(feature = "bigfloat")]
+            Value::Big(num) => write!(f, "{num}"),
+            Value::Lambda(name, body) => write!(f, "{name} -> {body}"),
+        }
+    }
+}